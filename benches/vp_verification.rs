@@ -0,0 +1,122 @@
+//! Allocations and time of verifying a large presentation with [`VP`] and with [`BorrowedVP`].
+//!
+//! `cargo bench --bench vp_verification`
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, PublicVerificationDocument, VerificationDocument},
+    proof::FiProof,
+    signer::InMemorySigner,
+    streaming::BorrowedVP,
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+const CREDENTIALS: usize = 300;
+const ROUNDS: usize = 5;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn presentation(alg: Algorithm) -> (String, PublicVerificationDocument, DocumentLoader) {
+    let issuer = VerificationDocument::generate(alg).expect("Key generation failed");
+    let issuer_signer = InMemorySigner::new(&issuer, alg).expect("Signer creation failed");
+    let holder = VerificationDocument::generate(alg).expect("Key generation failed");
+    let holder_signer = InMemorySigner::new(&holder, alg).expect("Signer creation failed");
+
+    let mut vp = VP::new(String::from("urn:uuid:upload"), None);
+    for index in 0..CREDENTIALS {
+        let mut vc = VC::new(
+            format!("urn:uuid:transcript-{}", index),
+            Value::from("did:example:registrar"),
+            Some(Value::from("Transcript")),
+            None,
+            None,
+        );
+        vc.set_credential_subject(json!({
+            "id": format!("did:example:student-{}", index),
+            "courses": (0..20)
+                .map(|course| json!({ "code": format!("CS{}", course), "grade": 3.7 }))
+                .collect::<Vec<Value>>()
+        }));
+        vc.sign(
+            &issuer_signer,
+            FiProof::new(alg, String::from("assertionMethod")),
+        )
+        .expect("Signing failed");
+        vp.add_verifiable_credentials(vc);
+    }
+    vp.sign(
+        &holder_signer,
+        FiProof::new(alg, String::from("authentication")),
+    )
+    .expect("Signing failed");
+
+    let loader = DocumentLoader::new(None).expect("Loader creation failed");
+    loader.add_document(issuer.public_document().unwrap());
+    let json = vp.to_object().expect("Serialization failed").to_string();
+    (json, holder.public_document().unwrap(), loader)
+}
+
+fn measure(name: &str, run: impl Fn() -> bool) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        assert!(run());
+    }
+    let elapsed = start.elapsed() / ROUNDS as u32;
+
+    println!(
+        "{:<24} {:>10} allocations {:>12} bytes {:>10.2?}",
+        name,
+        (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / ROUNDS,
+        (ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes) / ROUNDS,
+        elapsed
+    );
+}
+
+fn main() {
+    for alg in [Algorithm::ES256, Algorithm::EdDSA] {
+        let (json, holder, loader) = presentation(alg);
+        println!(
+            "{} credentials, {} bytes, {}",
+            CREDENTIALS,
+            json.len(),
+            alg.to_str()
+        );
+
+        measure("VP", || {
+            let vp = VP::from(serde_json::from_str(json.as_str()).unwrap()).unwrap();
+            vp.verify(&holder).unwrap() && vp.verify_credentials(&loader).unwrap()
+        });
+        measure("BorrowedVP", || {
+            let vp = BorrowedVP::parse(json.as_str()).unwrap();
+            vp.verify(&holder).unwrap() && vp.verify_credentials(&loader).unwrap()
+        });
+    }
+}
@@ -0,0 +1,83 @@
+//! Signing and verifying many credentials with one key.
+//!
+//! Credentials are signed through a single [`Signer`], so the key may live in an HSM or KMS.
+//! Verification parses the key once per batch, or once per worker thread with the `parallel`
+//! feature, and every credential gets its own result so one failure does not abort the batch.
+
+use fi_digital_signatures::algorithms::Algorithm;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::{
+    document::PublicVerificationDocument,
+    error::FiError,
+    proof::{FiProof, VerifyingKeys},
+    signer::Signer,
+    vc::VC,
+};
+
+/// Signs every credential with a copy of `proof`. With the `parallel` feature the signer is
+/// shared by the worker threads.
+pub fn sign_credentials(
+    signer: &(dyn Signer + Sync),
+    credentials: Vec<VC>,
+    proof: &FiProof,
+) -> Vec<Result<VC, FiError>> {
+    #[cfg(not(feature = "parallel"))]
+    {
+        return credentials
+            .into_iter()
+            .map(|vc| sign_credential(signer, vc, proof))
+            .collect();
+    }
+
+    #[cfg(feature = "parallel")]
+    {
+        return credentials
+            .into_par_iter()
+            .map(|vc| sign_credential(signer, vc, proof))
+            .collect();
+    }
+}
+
+/// Verifies every credential against the document, accepting the algorithms of its key.
+pub fn verify_credentials(
+    doc: &PublicVerificationDocument,
+    credentials: &[VC],
+) -> Vec<Result<bool, FiError>> {
+    verify_credentials_with_algorithms(doc, credentials, doc.get_algorithms().as_slice())
+}
+
+/// Like [`verify_credentials`], but only accepts embedded proofs signed with one of `allowed`.
+pub fn verify_credentials_with_algorithms(
+    doc: &PublicVerificationDocument,
+    credentials: &[VC],
+    allowed: &[Algorithm],
+) -> Vec<Result<bool, FiError>> {
+    #[cfg(not(feature = "parallel"))]
+    {
+        let mut keys = VerifyingKeys::new(doc);
+        return credentials
+            .iter()
+            .map(|vc| vc.verify_with_keys(&mut keys, allowed))
+            .collect();
+    }
+
+    #[cfg(feature = "parallel")]
+    {
+        return credentials
+            .par_iter()
+            .map_init(
+                || VerifyingKeys::new(doc),
+                |keys, vc| vc.verify_with_keys(keys, allowed),
+            )
+            .collect();
+    }
+}
+
+fn sign_credential(signer: &dyn Signer, mut vc: VC, proof: &FiProof) -> Result<VC, FiError> {
+    match vc.sign(signer, proof.clone()) {
+        Err(error) => Err(error),
+        Ok(()) => Ok(vc),
+    }
+}
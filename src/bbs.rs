@@ -0,0 +1,360 @@
+//! Data Integrity proofs with unlinkable selective disclosure, modelled on `bbs-2023`.
+//!
+//! Statements covered by the mandatory pointers are bound to the signature header and revealed
+//! in every derived proof, the others are signed as BBS messages the holder can selectively
+//! disclose.
+//!
+//! Claims are signed as JSON pointer statements and the proof value is not CBOR encoded, so the
+//! proofs are published under this library's own `fi-bbs-2023` cryptosuite rather than
+//! `bbs-2023`.
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_json::{json, Value};
+use zkryptium::{
+    bbsplus::keys::BBSplusPublicKey,
+    schemes::{
+        algorithms::BbsBls12381Sha256,
+        generics::{PoKSignature, Signature},
+    },
+};
+
+use crate::{
+    document::{PublicVerificationDocument, VerificationDocument},
+    error::FiError,
+    keys::{bls_public_key, bls_secret_key},
+    proof::{CredentialProof, DataIntegrityProof},
+    statements::{
+        check_pointers, credential_claims, credential_proof, decode_proof_value,
+        derived_credential, disclose, encode_proof_value, options_hash, proof_bytes,
+        proof_pointers, split_statements, statements_hash, with_required_pointers,
+    },
+    vc::VC,
+};
+
+pub const FI_BBS_2023: &str = "fi-bbs-2023";
+
+/// Adds a base proof signed with the document's BLS12-381 key. Claims under the mandatory
+/// pointers are revealed in every derived proof, in addition to the required VC fields.
+pub fn add_base_proof(
+    vc: &mut VC,
+    doc: &VerificationDocument,
+    purpose: String,
+    mandatory_pointers: &[String],
+) -> Result<(), FiError> {
+    let secret_key = match doc.get_private_key() {
+        None => return Err(FiError::new("Private key is required to sign")),
+        Some(val) => match bls_secret_key(val.as_bytes()) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        },
+    };
+    let public_key = secret_key.public_key();
+
+    let pointers = with_required_pointers(mandatory_pointers);
+
+    let claims = match credential_claims(vc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match check_pointers(&claims, &pointers) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let mut proof = DataIntegrityProof::new(FI_BBS_2023, doc.get_id().clone(), purpose);
+
+    let (header, messages) = match signing_input(&proof, &claims, &pointers) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let signature = match Signature::<BbsBls12381Sha256>::sign(
+        Some(&messages),
+        &secret_key,
+        &public_key,
+        Some(&header),
+    ) {
+        Ok(val) => val,
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Failed to create BBS signature"));
+        }
+    };
+
+    proof.set_proof_value(Some(encode_proof_value(&json!({
+        "bbsSignature": URL_SAFE_NO_PAD.encode(signature.to_bytes()),
+        "publicKey": URL_SAFE_NO_PAD.encode(public_key.to_bytes()),
+        "mandatoryPointers": pointers,
+    }))));
+
+    *vc.get_proof_mut() = Some(CredentialProof::DataIntegrity(proof));
+    return Ok(());
+}
+
+/// Derives a credential revealing the mandatory claims and the claims under the selective
+/// pointers. Each derivation is unlinkable to the base proof and to other derivations. The
+/// presentation header, e.g. a verifier nonce, is bound to the derived proof.
+pub fn derive_proof(
+    vc: &VC,
+    selective_pointers: &[String],
+    presentation_header: Option<&[u8]>,
+) -> Result<VC, FiError> {
+    let proof = match credential_proof(vc, FI_BBS_2023) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let proof_value = match decode_proof_value(proof) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    if proof_value.get("bbsSignature").is_none() {
+        return Err(FiError::new("Proofs can only be derived from a base proof"));
+    }
+
+    let signature = match proof_bytes(&proof_value, "bbsSignature") {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let public_key = match proof_bytes(&proof_value, "publicKey") {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => match bls_public_key(val.as_slice()) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        },
+    };
+
+    let mandatory_pointers = match proof_pointers(&proof_value) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let claims = match credential_claims(vc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match check_pointers(&claims, selective_pointers) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let (header, messages) = match signing_input(proof, &claims, &mandatory_pointers) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let (disclosed, selective_indexes) = disclose(&claims, &mandatory_pointers, selective_pointers);
+
+    let bbs_proof = match PoKSignature::<BbsBls12381Sha256>::proof_gen(
+        &public_key,
+        signature.as_slice(),
+        Some(&header),
+        presentation_header,
+        Some(&messages),
+        Some(&selective_indexes),
+    ) {
+        Ok(val) => val,
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Failed to derive BBS proof"));
+        }
+    };
+
+    let mut derived_proof = proof.clone();
+    derived_proof.set_proof_value(Some(encode_proof_value(&json!({
+        "bbsProof": URL_SAFE_NO_PAD.encode(bbs_proof.to_bytes()),
+        "mandatoryPointers": mandatory_pointers,
+        "selectiveIndexes": selective_indexes,
+        "presentationHeader": presentation_header.map(|val| URL_SAFE_NO_PAD.encode(val)),
+    }))));
+
+    derived_credential(disclosed, derived_proof)
+}
+
+/// Verifies a base or derived `fi-bbs-2023` proof with the document's BLS12-381 public key.
+pub(crate) fn verify_proof(
+    vc: &VC,
+    proof: &DataIntegrityProof,
+    doc: &PublicVerificationDocument,
+) -> Result<bool, FiError> {
+    let public_key = match bls_public_key(doc.get_public_key().as_slice()) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let proof_value = match decode_proof_value(proof) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let mandatory_pointers = match proof_pointers(&proof_value) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let claims = match credential_claims(vc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let (header, messages) = match signing_input(proof, &claims, &mandatory_pointers) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    if proof_value.get("bbsSignature").is_some() {
+        return verify_base_proof(&proof_value, &public_key, &header, &messages);
+    }
+
+    return verify_derived_proof(&proof_value, &public_key, &header, &messages);
+}
+
+fn verify_base_proof(
+    proof_value: &Value,
+    public_key: &BBSplusPublicKey,
+    header: &[u8],
+    messages: &[Vec<u8>],
+) -> Result<bool, FiError> {
+    let signature = match proof_bytes(proof_value, "bbsSignature") {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let signature = match <&[u8; 80]>::try_from(signature.as_slice())
+        .ok()
+        .and_then(|val| Signature::<BbsBls12381Sha256>::from_bytes(val).ok())
+    {
+        None => return Err(FiError::new("Invalid BBS signature")),
+        Some(val) => val,
+    };
+
+    return Ok(signature
+        .verify(public_key, Some(messages), Some(header))
+        .is_ok());
+}
+
+fn verify_derived_proof(
+    proof_value: &Value,
+    public_key: &BBSplusPublicKey,
+    header: &[u8],
+    messages: &[Vec<u8>],
+) -> Result<bool, FiError> {
+    let bbs_proof = match proof_bytes(proof_value, "bbsProof") {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => match PoKSignature::<BbsBls12381Sha256>::from_bytes(val.as_slice()) {
+            Ok(val) => val,
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Invalid BBS proof"));
+            }
+        },
+    };
+
+    let selective_indexes: Vec<usize> = match proof_value["selectiveIndexes"].as_array() {
+        None => return Err(FiError::new("Derived proof has no selective indexes")),
+        Some(val) => val
+            .iter()
+            .filter_map(|val| val.as_u64().map(|val| val as usize))
+            .collect(),
+    };
+
+    // Every disclosed statement must have been signed, in the order it was signed in
+    if selective_indexes.len() != messages.len()
+        || selective_indexes.windows(2).any(|val| val[0] >= val[1])
+    {
+        return Ok(false);
+    }
+
+    let presentation_header = match proof_value["presentationHeader"].as_str() {
+        None => None,
+        Some(val) => match URL_SAFE_NO_PAD.decode(val) {
+            Ok(val) => Some(val),
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Presentation header is not base64url encoded"));
+            }
+        },
+    };
+
+    return Ok(bbs_proof
+        .proof_verify(
+            public_key,
+            Some(messages),
+            Some(&selective_indexes),
+            Some(header),
+            presentation_header.as_deref(),
+        )
+        .is_ok());
+}
+
+/// Signature header binding the proof options and mandatory statements, and the remaining
+/// statements as BBS messages.
+fn signing_input(
+    proof: &DataIntegrityProof,
+    claims: &BTreeMap<String, Value>,
+    mandatory_pointers: &[String],
+) -> Result<(Vec<u8>, Vec<Vec<u8>>), FiError> {
+    let mut header = match options_hash(proof) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let statements = split_statements(claims, mandatory_pointers);
+    header.extend(statements_hash(&statements.mandatory));
+
+    let messages = statements
+        .selective
+        .into_iter()
+        .map(|val| val.into_bytes())
+        .collect();
+
+    return Ok((header, messages));
+}
@@ -0,0 +1,677 @@
+//! Issues, presents, verifies and inspects verifiable credentials. Results are printed as JSON
+//! and the exit code is 0 on success, 1 when verification fails and 2 for invalid input.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use chrono::Utc;
+use clap::{Parser, Subcommand, ValueEnum};
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocResolver, DocumentLoader, PublicVerificationDocument, VerificationDocument},
+    enveloped::{Envelope, EnvelopedCredential, ENVELOPED_VC_TYPE},
+    error::FiError,
+    jose::{Jwt, SdJwt},
+    oauth::random_urn,
+    proof::FiProof,
+    signer::{InMemorySigner, Signer},
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+const EXIT_NOT_VERIFIED: u8 = 1;
+const EXIT_INVALID_INPUT: u8 = 2;
+
+#[derive(Parser)]
+#[command(
+    name = "fi-vc",
+    version,
+    about = "Issue, present and verify verifiable credentials"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a key pair and print it as a key file
+    Keygen {
+        #[arg(long, default_value = "EdDSA")]
+        alg: String,
+        /// Verification method id, defaults to the did:key of the public key
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Sign a credential template
+    Issue {
+        /// Credential JSON without a proof, `-` reads stdin
+        template: PathBuf,
+        #[command(flatten)]
+        signing: SigningArgs,
+        #[arg(long, default_value = "assertionMethod")]
+        purpose: String,
+    },
+    /// Bundle credentials into a signed presentation
+    Present {
+        /// Signed JSON credentials
+        #[arg(required = true)]
+        credentials: Vec<PathBuf>,
+        #[command(flatten)]
+        signing: SigningArgs,
+        /// Presentation id, defaults to a random urn:uuid
+        #[arg(long)]
+        id: Option<String>,
+        /// Defaults to the DID of the signing key
+        #[arg(long)]
+        holder: Option<String>,
+        #[arg(long, default_value = "authentication")]
+        purpose: String,
+    },
+    /// Verify a credential or presentation given as JSON, JWT or SD-JWT
+    Verify {
+        /// `-` reads stdin
+        input: PathBuf,
+        /// DID document whose verification methods are trusted, can be repeated
+        #[arg(long = "did-doc")]
+        did_docs: Vec<PathBuf>,
+        /// Key file or verification method whose public key is trusted, can be repeated
+        #[arg(long = "key")]
+        keys: Vec<PathBuf>,
+        /// Resolve any did:key verification method from the key encoded in the DID
+        #[arg(long)]
+        resolve_did_key: bool,
+    },
+    /// Pretty-print a credential or presentation and decode JWTs and SD-JWTs
+    Inspect {
+        /// `-` reads stdin
+        input: PathBuf,
+    },
+}
+
+#[derive(clap::Args)]
+struct SigningArgs {
+    /// Key file from `keygen` or a private key PEM
+    #[arg(long)]
+    key: PathBuf,
+    /// Verification method id, required for PEM keys
+    #[arg(long)]
+    kid: Option<String>,
+    /// Defaults to the key file's algorithm or the first one of the key type
+    #[arg(long)]
+    alg: Option<String>,
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Embedded proof
+    Json,
+    /// Compact JWS with the document as payload
+    Jwt,
+}
+
+/// Credential or presentation as read from a file.
+enum Input {
+    Json(Value),
+    Jwt(String),
+    SdJwt(String),
+}
+
+/// Resolves `did:key` verification methods from the key encoded in the DID.
+struct DidKeyResolver;
+
+impl DocResolver for DidKeyResolver {
+    fn resolve(&self, url: &str) -> Option<PublicVerificationDocument> {
+        let multikey = url.strip_prefix("did:key:")?.split('#').next()?;
+        PublicVerificationDocument::from_multikey(String::from(url), multikey).ok()
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Keygen { alg, id } => keygen(alg.as_str(), id),
+        Command::Issue {
+            template,
+            signing,
+            purpose,
+        } => issue(&template, &signing, purpose),
+        Command::Present {
+            credentials,
+            signing,
+            id,
+            holder,
+            purpose,
+        } => present(&credentials, &signing, id, holder, purpose),
+        Command::Verify {
+            input,
+            did_docs,
+            keys,
+            resolve_did_key,
+        } => verify(&input, &did_docs, &keys, resolve_did_key),
+        Command::Inspect { input } => inspect(&input),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            print_json(&json!({ "error": error.to_string() }));
+            ExitCode::from(EXIT_INVALID_INPUT)
+        }
+    }
+}
+
+fn keygen(alg: &str, id: Option<String>) -> Result<ExitCode, FiError> {
+    let alg = parse_algorithm(alg)?;
+
+    let mut doc = VerificationDocument::generate(alg)?;
+    if let Some(id) = id {
+        *doc.get_id_mut() = id;
+    }
+
+    let public_doc = doc.public_document()?;
+    print_json(&json!({
+        "id": doc.get_id(),
+        "algorithm": alg.to_str(),
+        "publicKeyMultibase": public_doc.to_multikey()?,
+        "publicKeyJwk": doc.to_public_jwk()?,
+        "privateKeyJwk": doc.to_private_jwk()?,
+    }));
+
+    return Ok(ExitCode::SUCCESS);
+}
+
+fn issue(template: &Path, signing: &SigningArgs, purpose: String) -> Result<ExitCode, FiError> {
+    let signer = read_signer(signing)?;
+
+    let mut template = match read_input(template)? {
+        Input::Json(val) if val.is_object() => val,
+        _ => return Err(FiError::new("Template must be a JSON object")),
+    };
+
+    let template_fields = template.as_object_mut().unwrap();
+    template_fields.remove("proof");
+    if !template_fields.contains_key("issuer") {
+        template_fields.insert(
+            String::from("issuer"),
+            Value::from(controller(signer.verification_method())),
+        );
+    }
+    if !template_fields.contains_key("validFrom") {
+        template_fields.insert(
+            String::from("validFrom"),
+            Value::from(Utc::now().to_rfc3339()),
+        );
+    }
+
+    let mut vc = VC::from(template)?;
+
+    match signing.format {
+        Format::Jwt => print_line(vc.to_jwt(&signer)?.as_str()),
+        Format::Json => {
+            vc.sign(&signer, FiProof::new(signer.algorithm(), purpose))?;
+            print_json(&vc.to_object()?);
+        }
+    };
+
+    return Ok(ExitCode::SUCCESS);
+}
+
+fn present(
+    credentials: &[PathBuf],
+    signing: &SigningArgs,
+    id: Option<String>,
+    holder: Option<String>,
+    purpose: String,
+) -> Result<ExitCode, FiError> {
+    let signer = read_signer(signing)?;
+
+    let holder = holder.unwrap_or_else(|| controller(signer.verification_method()));
+    let mut vp = VP::new(id.unwrap_or_else(random_urn), Some(holder));
+
+    for path in credentials.iter() {
+        match read_input(path)? {
+            Input::Json(val) => vp.add_verifiable_credentials(VC::from(val)?),
+            _ => {
+                return Err(FiError::new(
+                    "Only credentials with an embedded proof can be presented",
+                ))
+            }
+        };
+    }
+
+    match signing.format {
+        Format::Jwt => print_line(vp.to_jwt(&signer)?.as_str()),
+        Format::Json => {
+            vp.sign(&signer, FiProof::new(signer.algorithm(), purpose))?;
+            print_json(&vp.to_object()?);
+        }
+    };
+
+    return Ok(ExitCode::SUCCESS);
+}
+
+fn verify(
+    input: &Path,
+    did_docs: &[PathBuf],
+    keys: &[PathBuf],
+    resolve_did_key: bool,
+) -> Result<ExitCode, FiError> {
+    let mut loader = DocumentLoader::new(None)?;
+    if resolve_did_key {
+        loader.add_resolver(Box::new(DidKeyResolver));
+    }
+
+    for path in did_docs.iter() {
+        loader.add_did_document(&read_json(path)?)?;
+    }
+
+    for path in keys.iter() {
+        let method = read_json(path)?;
+        let id = match method["id"].as_str() {
+            None => return Err(FiError::new("Key file has no id")),
+            Some(val) => String::from(val),
+        };
+        loader.add_document(PublicVerificationDocument::from_verification_method(
+            &method,
+            id.as_str(),
+        )?);
+    }
+
+    let result = match read_input(input)? {
+        Input::Json(val) => verify_json(&val, &loader),
+        Input::Jwt(token) => verify_jwt(&Jwt::decode(token.as_str())?, None, &loader),
+        Input::SdJwt(token) => {
+            let sd_jwt = SdJwt::decode(token.as_str())?;
+            verify_jwt(sd_jwt.get_jwt(), Some(&sd_jwt), &loader)
+        }
+    };
+
+    print_json(&result);
+
+    if result["verified"] == Value::Bool(true) {
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    return Ok(ExitCode::from(EXIT_NOT_VERIFIED));
+}
+
+fn verify_json(value: &Value, loader: &DocumentLoader) -> Value {
+    if !is_presentation(value) {
+        return verification_result(
+            "json",
+            "VerifiableCredential",
+            verify_credential(value, loader),
+        );
+    }
+
+    let mut result = verification_result(
+        "json",
+        "VerifiablePresentation",
+        VP::from(value.clone()).and_then(|vp| {
+            let verification_method = proof_verification_method(
+                vp.get_proof()
+                    .as_ref()
+                    .map(|val| val.get_verification_method()),
+            )?;
+            let doc = resolve(loader, verification_method.as_str())?;
+            Ok((verification_method, vp.verify(&doc)?))
+        }),
+    );
+    add_credential_results(&mut result, value, loader);
+
+    return result;
+}
+
+fn verify_jwt(jwt: &Jwt, sd_jwt: Option<&SdJwt>, loader: &DocumentLoader) -> Value {
+    let format = if sd_jwt.is_some() { "sd-jwt" } else { "jwt" };
+
+    let payload = match sd_jwt {
+        None => Ok(jwt.get_payload().clone()),
+        Some(val) => val.get_claims(),
+    };
+
+    let presentation = payload.as_ref().map(is_presentation).unwrap_or(false);
+    let document_type = match presentation {
+        true => "VerifiablePresentation",
+        false => "VerifiableCredential",
+    };
+
+    let mut result = verification_result(
+        format,
+        document_type,
+        payload.and_then(|claims| {
+            let verification_method = match jwt.get_kid() {
+                None => return Err(FiError::new("JWT header has no kid")),
+                Some(val) => String::from(val),
+            };
+            check_controller(&claims, verification_method.as_str())?;
+            let doc = resolve(loader, verification_method.as_str())?;
+            Ok((verification_method, jwt.verify(&doc)?))
+        }),
+    );
+
+    if presentation {
+        add_credential_results(&mut result, jwt.get_payload(), loader);
+    }
+
+    return result;
+}
+
+fn verify_credential(value: &Value, loader: &DocumentLoader) -> Result<(String, bool), FiError> {
+    let vc = VC::from(value.clone())?;
+    let verification_method = proof_verification_method(
+        vc.get_proof()
+            .as_ref()
+            .map(|val| val.get_verification_method()),
+    )?;
+    check_controller(value, verification_method.as_str())?;
+    let doc = resolve(loader, verification_method.as_str())?;
+    Ok((verification_method, vc.verify(&doc)?))
+}
+
+fn credential_result(credential: &Value, loader: &DocumentLoader) -> Value {
+    if credential["type"] != ENVELOPED_VC_TYPE {
+        return verification_result(
+            "json",
+            "VerifiableCredential",
+            verify_credential(credential, loader),
+        );
+    }
+
+    let enveloped: Result<EnvelopedCredential, FiError> =
+        serde_json::from_value(credential.clone())
+            .map_err(|error| FiError::new(&error.to_string()));
+    let format = match enveloped.as_ref().map(|val| val.decode()) {
+        Ok(Ok(Envelope::SdJwt(_))) => "sd-jwt",
+        Ok(Ok(Envelope::Cose(_))) => "cose",
+        _ => "jwt",
+    };
+
+    verification_result(
+        format,
+        "VerifiableCredential",
+        enveloped.and_then(|enveloped| {
+            let verification_method = enveloped.get_verification_method()?;
+            check_controller(
+                &enveloped.get_credential()?.to_object()?,
+                verification_method.as_str(),
+            )?;
+            let doc = resolve(loader, verification_method.as_str())?;
+            Ok((verification_method, enveloped.verify(&doc)?))
+        }),
+    )
+}
+
+/// Verifies the credentials of a presentation, which is only verified if all of them are.
+fn add_credential_results(result: &mut Value, presentation: &Value, loader: &DocumentLoader) {
+    let credentials = match presentation["verifiableCredential"].as_array() {
+        None => Vec::new(),
+        Some(val) => val
+            .iter()
+            .map(|credential| credential_result(credential, loader))
+            .collect(),
+    };
+
+    if credentials
+        .iter()
+        .any(|val| val["verified"] != Value::Bool(true))
+    {
+        result["verified"] = Value::Bool(false);
+    }
+
+    result["credentials"] = Value::Array(credentials);
+}
+
+fn verification_result(
+    format: &str,
+    document_type: &str,
+    result: Result<(String, bool), FiError>,
+) -> Value {
+    let mut value = json!({
+        "verified": false,
+        "format": format,
+        "type": document_type,
+    });
+
+    match result {
+        Ok((verification_method, verified)) => {
+            value["verified"] = Value::Bool(verified);
+            value["verificationMethod"] = Value::from(verification_method);
+            if !verified {
+                value["error"] = Value::from("Signature does not match");
+            }
+        }
+        Err(error) => value["error"] = Value::from(error.to_string()),
+    };
+
+    return value;
+}
+
+fn inspect(input: &Path) -> Result<ExitCode, FiError> {
+    let value = match read_input(input)? {
+        Input::Json(val) => json!({ "format": "json", "document": val }),
+        Input::Jwt(token) => {
+            let jwt = Jwt::decode(token.as_str())?;
+            json!({
+                "format": "jwt",
+                "header": jwt.get_header(),
+                "payload": jwt.get_payload(),
+            })
+        }
+        Input::SdJwt(token) => {
+            let sd_jwt = SdJwt::decode(token.as_str())?;
+            let disclosures: Vec<Value> = sd_jwt
+                .get_disclosures()
+                .iter()
+                .map(|disclosure| {
+                    json!({
+                        "digest": disclosure.get_digest(),
+                        "salt": disclosure.get_salt(),
+                        "name": disclosure.get_name(),
+                        "value": disclosure.get_value(),
+                    })
+                })
+                .collect();
+            let key_binding = sd_jwt.get_key_binding_jwt().as_ref().map(|jwt| {
+                json!({
+                    "header": jwt.get_header(),
+                    "payload": jwt.get_payload(),
+                })
+            });
+
+            json!({
+                "format": "sd-jwt",
+                "header": sd_jwt.get_jwt().get_header(),
+                "payload": sd_jwt.get_jwt().get_payload(),
+                "disclosures": disclosures,
+                "claims": sd_jwt.get_claims()?,
+                "keyBinding": key_binding,
+            })
+        }
+    };
+
+    print_json(&value);
+    return Ok(ExitCode::SUCCESS);
+}
+
+fn read_signer(signing: &SigningArgs) -> Result<InMemorySigner, FiError> {
+    let content = read_file(&signing.key)?;
+
+    let (mut doc, file_alg) = if content.trim_start().starts_with("-----BEGIN") {
+        let kid = match signing.kid.as_ref() {
+            None => return Err(FiError::new("--kid is required for PEM keys")),
+            Some(val) => val.clone(),
+        };
+        (VerificationDocument::from_pem(kid, content.as_str())?, None)
+    } else {
+        let key_file = parse_json(content.as_str())?;
+        let id = match key_file["id"].as_str() {
+            None => String::new(),
+            Some(val) => String::from(val),
+        };
+        let doc = VerificationDocument::from_jwk(id, &key_file["privateKeyJwk"])?;
+        (doc, key_file["algorithm"].as_str().map(String::from))
+    };
+
+    if let Some(kid) = signing.kid.as_ref() {
+        *doc.get_id_mut() = kid.clone();
+    }
+
+    if doc.get_id().is_empty() {
+        return Err(FiError::new("The key has no id, pass --kid"));
+    }
+
+    let alg = match (signing.alg.as_ref(), file_alg.as_ref(), doc.get_key_type()) {
+        (Some(val), _, _) | (None, Some(val), _) => parse_algorithm(val.as_str())?,
+        (None, None, Some(key_type)) => match key_type.get_algorithms().first() {
+            None => return Err(FiError::new("The key cannot sign JWS or FiProof proofs")),
+            Some(val) => *val,
+        },
+        (None, None, None) => return Err(FiError::new("The key type is unknown, pass --alg")),
+    };
+
+    InMemorySigner::new(&doc, alg)
+}
+
+fn read_input(path: &Path) -> Result<Input, FiError> {
+    let content = read_file(path)?;
+    let content = content.trim();
+
+    if content.starts_with('{') {
+        return Ok(Input::Json(parse_json(content)?));
+    }
+
+    // A token may also be stored as a JSON string
+    let token = match content.starts_with('"') {
+        false => String::from(content),
+        true => match parse_json(content)?.as_str() {
+            None => return Err(FiError::new("Input is not a JWT")),
+            Some(val) => String::from(val),
+        },
+    };
+
+    if token.contains('~') {
+        return Ok(Input::SdJwt(token));
+    }
+
+    return Ok(Input::Jwt(token));
+}
+
+fn read_json(path: &Path) -> Result<Value, FiError> {
+    parse_json(read_file(path)?.as_str())
+}
+
+fn read_file(path: &Path) -> Result<String, FiError> {
+    let mut content = String::new();
+
+    let result = match path.to_str() {
+        Some("-") => io::stdin().read_to_string(&mut content).map(|_| ()),
+        _ => fs::read_to_string(path).map(|val| content = val),
+    };
+
+    match result {
+        Ok(_) => Ok(content),
+        Err(error) => Err(FiError::new(
+            format!("Cannot read {}: {}", path.display(), error).as_str(),
+        )),
+    }
+}
+
+fn parse_json(content: &str) -> Result<Value, FiError> {
+    match serde_json::from_str(content) {
+        Ok(val) => Ok(val),
+        Err(error) => Err(FiError::new(format!("Invalid JSON: {}", error).as_str())),
+    }
+}
+
+fn parse_algorithm(alg: &str) -> Result<Algorithm, FiError> {
+    match Algorithm::from_str(alg) {
+        None => Err(FiError::new(format!("Unknown algorithm {}", alg).as_str())),
+        Some(val) => Ok(val),
+    }
+}
+
+fn resolve(loader: &DocumentLoader, url: &str) -> Result<PublicVerificationDocument, FiError> {
+    match loader.get_verification_document(url) {
+        None => Err(FiError::new(
+            format!("Verification method {} could not be resolved", url).as_str(),
+        )),
+        Some(val) => Ok(val),
+    }
+}
+
+fn proof_verification_method(
+    verification_method: Option<&Option<String>>,
+) -> Result<String, FiError> {
+    match verification_method {
+        None => Err(FiError::new("No proof was found")),
+        Some(None) => Err(FiError::new("The proof has no verification method")),
+        Some(Some(val)) => Ok(val.clone()),
+    }
+}
+
+fn is_presentation(value: &Value) -> bool {
+    match &value["type"] {
+        Value::String(val) => val == "VerifiablePresentation",
+        Value::Array(val) => val.iter().any(|item| item == "VerifiablePresentation"),
+        _ => false,
+    }
+}
+
+/// DID of a verification method id.
+fn controller(verification_method: &str) -> String {
+    String::from(verification_method.split('#').next().unwrap_or_default())
+}
+
+/// Fails unless the verification method belongs to the credential's issuer, or to the
+/// presentation's holder when it names one, so a document cannot vouch for itself with a key
+/// of its own choosing.
+fn check_controller(document: &Value, verification_method: &str) -> Result<(), FiError> {
+    let (field, expected) = match is_presentation(document) {
+        true => ("holder", &document["holder"]),
+        false => match &document["issuer"] {
+            Value::Null => ("issuer", &document["iss"]),
+            val => ("issuer", val),
+        },
+    };
+
+    let expected = match expected {
+        Value::Null if field == "holder" => return Ok(()),
+        Value::String(val) => Some(val.as_str()),
+        val => val["id"].as_str(),
+    };
+
+    if expected != Some(controller(verification_method).as_str()) {
+        return Err(FiError::new(
+            format!(
+                "Verification method {} does not belong to the {}",
+                verification_method, field
+            )
+            .as_str(),
+        ));
+    }
+
+    return Ok(());
+}
+
+fn print_json(value: &Value) {
+    print_line(
+        serde_json::to_string_pretty(value)
+            .unwrap_or_default()
+            .as_str(),
+    );
+}
+
+/// Ignores write errors, so piping into e.g. `head` does not panic.
+fn print_line(line: &str) {
+    let _ = writeln!(io::stdout(), "{}", line);
+}
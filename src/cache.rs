@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::document::PublicVerificationDocument;
+
+/// Bounds for the documents a [`DocumentLoader`](crate::document::DocumentLoader) keeps after
+/// resolving them. Documents the loader is seeded with are pinned and never evicted.
+#[derive(Clone, Debug)]
+pub struct CacheOptions {
+    ttl: Option<Duration>,
+    negative_ttl: Option<Duration>,
+    max_entries: Option<usize>,
+}
+
+impl CacheOptions {
+    /// `ttl` of `None` keeps resolved documents until evicted, `negative_ttl` of `None` disables
+    /// caching of failed lookups and `max_entries` of `None` leaves the cache unbounded.
+    pub fn new(
+        ttl: Option<Duration>,
+        negative_ttl: Option<Duration>,
+        max_entries: Option<usize>,
+    ) -> Self {
+        return CacheOptions {
+            ttl,
+            negative_ttl,
+            max_entries,
+        };
+    }
+
+    pub fn get_ttl(&self) -> &Option<Duration> {
+        &self.ttl
+    }
+
+    pub fn get_negative_ttl(&self) -> &Option<Duration> {
+        &self.negative_ttl
+    }
+
+    pub fn get_max_entries(&self) -> &Option<usize> {
+        &self.max_entries
+    }
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        CacheOptions::new(
+            Some(Duration::hours(1)),
+            Some(Duration::minutes(1)),
+            Some(1000),
+        )
+    }
+}
+
+/// Result of a cache lookup.
+pub(crate) enum CacheLookup {
+    Found(PublicVerificationDocument),
+    NotFound,
+    Miss,
+}
+
+struct CacheEntry {
+    doc: Option<PublicVerificationDocument>,
+    expires_at: Option<DateTime<Utc>>,
+    last_used: u64,
+}
+
+pub(crate) struct DocumentCache {
+    options: CacheOptions,
+    pinned: HashMap<String, PublicVerificationDocument>,
+    entries: HashMap<String, CacheEntry>,
+    clock: u64,
+}
+
+impl DocumentCache {
+    pub(crate) fn new(
+        pinned: HashMap<String, PublicVerificationDocument>,
+        options: CacheOptions,
+    ) -> Self {
+        return DocumentCache {
+            options,
+            pinned,
+            entries: HashMap::new(),
+            clock: 0,
+        };
+    }
+
+    pub(crate) fn get(&mut self, url: &str) -> CacheLookup {
+        if let Some(doc) = self.pinned.get(url) {
+            return CacheLookup::Found(doc.clone());
+        }
+
+        let now = Utc::now();
+        let expired = match self.entries.get(url) {
+            None => return CacheLookup::Miss,
+            Some(entry) => is_expired(entry, now),
+        };
+
+        if expired {
+            self.entries.remove(url);
+            return CacheLookup::Miss;
+        }
+
+        self.clock += 1;
+        let entry = self.entries.get_mut(url).unwrap();
+        entry.last_used = self.clock;
+
+        match entry.doc.as_ref() {
+            None => CacheLookup::NotFound,
+            Some(doc) => CacheLookup::Found(doc.clone()),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, url: &str, doc: Option<PublicVerificationDocument>) {
+        let ttl = match doc {
+            Some(_) => self.options.ttl,
+            None => match self.options.negative_ttl {
+                None => return,
+                Some(val) => Some(val),
+            },
+        };
+
+        if let Some(max_entries) = self.options.max_entries {
+            if max_entries == 0 {
+                return;
+            }
+
+            if !self.entries.contains_key(url) && self.entries.len() >= max_entries {
+                self.evict(max_entries - 1);
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(
+            String::from(url),
+            CacheEntry {
+                doc,
+                expires_at: ttl.map(|val| Utc::now() + val),
+                last_used: self.clock,
+            },
+        );
+    }
+
+    pub(crate) fn pin(&mut self, doc: PublicVerificationDocument) {
+        self.entries.remove(doc.get_id());
+        self.pinned.insert(doc.get_id().clone(), doc);
+    }
+
+    pub(crate) fn invalidate(&mut self, url: &str) {
+        self.pinned.remove(url);
+        self.entries.remove(url);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.pinned.len() + self.entries.len()
+    }
+
+    /// Drops expired entries, then the least recently used ones until at most `size` are left.
+    fn evict(&mut self, size: usize) {
+        let now = Utc::now();
+        self.entries.retain(|_url, entry| !is_expired(entry, now));
+
+        while self.entries.len() > size {
+            let oldest = match self
+                .entries
+                .iter()
+                .min_by_key(|(_url, entry)| entry.last_used)
+            {
+                None => return,
+                Some((url, _entry)) => url.clone(),
+            };
+
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+fn is_expired(entry: &CacheEntry, now: DateTime<Utc>) -> bool {
+    match entry.expires_at {
+        None => false,
+        Some(val) => val <= now,
+    }
+}
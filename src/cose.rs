@@ -0,0 +1,393 @@
+//! COSE_Sign1 messages (RFC 9052) and the CBOR helpers shared by the CBOR based formats.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use fi_digital_signatures::algorithms::Algorithm;
+
+use crate::{document::PublicVerificationDocument, error::FiError, keys, signer::Signer};
+
+/// CBOR tag of a COSE_Sign1 message.
+pub const COSE_SIGN1_TAG: u64 = 18;
+/// CBOR tag of an embedded CBOR data item (`bstr .cbor`).
+pub const ENCODED_CBOR_TAG: u64 = 24;
+
+/// `typ` of a credential secured with COSE, the payload is the credential JSON.
+pub const VC_COSE_TYPE: &str = "application/vc+cose";
+/// `typ` of a presentation secured with COSE, the payload is the presentation JSON.
+pub const VP_COSE_TYPE: &str = "application/vp+cose";
+pub const VC_CONTENT_TYPE: &str = "application/vc";
+pub const VP_CONTENT_TYPE: &str = "application/vp";
+
+const HEADER_ALGORITHM: i128 = 1;
+const HEADER_CONTENT_TYPE: i128 = 3;
+const HEADER_KID: i128 = 4;
+const HEADER_TYPE: i128 = 16;
+const HEADER_X5CHAIN: i128 = 33;
+
+/// COSE_Sign1 message, keeping the encoded protected header the signature covers. The
+/// unprotected header is not signed, so the algorithm, content type, type and certificate
+/// chain are only read from the protected one.
+#[derive(Clone, Debug)]
+pub struct CoseSign1 {
+    protected: Vec<u8>,
+    protected_header: Value,
+    unprotected_header: Value,
+    payload: Option<Vec<u8>>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    /// Signs the payload as a tagged COSE_Sign1 message, naming the signer's verification
+    /// method as the `kid`. Headers are encoded deterministically, with sorted labels.
+    pub fn sign(
+        payload: &[u8],
+        typ: &str,
+        content_type: &str,
+        signer: &dyn Signer,
+    ) -> Result<Vec<u8>, FiError> {
+        let alg = match algorithm_label(signer.algorithm()) {
+            None => return Err(FiError::new("Algorithm is not supported by COSE")),
+            Some(val) => val,
+        };
+
+        let protected = match encode_cbor(&Value::Map(vec![
+            (Value::from(HEADER_ALGORITHM as i64), Value::from(alg)),
+            (
+                Value::from(HEADER_CONTENT_TYPE as i64),
+                Value::Text(String::from(content_type)),
+            ),
+            (
+                Value::from(HEADER_KID as i64),
+                Value::Bytes(signer.verification_method().as_bytes().to_vec()),
+            ),
+            (
+                Value::from(HEADER_TYPE as i64),
+                Value::Text(String::from(typ)),
+            ),
+        ])) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let to_be_signed = match signature_structure(&protected, payload) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let signature = match signer.sign(to_be_signed.as_slice()) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        encode_cbor(&Value::Tag(
+            COSE_SIGN1_TAG,
+            Box::new(Value::Array(vec![
+                Value::Bytes(protected),
+                Value::Map(Vec::new()),
+                Value::Bytes(payload.to_vec()),
+                Value::Bytes(signature),
+            ])),
+        ))
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<CoseSign1, FiError> {
+        match decode_cbor(bytes) {
+            Err(error) => Err(error),
+            Ok(val) => CoseSign1::from_cbor(val),
+        }
+    }
+
+    pub(crate) fn from_cbor(value: Value) -> Result<CoseSign1, FiError> {
+        let value = match value {
+            Value::Tag(COSE_SIGN1_TAG, val) => *val,
+            val => val,
+        };
+
+        let parts = match value {
+            Value::Array(val) if val.len() == 4 => val,
+            _ => return Err(FiError::new("COSE_Sign1 must be an array of four")),
+        };
+
+        let protected = match &parts[0] {
+            Value::Bytes(val) => val.clone(),
+            _ => return Err(FiError::new("COSE protected header must be a byte string")),
+        };
+
+        // An empty protected header is encoded as a zero length byte string
+        let protected_header = match protected.is_empty() {
+            true => Value::Map(Vec::new()),
+            false => match decode_cbor(protected.as_slice()) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        };
+
+        let payload = match &parts[2] {
+            Value::Bytes(val) => Some(val.clone()),
+            Value::Null => None,
+            _ => return Err(FiError::new("COSE payload must be a byte string or nil")),
+        };
+
+        let signature = match &parts[3] {
+            Value::Bytes(val) => val.clone(),
+            _ => return Err(FiError::new("COSE signature must be a byte string")),
+        };
+
+        return Ok(CoseSign1 {
+            protected,
+            protected_header,
+            unprotected_header: parts[1].clone(),
+            payload,
+            signature,
+        });
+    }
+
+    pub fn get_algorithm(&self) -> Result<Algorithm, FiError> {
+        match map_label(&self.protected_header, HEADER_ALGORITHM).and_then(integer) {
+            Some(-7) => Ok(Algorithm::ES256),
+            Some(-35) => Ok(Algorithm::ES384),
+            Some(-36) => Ok(Algorithm::ES512),
+            Some(-8) => Ok(Algorithm::EdDSA),
+            _ => Err(FiError::new("COSE algorithm is missing or not supported")),
+        }
+    }
+
+    pub fn get_type(&self) -> Option<&str> {
+        map_label(&self.protected_header, HEADER_TYPE).and_then(|val| val.as_text())
+    }
+
+    pub fn get_content_type(&self) -> Option<&str> {
+        map_label(&self.protected_header, HEADER_CONTENT_TYPE).and_then(|val| val.as_text())
+    }
+
+    pub fn get_kid(&self) -> Option<&[u8]> {
+        self.get_header(HEADER_KID)
+            .and_then(|val| val.as_bytes())
+            .map(|val| val.as_slice())
+    }
+
+    /// DER encoded certificates of the protected `x5chain` header, the signer's certificate
+    /// first.
+    pub fn get_x5chain(&self) -> Vec<Vec<u8>> {
+        certificates(map_label(&self.protected_header, HEADER_X5CHAIN))
+    }
+
+    /// Certificates of the unprotected `x5chain` header, where ISO 18013-5 `issuerAuth` carries
+    /// them. They are not signed and must be validated against a trust anchor before use.
+    pub fn get_unprotected_x5chain(&self) -> Vec<Vec<u8>> {
+        certificates(map_label(&self.unprotected_header, HEADER_X5CHAIN))
+    }
+
+    pub fn get_payload(&self) -> &Option<Vec<u8>> {
+        &self.payload
+    }
+
+    pub fn get_signature(&self) -> &Vec<u8> {
+        &self.signature
+    }
+
+    /// Verifies the signature with the document's public key. A detached payload is passed
+    /// as `payload`, otherwise the embedded one is used.
+    pub fn verify(
+        &self,
+        doc: &PublicVerificationDocument,
+        payload: Option<&[u8]>,
+    ) -> Result<bool, FiError> {
+        let alg = match self.get_algorithm() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        match doc.key_type() {
+            Some(val) if val.get_algorithms().contains(&alg) => {}
+            _ => {
+                return Err(FiError::new("The key does not match the COSE algorithm"));
+            }
+        };
+
+        let payload = match payload.or(self.payload.as_deref()) {
+            None => return Err(FiError::new("COSE payload is detached")),
+            Some(val) => val,
+        };
+
+        let to_be_signed = match signature_structure(&self.protected, payload) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        keys::verify_raw(
+            alg,
+            doc.get_public_key().as_slice(),
+            to_be_signed.as_slice(),
+            self.signature.as_slice(),
+        )
+    }
+
+    /// Protected header parameters take precedence over unprotected ones. Only the `kid` hint
+    /// is read this way, everything the verifier relies on must be in the protected header.
+    fn get_header(&self, label: i128) -> Option<&Value> {
+        map_label(&self.protected_header, label).or(map_label(&self.unprotected_header, label))
+    }
+}
+
+/// JSON payload of a COSE secured credential or presentation, once its signature is verified.
+pub(crate) fn verified_json_payload(
+    bytes: &[u8],
+    content_type: &str,
+    doc: &PublicVerificationDocument,
+) -> Result<serde_json::Value, FiError> {
+    let message = match CoseSign1::decode(bytes) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    if message.get_content_type() != Some(content_type) {
+        return Err(FiError::new(
+            format!("COSE content type must be {}", content_type).as_str(),
+        ));
+    }
+
+    match message.verify(doc, None) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(false) => return Err(FiError::new("COSE signature is invalid")),
+        Ok(true) => {}
+    };
+
+    match serde_json::from_slice(message.payload.as_deref().unwrap_or_default()) {
+        Ok(val) => Ok(val),
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("COSE payload is not valid JSON"));
+        }
+    }
+}
+
+fn certificates(value: Option<&Value>) -> Vec<Vec<u8>> {
+    match value {
+        Some(Value::Bytes(val)) => vec![val.clone()],
+        Some(Value::Array(val)) => val
+            .iter()
+            .filter_map(|item| item.as_bytes().cloned())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn signature_structure(protected: &[u8], payload: &[u8]) -> Result<Vec<u8>, FiError> {
+    encode_cbor(&Value::Array(vec![
+        Value::Text(String::from("Signature1")),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(Vec::new()),
+        Value::Bytes(payload.to_vec()),
+    ]))
+}
+
+fn algorithm_label(alg: Algorithm) -> Option<i64> {
+    match alg {
+        Algorithm::ES256 => Some(-7),
+        Algorithm::ES384 => Some(-35),
+        Algorithm::ES512 => Some(-36),
+        Algorithm::EdDSA => Some(-8),
+        _ => None,
+    }
+}
+
+pub(crate) fn decode_cbor(bytes: &[u8]) -> Result<Value, FiError> {
+    match ciborium::from_reader(bytes) {
+        Ok(val) => Ok(val),
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Value is not valid CBOR"));
+        }
+    }
+}
+
+pub(crate) fn encode_cbor(value: &Value) -> Result<Vec<u8>, FiError> {
+    let mut bytes = Vec::new();
+
+    match ciborium::into_writer(value, &mut bytes) {
+        Ok(_) => Ok(bytes),
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Failed to encode CBOR"));
+        }
+    }
+}
+
+/// Item of an embedded CBOR data item, with or without its tag.
+pub(crate) fn decode_embedded(value: &Value) -> Result<Value, FiError> {
+    match value {
+        Value::Tag(ENCODED_CBOR_TAG, val) => match val.as_ref() {
+            Value::Bytes(val) => decode_cbor(val.as_slice()),
+            _ => Err(FiError::new("Embedded CBOR must be a byte string")),
+        },
+        Value::Bytes(val) => decode_cbor(val.as_slice()),
+        _ => Err(FiError::new("Embedded CBOR must be a byte string")),
+    }
+}
+
+pub(crate) fn map_text<'a>(map: &'a Value, key: &str) -> Option<&'a Value> {
+    map.as_map().and_then(|entries| {
+        entries
+            .iter()
+            .find(|(name, _)| name.as_text() == Some(key))
+            .map(|(_, value)| value)
+    })
+}
+
+pub(crate) fn map_label(map: &Value, label: i128) -> Option<&Value> {
+    map.as_map().and_then(|entries| {
+        entries
+            .iter()
+            .find(|(name, _)| integer(name) == Some(label))
+            .map(|(_, value)| value)
+    })
+}
+
+pub(crate) fn integer(value: &Value) -> Option<i128> {
+    value.as_integer().map(i128::from)
+}
+
+/// JSON view of a CBOR value. Byte strings become base64url strings and tags are dropped.
+pub(crate) fn cbor_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Integer(val) => match i64::try_from(i128::from(*val)) {
+            Ok(val) => serde_json::Value::from(val),
+            Err(_) => serde_json::Value::from(i128::from(*val).to_string()),
+        },
+        Value::Bytes(val) => serde_json::Value::from(URL_SAFE_NO_PAD.encode(val)),
+        Value::Float(val) => serde_json::Value::from(*val),
+        Value::Text(val) => serde_json::Value::from(val.clone()),
+        Value::Bool(val) => serde_json::Value::from(*val),
+        Value::Tag(_, val) => cbor_to_json(val),
+        Value::Array(val) => serde_json::Value::Array(val.iter().map(cbor_to_json).collect()),
+        Value::Map(val) => serde_json::Value::Object(
+            val.iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Value::Text(val) => val.clone(),
+                        other => cbor_to_json(other).to_string(),
+                    };
+                    (key, cbor_to_json(value))
+                })
+                .collect(),
+        ),
+        _ => serde_json::Value::Null,
+    }
+}
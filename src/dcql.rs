@@ -0,0 +1,300 @@
+//! Digital Credentials Query Language, the query syntax of newer OpenID4VP drafts.
+//!
+//! A credential query matches on format, `meta.type_values` and claims addressed by path,
+//! where a path element is an object key, an array index or `null` for every array element.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::FiError,
+    presentation_exchange::credential_format,
+    vp::{PresentedCredential, VP},
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClaimsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    path: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    values: Option<Vec<Value>>,
+}
+
+impl ClaimsQuery {
+    /// Some value at the path is one of the expected values, when any are given.
+    pub fn matches(&self, credential: &Value) -> bool {
+        let claims = select_claims(credential, self.path.as_slice());
+        match self.values.as_ref() {
+            None => !claims.is_empty(),
+            Some(values) => claims.iter().any(|claim| values.contains(claim)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CredentialQuery {
+    id: String,
+    format: String,
+    #[serde(default)]
+    multiple: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    claims: Option<Vec<ClaimsQuery>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    claim_sets: Option<Vec<Vec<String>>>,
+}
+
+impl CredentialQuery {
+    pub fn get_id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn get_format(&self) -> &String {
+        &self.format
+    }
+
+    pub fn matches(&self, credential: &Value, format: &str) -> bool {
+        if self.format != format {
+            return false;
+        }
+
+        // Types must include every entry of one of the `type_values` sets
+        if let Some(type_values) = self
+            .meta
+            .as_ref()
+            .and_then(|val| val["type_values"].as_array())
+        {
+            let types = match &credential["type"] {
+                Value::Array(val) => val.clone(),
+                val => vec![val.clone()],
+            };
+            let accepted = type_values.iter().any(|set| {
+                set.as_array()
+                    .is_some_and(|val| val.iter().all(|_type| types.contains(_type)))
+            });
+            if !accepted {
+                return false;
+            }
+        }
+
+        let claims = match self.claims.as_ref() {
+            None => return true,
+            Some(val) => val,
+        };
+
+        match self.claim_sets.as_ref() {
+            None => claims.iter().all(|claim| claim.matches(credential)),
+            Some(claim_sets) => claim_sets.iter().any(|set| {
+                set.iter().all(|id| {
+                    claims
+                        .iter()
+                        .find(|claim| claim.id.as_ref() == Some(id))
+                        .is_some_and(|claim| claim.matches(credential))
+                })
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CredentialSetQuery {
+    options: Vec<Vec<String>>,
+    #[serde(default = "required_default")]
+    required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purpose: Option<Value>,
+}
+
+fn required_default() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DcqlQuery {
+    credentials: Vec<CredentialQuery>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credential_sets: Option<Vec<CredentialSetQuery>>,
+}
+
+impl DcqlQuery {
+    pub fn from(value: Value) -> Result<Self, FiError> {
+        let query: DcqlQuery = match serde_json::from_value(value) {
+            Ok(val) => val,
+            Err(error) => return Err(FiError::new(error.to_string().as_str())),
+        };
+
+        let ids: BTreeSet<&String> = query.credentials.iter().map(|val| &val.id).collect();
+        if ids.len() != query.credentials.len() {
+            return Err(FiError::new("Credential query ids must be unique"));
+        }
+
+        let unknown = query
+            .credential_sets
+            .iter()
+            .flatten()
+            .flat_map(|val| val.options.iter().flatten())
+            .any(|id| !ids.contains(id));
+        if unknown {
+            return Err(FiError::new(
+                "Credential set option names an unknown credential query",
+            ));
+        }
+
+        return Ok(query);
+    }
+
+    pub fn get_credentials(&self) -> &Vec<CredentialQuery> {
+        &self.credentials
+    }
+
+    /// Indexes of the credentials matching each credential query.
+    pub fn match_credentials(
+        &self,
+        credentials: &[PresentedCredential],
+    ) -> Result<BTreeMap<String, Vec<usize>>, FiError> {
+        let mut objects = Vec::new();
+        for credential in credentials.iter() {
+            let object = match credential.get_credential() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => match val.to_object() {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                },
+            };
+
+            let format = match credential_format(credential) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+
+            objects.push((object, format));
+        }
+
+        return Ok(self
+            .credentials
+            .iter()
+            .map(|query| {
+                let matches = objects
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (object, format))| query.matches(object, format))
+                    .map(|(index, _)| index)
+                    .collect();
+                (query.id.clone(), matches)
+            })
+            .collect());
+    }
+
+    /// Wallet side: the credentials to present for each credential query the credential sets
+    /// require. A query not marked `multiple` gets its first match only.
+    pub fn select(
+        &self,
+        credentials: &[PresentedCredential],
+    ) -> Result<BTreeMap<String, Vec<PresentedCredential>>, FiError> {
+        let matches = match self.match_credentials(credentials) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let selected = match self.select_queries(&matches) {
+            None => return Err(FiError::new("The credentials do not satisfy the query")),
+            Some(val) => val,
+        };
+
+        return Ok(matches
+            .into_iter()
+            .filter(|(id, _)| selected.contains(id))
+            .map(|(id, indexes)| {
+                let multiple = self
+                    .credentials
+                    .iter()
+                    .any(|query| query.id == id && query.multiple);
+                let count = match multiple {
+                    true => indexes.len(),
+                    false => 1,
+                };
+                let presented = indexes
+                    .into_iter()
+                    .take(count)
+                    .map(|index| credentials[index].clone())
+                    .collect();
+                (id, presented)
+            })
+            .collect());
+    }
+
+    /// Verifier side: the presentation holds credentials satisfying the query.
+    pub fn evaluate_presentation(&self, vp: &VP) -> Result<bool, FiError> {
+        match self.match_credentials(vp.get_verifiable_credentials().as_slice()) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(self.select_queries(&val).is_some()),
+        }
+    }
+
+    /// Credential queries to answer, `None` when a required credential set has no option
+    /// with every query matched. Without credential sets every query is required.
+    fn select_queries(&self, matches: &BTreeMap<String, Vec<usize>>) -> Option<BTreeSet<String>> {
+        let matched = |id: &String| matches.get(id).is_some_and(|val| !val.is_empty());
+
+        let credential_sets = match self.credential_sets.as_ref() {
+            None => {
+                return match self.credentials.iter().all(|val| matched(&val.id)) {
+                    true => Some(matches.keys().cloned().collect()),
+                    false => None,
+                };
+            }
+            Some(val) => val,
+        };
+
+        let mut selected = BTreeSet::new();
+        for set in credential_sets.iter() {
+            match set
+                .options
+                .iter()
+                .find(|option| option.iter().all(&matched))
+            {
+                Some(option) => selected.extend(option.iter().cloned()),
+                None if set.required => return None,
+                None => {}
+            };
+        }
+
+        return Some(selected);
+    }
+}
+
+/// Values addressed by a claims path.
+fn select_claims<'a>(value: &'a Value, path: &[Value]) -> Vec<&'a Value> {
+    let (element, rest) = match path.split_first() {
+        None => return vec![value],
+        Some(val) => val,
+    };
+
+    let next: Vec<&Value> = match (element, value) {
+        (Value::String(key), Value::Object(object)) => object.get(key).into_iter().collect(),
+        (Value::Number(index), Value::Array(array)) => index
+            .as_u64()
+            .and_then(|val| array.get(val as usize))
+            .into_iter()
+            .collect(),
+        (Value::Null, Value::Array(array)) => array.iter().collect(),
+        _ => Vec::new(),
+    };
+
+    return next
+        .into_iter()
+        .flat_map(|val| select_claims(val, rest))
+        .collect();
+}
@@ -1,26 +1,91 @@
 use std::{
     borrow::{Borrow, BorrowMut},
     collections::HashMap,
+    fmt::Debug,
+    sync::{Mutex, MutexGuard, PoisonError},
 };
 
+use async_trait::async_trait;
+use fi_digital_signatures::algorithms::Algorithm;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use wasm_bindgen::prelude::wasm_bindgen;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
+#[cfg(feature = "wasm")]
+use crate::wasm::{from_js_value, to_js_value};
+#[cfg(feature = "wasm")]
+use js_sys::{Array, Function, Object, Promise, Reflect};
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::JsValue;
+#[cfg(feature = "wasm")]
+use wasm_bindgen_futures::JsFuture;
 
-use crate::error::FiError;
+use crate::{
+    cache::{CacheLookup, CacheOptions, DocumentCache},
+    error::FiError,
+    keys::{self, KeyMaterial, KeyType},
+};
 
+#[cfg(not(feature = "wasm"))]
+pub trait DocResolver: Send + Sync {
+    fn resolve(&self, url: &str) -> Option<PublicVerificationDocument>;
+}
+
+#[cfg(feature = "wasm")]
 pub trait DocResolver {
-    fn resolve(&self, url: &str) -> Option<VerificationDocument>;
+    fn resolve(&self, url: &str) -> Option<PublicVerificationDocument>;
+}
+
+/// Resolver for documents that have to be fetched, e.g. DID documents or status lists.
+#[cfg(not(feature = "wasm"))]
+#[async_trait]
+pub trait AsyncDocResolver: Send + Sync {
+    async fn resolve(&self, url: &str) -> Option<PublicVerificationDocument>;
+}
+
+/// Resolver for documents that have to be fetched, e.g. DID documents or status lists.
+#[cfg(feature = "wasm")]
+#[async_trait(?Send)]
+pub trait AsyncDocResolver {
+    async fn resolve(&self, url: &str) -> Option<PublicVerificationDocument>;
+}
+
+/// Private key bytes. Wiped from memory on drop, never serialised and redacted from `Debug`.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey(Vec<u8>);
+
+impl SecretKey {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        SecretKey(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+impl From<Vec<u8>> for SecretKey {
+    fn from(value: Vec<u8>) -> Self {
+        SecretKey::new(value)
+    }
+}
+
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey([REDACTED])")
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct VerificationDocument {
-    private_key: Option<Vec<u8>>,
+    #[serde(skip)]
+    private_key: Option<SecretKey>,
     public_key: Option<Vec<u8>>,
     id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_type: Option<KeyType>,
 }
 
 #[wasm_bindgen]
@@ -33,14 +98,269 @@ impl VerificationDocument {
     ) -> VerificationDocument {
         return VerificationDocument {
             id,
-            private_key,
+            private_key: private_key.map(SecretKey::new),
             public_key,
+            key_type: None,
+        };
+    }
+
+    /// Generates a new key pair for the algorithm. The id is the `did:key` verification method
+    /// of the public key.
+    pub fn generate(alg: Algorithm) -> Result<VerificationDocument, FiError> {
+        match KeyType::from_algorithm(alg) {
+            Ok(val) => VerificationDocument::generate_key(val),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Generates a new key pair of the key type, e.g. a BLS12-381 key for `fi-bbs-2023` proofs.
+    #[wasm_bindgen(js_name = "generateKey")]
+    pub fn generate_key(key_type: KeyType) -> Result<VerificationDocument, FiError> {
+        let key = match keys::generate_key(key_type) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let multikey = match keys::public_key_to_multikey(key.key_type, key.public_key.as_slice()) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
         };
+
+        return Ok(VerificationDocument::from_key_material(
+            format!("did:key:{}#{}", multikey, multikey),
+            key,
+        ));
+    }
+
+    #[wasm_bindgen(js_name = "fromPem")]
+    pub fn from_pem(id: String, pem: &str) -> Result<VerificationDocument, FiError> {
+        match keys::key_from_pem(pem) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(VerificationDocument::from_key_material(id, val)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "fromMultikey")]
+    pub fn from_multikey(id: String, multibase: &str) -> Result<VerificationDocument, FiError> {
+        match keys::key_from_multikey(multibase) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(VerificationDocument::from_key_material(id, val)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "fromHex")]
+    pub fn from_hex(
+        id: String,
+        alg: Algorithm,
+        private_key: Option<String>,
+        public_key: Option<String>,
+    ) -> Result<VerificationDocument, FiError> {
+        let key_type = match KeyType::from_algorithm(alg) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let public_key = match public_key {
+            None => None,
+            Some(val) => match decode_hex(val.as_str()) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => match keys::check_public_key(key_type, val.as_slice()) {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => Some(val),
+                },
+            },
+        };
+
+        let private_key = match private_key {
+            None => None,
+            Some(val) => match decode_hex(val.as_str()) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => Some(SecretKey::new(val)),
+            },
+        };
+
+        let key = match private_key {
+            None => match public_key.clone() {
+                None => return Err(FiError::new("A private or public key is required")),
+                Some(val) => KeyMaterial {
+                    key_type,
+                    private_key: None,
+                    public_key: val,
+                },
+            },
+            Some(val) => match keys::key_from_private(key_type, val) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        };
+
+        if let Some(val) = public_key {
+            if val != key.public_key {
+                return Err(FiError::new("Private key does not match the public key"));
+            }
+        }
+
+        return Ok(VerificationDocument::from_key_material(id, key));
+    }
+
+    #[wasm_bindgen(js_name = "toPrivatePem")]
+    pub fn to_private_pem(&self) -> Result<String, FiError> {
+        match self.private_key_parts() {
+            Err(error) => Err(error),
+            Ok((key_type, private_key)) => keys::private_key_to_pem(key_type, private_key),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toPublicPem")]
+    pub fn to_public_pem(&self) -> Result<String, FiError> {
+        match self.public_document() {
+            Err(error) => Err(error),
+            Ok(val) => val.to_pem(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toPrivateMultikey")]
+    pub fn to_private_multikey(&self) -> Result<String, FiError> {
+        match self.private_key_parts() {
+            Err(error) => Err(error),
+            Ok((key_type, private_key)) => keys::private_key_to_multikey(key_type, private_key),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toPublicMultikey")]
+    pub fn to_public_multikey(&self) -> Result<String, FiError> {
+        match self.public_document() {
+            Err(error) => Err(error),
+            Ok(val) => val.to_multikey(),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toPrivateHex")]
+    pub fn to_private_hex(&self) -> Result<String, FiError> {
+        match self.private_key.as_ref() {
+            None => Err(FiError::new(
+                "No private key was found in the VerificationDocument",
+            )),
+            Some(val) => Ok(hex::encode(val.as_bytes())),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toPublicHex")]
+    pub fn to_public_hex(&self) -> Result<String, FiError> {
+        match self.public_document() {
+            Err(error) => Err(error),
+            Ok(val) => Ok(val.to_hex()),
+        }
+    }
+
+    #[wasm_bindgen(getter = keyType)]
+    pub fn key_type(&self) -> Option<KeyType> {
+        self.key_type
+    }
+
+    #[wasm_bindgen(js_name = "publicDocument")]
+    pub fn public_document(&self) -> Result<PublicVerificationDocument, FiError> {
+        match self.public_key.as_ref() {
+            None => Err(FiError::new(
+                "No public key was found in the VerificationDocument",
+            )),
+            Some(val) => Ok(PublicVerificationDocument {
+                id: self.id.clone(),
+                public_key: val.clone(),
+                key_type: self.key_type,
+            }),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "intoSigningDocument")]
+    pub fn into_signing_document(mut self) -> Result<SigningDocument, FiError> {
+        match self.private_key.take() {
+            None => Err(FiError::new(
+                "No private key was found in the VerificationDocument",
+            )),
+            Some(val) => Ok(SigningDocument {
+                id: self.id,
+                private_key: val,
+                key_type: self.key_type,
+            }),
+        }
     }
 }
 
 impl VerificationDocument {
-    pub fn get_private_key(&self) -> &Option<Vec<u8>> {
+    pub fn from_jwk(id: String, jwk: &Value) -> Result<VerificationDocument, FiError> {
+        match keys::key_from_jwk(jwk) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(VerificationDocument::from_key_material(id, val)),
+        }
+    }
+
+    pub fn to_private_jwk(&self) -> Result<Value, FiError> {
+        let mut jwk = match self.private_key_parts() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok((key_type, private_key)) => match keys::private_key_to_jwk(key_type, private_key) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        };
+
+        set_kid(&mut jwk, self.id.as_str());
+        return Ok(jwk);
+    }
+
+    pub fn to_public_jwk(&self) -> Result<Value, FiError> {
+        match self.public_document() {
+            Err(error) => Err(error),
+            Ok(val) => val.to_jwk(),
+        }
+    }
+
+    fn from_key_material(id: String, key: KeyMaterial) -> VerificationDocument {
+        return VerificationDocument {
+            id,
+            private_key: key.private_key,
+            public_key: Some(key.public_key),
+            key_type: Some(key.key_type),
+        };
+    }
+
+    fn private_key_parts(&self) -> Result<(KeyType, &[u8]), FiError> {
+        let private_key = match self.private_key.as_ref() {
+            None => {
+                return Err(FiError::new(
+                    "No private key was found in the VerificationDocument",
+                ))
+            }
+            Some(val) => val.as_bytes(),
+        };
+
+        match self.key_type {
+            None => Err(FiError::new(
+                "The key type of the VerificationDocument is unknown",
+            )),
+            Some(val) => Ok((val, private_key)),
+        }
+    }
+
+    pub fn get_private_key(&self) -> &Option<SecretKey> {
         self.private_key.borrow()
     }
 
@@ -52,7 +372,7 @@ impl VerificationDocument {
         self.id.borrow()
     }
 
-    pub fn get_private_key_mut(&mut self) -> &mut Option<Vec<u8>> {
+    pub fn get_private_key_mut(&mut self) -> &mut Option<SecretKey> {
         self.private_key.borrow_mut()
     }
 
@@ -63,47 +383,552 @@ impl VerificationDocument {
     pub fn get_id_mut(&mut self) -> &mut String {
         self.id.borrow_mut()
     }
+
+    pub fn get_key_type(&self) -> &Option<KeyType> {
+        self.key_type.borrow()
+    }
+
+    pub fn get_key_type_mut(&mut self) -> &mut Option<KeyType> {
+        self.key_type.borrow_mut()
+    }
 }
 
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl VerificationDocument {
+    #[wasm_bindgen(js_name = "fromJwk")]
+    pub fn from_jwk_object(id: String, jwk: JsValue) -> Result<VerificationDocument, FiError> {
+        match from_js_value(jwk) {
+            Err(error) => Err(error),
+            Ok(val) => VerificationDocument::from_jwk(id, &val),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toPrivateJwk")]
+    pub fn to_private_jwk_object(&self) -> Result<JsValue, FiError> {
+        match self.to_private_jwk() {
+            Err(error) => Err(error),
+            Ok(val) => to_js_value(&val),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toPublicJwk")]
+    pub fn to_public_jwk_object(&self) -> Result<JsValue, FiError> {
+        match self.to_public_jwk() {
+            Err(error) => Err(error),
+            Ok(val) => to_js_value(&val),
+        }
+    }
+}
+
+/// Public half of a [`VerificationDocument`]. This is the only document type a
+/// [`DocumentLoader`] stores and hands out.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[wasm_bindgen]
+pub struct PublicVerificationDocument {
+    public_key: Vec<u8>,
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_type: Option<KeyType>,
+}
+
+#[wasm_bindgen]
+impl PublicVerificationDocument {
+    #[wasm_bindgen(constructor)]
+    pub fn new(id: String, public_key: Vec<u8>) -> PublicVerificationDocument {
+        return PublicVerificationDocument {
+            id,
+            public_key,
+            key_type: None,
+        };
+    }
+
+    /// Raw public key of the key type, e.g. 32 bytes for Ed25519 or a SEC1 point for ECDSA.
+    #[wasm_bindgen(js_name = "withKeyType")]
+    pub fn with_key_type(
+        id: String,
+        public_key: Vec<u8>,
+        key_type: KeyType,
+    ) -> Result<PublicVerificationDocument, FiError> {
+        match keys::check_public_key(key_type, public_key.as_slice()) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(PublicVerificationDocument {
+                id,
+                public_key: val,
+                key_type: Some(key_type),
+            }),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "fromPem")]
+    pub fn from_pem(id: String, pem: &str) -> Result<PublicVerificationDocument, FiError> {
+        match keys::key_from_pem(pem) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(PublicVerificationDocument::from_key_material(id, val)),
+        }
+    }
+
+    /// Reads the subject public key of a DER or PEM encoded X.509 certificate. The certificate
+    /// itself is not validated.
+    #[wasm_bindgen(js_name = "fromCertificate")]
+    pub fn from_certificate(
+        id: String,
+        certificate: &[u8],
+    ) -> Result<PublicVerificationDocument, FiError> {
+        match keys::key_from_certificate(certificate) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(PublicVerificationDocument::from_key_material(id, val)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "fromMultikey")]
+    pub fn from_multikey(
+        id: String,
+        multibase: &str,
+    ) -> Result<PublicVerificationDocument, FiError> {
+        match keys::key_from_multikey(multibase) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(PublicVerificationDocument::from_key_material(id, val)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toPem")]
+    pub fn to_pem(&self) -> Result<String, FiError> {
+        match self.key_type_or_err() {
+            Err(error) => Err(error),
+            Ok(val) => keys::public_key_to_pem(val, self.public_key.as_slice()),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toMultikey")]
+    pub fn to_multikey(&self) -> Result<String, FiError> {
+        match self.key_type_or_err() {
+            Err(error) => Err(error),
+            Ok(val) => keys::public_key_to_multikey(val, self.public_key.as_slice()),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toHex")]
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.public_key.as_slice())
+    }
+
+    #[wasm_bindgen(getter = keyType)]
+    pub fn key_type(&self) -> Option<KeyType> {
+        self.key_type
+    }
+}
+
+/// Key fields of a DID document verification method.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationMethod {
+    id: Option<String>,
+    #[serde(rename = "type")]
+    method_type: Option<String>,
+    key_type: Option<KeyType>,
+    public_key_multibase: Option<String>,
+    public_key_jwk: Option<Value>,
+    public_key_pem: Option<String>,
+    public_key_base58: Option<String>,
+    public_key_hex: Option<String>,
+}
+
+impl PublicVerificationDocument {
+    /// Reads a verification method with one of `publicKeyMultibase`, `publicKeyJwk`,
+    /// `publicKeyPem`, `publicKeyBase58` or `publicKeyHex`. A missing id defaults to `base_id` and
+    /// a relative one (`#key-1`) is resolved against it. Base58 and hex keys are only typed
+    /// through `keyType` or an Ed25519 or secp256k1 method `type`.
+    pub fn from_verification_method(
+        method: &Value,
+        base_id: &str,
+    ) -> Result<PublicVerificationDocument, FiError> {
+        let method: VerificationMethod = match serde_json::from_value(method.clone()) {
+            Ok(val) => val,
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Invalid verification method"));
+            }
+        };
+
+        let id = match method.id {
+            None => String::from(base_id),
+            Some(val) if val.starts_with('#') => {
+                format!("{}{}", base_id.split('#').next().unwrap_or_default(), val)
+            }
+            Some(val) => val,
+        };
+
+        let result = if let Some(multibase) = method.public_key_multibase {
+            PublicVerificationDocument::from_multikey(id, multibase.as_str())
+        } else if let Some(jwk) = method.public_key_jwk {
+            PublicVerificationDocument::from_jwk(id, &jwk)
+        } else if let Some(pem) = method.public_key_pem {
+            PublicVerificationDocument::from_pem(id, pem.as_str())
+        } else if let Some(base58) = method.public_key_base58 {
+            match bs58::decode(base58).into_vec() {
+                Ok(val) => Ok(PublicVerificationDocument::new(id, val)),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    Err(FiError::new("Key is not base58 encoded"))
+                }
+            }
+        } else if let Some(hex) = method.public_key_hex {
+            match decode_hex(hex.as_str()) {
+                Err(error) => Err(error),
+                Ok(val) => Ok(PublicVerificationDocument::new(id, val)),
+            }
+        } else {
+            Err(FiError::new("Verification method has no public key"))
+        };
+
+        let mut doc = match result {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        if method.key_type.is_some() {
+            doc.key_type = method.key_type;
+        } else if doc.key_type.is_none() {
+            // Raw base58 and hex keys only say what they are through the method type
+            doc.key_type = match method.method_type.as_deref() {
+                Some("Ed25519VerificationKey2018") | Some("Ed25519VerificationKey2020") => {
+                    Some(KeyType::Ed25519)
+                }
+                Some("EcdsaSecp256k1VerificationKey2019") => Some(KeyType::Secp256k1),
+                _ => None,
+            };
+        }
+
+        return Ok(doc);
+    }
+
+    pub fn from_jwk(id: String, jwk: &Value) -> Result<PublicVerificationDocument, FiError> {
+        match keys::key_from_jwk(jwk) {
+            Err(error) => Err(error),
+            Ok(val) => Ok(PublicVerificationDocument::from_key_material(id, val)),
+        }
+    }
+
+    pub fn to_jwk(&self) -> Result<Value, FiError> {
+        let mut jwk = match self.key_type_or_err() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => match keys::public_key_to_jwk(val, self.public_key.as_slice()) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        };
+
+        set_kid(&mut jwk, self.id.as_str());
+        return Ok(jwk);
+    }
+
+    fn from_key_material(id: String, key: KeyMaterial) -> PublicVerificationDocument {
+        return PublicVerificationDocument {
+            id,
+            public_key: key.public_key,
+            key_type: Some(key.key_type),
+        };
+    }
+
+    fn key_type_or_err(&self) -> Result<KeyType, FiError> {
+        match self.key_type {
+            None => Err(FiError::new(
+                "The key type of the PublicVerificationDocument is unknown",
+            )),
+            Some(val) => Ok(val),
+        }
+    }
+
+    pub fn get_key_type(&self) -> &Option<KeyType> {
+        self.key_type.borrow()
+    }
+
+    /// Algorithms the key can verify. Keys without a known key type verify none, as the
+    /// algorithm cannot be told from the key bytes alone.
+    pub fn get_algorithms(&self) -> Vec<Algorithm> {
+        match self.key_type {
+            None => Vec::new(),
+            Some(key_type) => key_type.get_algorithms(),
+        }
+    }
+
+    pub fn get_public_key(&self) -> &Vec<u8> {
+        self.public_key.borrow()
+    }
+
+    pub fn get_id(&self) -> &String {
+        self.id.borrow()
+    }
+}
+
+/// Private half of a [`VerificationDocument`], used to create signers. Cannot be cloned or
+/// serialised.
+#[derive(Debug)]
+#[wasm_bindgen]
+pub struct SigningDocument {
+    private_key: SecretKey,
+    id: String,
+    key_type: Option<KeyType>,
+}
+
+#[wasm_bindgen]
+impl SigningDocument {
+    #[wasm_bindgen(constructor)]
+    pub fn new(id: String, private_key: Vec<u8>) -> SigningDocument {
+        return SigningDocument {
+            id,
+            private_key: SecretKey::new(private_key),
+            key_type: None,
+        };
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl PublicVerificationDocument {
+    #[wasm_bindgen(js_name = "fromJwk")]
+    pub fn from_jwk_object(
+        id: String,
+        jwk: JsValue,
+    ) -> Result<PublicVerificationDocument, FiError> {
+        match from_js_value(jwk) {
+            Err(error) => Err(error),
+            Ok(val) => PublicVerificationDocument::from_jwk(id, &val),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toJwk")]
+    pub fn to_jwk_object(&self) -> Result<JsValue, FiError> {
+        match self.to_jwk() {
+            Err(error) => Err(error),
+            Ok(val) => to_js_value(&val),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "toJSON")]
+    pub fn to_json_object(&self) -> Result<JsValue, FiError> {
+        let mut object = serde_json::json!({
+            "id": self.id,
+            "publicKeyHex": self.to_hex(),
+        });
+
+        if let Some(key_type) = self.key_type {
+            object["keyType"] = serde_json::to_value(key_type).unwrap_or_default();
+        }
+
+        to_js_value(&object)
+    }
+}
+
+impl SigningDocument {
+    pub fn get_private_key(&self) -> &SecretKey {
+        self.private_key.borrow()
+    }
+
+    pub fn get_key_type(&self) -> &Option<KeyType> {
+        self.key_type.borrow()
+    }
+
+    pub fn get_id(&self) -> &String {
+        self.id.borrow()
+    }
+}
+
+/// Resolves and caches [`PublicVerificationDocument`]s. Lookups take `&self`, so a single loader
+/// can be shared between threads.
 #[wasm_bindgen]
 pub struct DocumentLoader {
-    docs: HashMap<String, VerificationDocument>,
+    cache: Mutex<DocumentCache>,
     doc_resolvers: Vec<Box<dyn DocResolver>>,
+    async_doc_resolvers: Vec<Box<dyn AsyncDocResolver>>,
 }
 
-#[cfg(not(feature = "wasm"))]
 impl DocumentLoader {
-    pub fn new(docs: Option<HashMap<String, VerificationDocument>>) -> Result<Self, FiError> {
-        return Ok(DocumentLoader {
+    fn from_cache(cache: DocumentCache) -> Self {
+        return DocumentLoader {
+            cache: Mutex::new(cache),
             doc_resolvers: Vec::new(),
-            docs: match docs {
+            async_doc_resolvers: Vec::new(),
+        };
+    }
+
+    fn cache(&self) -> MutexGuard<'_, DocumentCache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl DocumentLoader {
+    pub fn new(docs: Option<HashMap<String, PublicVerificationDocument>>) -> Result<Self, FiError> {
+        DocumentLoader::with_cache_options(docs, CacheOptions::default())
+    }
+
+    pub fn with_cache_options(
+        docs: Option<HashMap<String, PublicVerificationDocument>>,
+        options: CacheOptions,
+    ) -> Result<Self, FiError> {
+        return Ok(DocumentLoader::from_cache(DocumentCache::new(
+            match docs {
                 Some(val) => val,
                 None => HashMap::new(),
             },
-        });
+            options,
+        )));
+    }
+
+    pub fn add_resolver(&mut self, resolver: Box<dyn DocResolver>) {
+        self.doc_resolvers.push(resolver);
+    }
+
+    pub fn add_async_resolver(&mut self, resolver: Box<dyn AsyncDocResolver>) {
+        self.async_doc_resolvers.push(resolver);
+    }
+
+    /// Adds a document that is never expired or evicted.
+    pub fn add_document(&self, doc: PublicVerificationDocument) {
+        self.cache().pin(doc);
     }
 
-    pub fn get_verification_document(&mut self, url: &str) -> Option<VerificationDocument> {
+    /// Pins the verification methods of a DID document, including the ones embedded in its
+    /// verification relationships, and returns how many were added.
+    pub fn add_did_document(&self, did_document: &Value) -> Result<usize, FiError> {
+        let did = match did_document["id"].as_str() {
+            None => return Err(FiError::new("DID document has no id")),
+            Some(val) => val,
+        };
+
+        let mut count = 0;
+
+        for field in [
+            "verificationMethod",
+            "authentication",
+            "assertionMethod",
+            "capabilityInvocation",
+            "capabilityDelegation",
+        ] {
+            let methods = match did_document[field].as_array() {
+                None => continue,
+                Some(val) => val,
+            };
+
+            // Relationships may also refer to a method by id, those are skipped
+            for method in methods.iter().filter(|method| method.is_object()) {
+                match PublicVerificationDocument::from_verification_method(method, did) {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => self.add_document(val),
+                };
+                count += 1;
+            }
+        }
+
+        return Ok(count);
+    }
+
+    pub fn get_verification_document(&self, url: &str) -> Option<PublicVerificationDocument> {
         get_verification_document(self, url)
     }
+
+    /// Looks the document up in the cache and the sync resolvers before trying the async ones.
+    pub async fn get_verification_document_async(
+        &self,
+        url: &str,
+    ) -> Option<PublicVerificationDocument> {
+        get_verification_document_async(self, url).await
+    }
+
+    /// Removes the document, pinned or resolved, so the next lookup resolves it again.
+    pub fn invalidate(&self, url: &str) {
+        self.cache().invalidate(url);
+    }
+
+    /// Removes every resolved document. Pinned documents are kept.
+    pub fn clear(&self) {
+        self.cache().clear();
+    }
+
+    pub fn get_cache_size(&self) -> usize {
+        self.cache().len()
+    }
 }
 
-fn get_verification_document(doc: &mut DocumentLoader, url: &str) -> Option<VerificationDocument> {
-    if doc.docs.contains_key(url) {
-        let val: VerificationDocument = match doc.docs.get_key_value(url) {
-            None => return None,
-            Some((_url, _doc)) => _doc.clone(),
-        };
-        return Some(val);
+fn decode_hex(value: &str) -> Result<Vec<u8>, FiError> {
+    match hex::decode(value) {
+        Ok(val) => Ok(val),
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Key is not hex encoded"));
+        }
     }
+}
 
-    let itr = doc.doc_resolvers.iter();
+fn set_kid(jwk: &mut Value, id: &str) {
+    if let (Some(obj), false) = (jwk.as_object_mut(), id.is_empty()) {
+        obj.insert(String::from("kid"), Value::from(id));
+    }
+}
+
+fn get_verification_document(
+    loader: &DocumentLoader,
+    url: &str,
+) -> Option<PublicVerificationDocument> {
+    match loader.cache().get(url) {
+        CacheLookup::Found(doc) => return Some(doc),
+        CacheLookup::NotFound => return None,
+        CacheLookup::Miss => {}
+    };
+
+    let doc = resolve_verification_document(loader, url);
+
+    // A failed lookup is only final when no async resolver could still find the document
+    if doc.is_some() || loader.async_doc_resolvers.is_empty() {
+        loader.cache().insert(url, doc.clone());
+    }
+
+    return doc;
+}
+
+async fn get_verification_document_async(
+    loader: &DocumentLoader,
+    url: &str,
+) -> Option<PublicVerificationDocument> {
+    match loader.cache().get(url) {
+        CacheLookup::Found(doc) => return Some(doc),
+        CacheLookup::NotFound => return None,
+        CacheLookup::Miss => {}
+    };
+
+    let mut doc = resolve_verification_document(loader, url);
+
+    if doc.is_none() {
+        for resolver in loader.async_doc_resolvers.iter() {
+            if let Some(value) = resolver.resolve(url).await {
+                doc = Some(value);
+                break;
+            }
+        }
+    }
+
+    loader.cache().insert(url, doc.clone());
+    return doc;
+}
+
+fn resolve_verification_document(
+    loader: &DocumentLoader,
+    url: &str,
+) -> Option<PublicVerificationDocument> {
+    let itr = loader.doc_resolvers.iter();
     for resolver in itr {
         let value = resolver.resolve(url);
         if value.is_some() {
-            let val = value.clone().unwrap();
-            doc.docs.insert(String::from(url), val);
-            return Some(value.unwrap());
+            return value;
         }
     }
 
@@ -113,28 +938,153 @@ fn get_verification_document(doc: &mut DocumentLoader, url: &str) -> Option<Veri
 #[wasm_bindgen]
 #[cfg(feature = "wasm")]
 impl DocumentLoader {
+    /// `docs` is an optional plain object mapping urls to documents, see `addResolver` for the
+    /// accepted document shapes.
     #[wasm_bindgen(constructor)]
-    pub fn new(docs: JsValue) -> Result<DocumentLoader, FiError> {
-        let mut values: Option<HashMap<String, VerificationDocument>> = None;
-
+    pub fn js_new(docs: JsValue) -> Result<DocumentLoader, FiError> {
         if docs.is_null() || docs.is_undefined() {
-            values = match serde_wasm_bindgen::from_value(docs) {
-                Ok(val) => val,
-                Err(error) => return Err(FiError::new(error.to_string().as_str())),
-            };
+            return DocumentLoader::new(None);
         }
 
-        return Ok(DocumentLoader {
-            doc_resolvers: Vec::new(),
-            docs: match values {
+        if !docs.is_object() {
+            return Err(FiError::new("Documents must be an object"));
+        }
+
+        let mut values = HashMap::new();
+
+        for entry in Object::entries(&Object::from(docs)).iter() {
+            let entry = Array::from(&entry);
+            let url = match entry.get(0).as_string() {
+                None => return Err(FiError::new("Document url must be a string")),
                 Some(val) => val,
-                None => HashMap::new(),
-            },
-        });
+            };
+
+            match public_document_from_js(entry.get(1), url.as_str()) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(None) => {}
+                Ok(Some(doc)) => {
+                    values.insert(url, doc);
+                }
+            };
+        }
+
+        DocumentLoader::new(Some(values))
+    }
+
+    /// Registers a function called with the url of a missing document. It may return the document
+    /// or a `Promise` of it, as a `PublicVerificationDocument` or a verification method object
+    /// with an `id` and one of `publicKeyMultibase`, `publicKeyJwk`, `publicKeyPem` or
+    /// `publicKeyHex`. `null`, `undefined` or a thrown error mean the document was not found.
+    ///
+    /// JS resolvers are only consulted by `getVerificationDocumentAsync` and `verifyAsync`.
+    #[wasm_bindgen(js_name = "addResolver")]
+    pub fn js_add_resolver(&mut self, resolver: Function) {
+        self.add_async_resolver(Box::new(JsDocResolver { callback: resolver }));
+    }
+
+    #[wasm_bindgen(js_name = "addDocument")]
+    pub fn js_add_document(&self, doc: PublicVerificationDocument) {
+        self.add_document(doc);
     }
 
     #[wasm_bindgen(js_name = "getVerificationDocument")]
-    pub fn get_verification_document(&mut self, url: &str) -> Option<VerificationDocument> {
-        get_verification_document(self, url)
+    pub fn js_get_verification_document(&self, url: &str) -> Option<PublicVerificationDocument> {
+        self.get_verification_document(url)
+    }
+
+    #[wasm_bindgen(js_name = "getVerificationDocumentAsync")]
+    pub async fn js_get_verification_document_async(
+        &self,
+        url: String,
+    ) -> Option<PublicVerificationDocument> {
+        self.get_verification_document_async(url.as_str()).await
+    }
+
+    #[wasm_bindgen(js_name = "invalidate")]
+    pub fn js_invalidate(&self, url: &str) {
+        self.invalidate(url);
+    }
+
+    #[wasm_bindgen(js_name = "clear")]
+    pub fn js_clear(&self) {
+        self.clear();
+    }
+
+    #[wasm_bindgen(js_name = "getCacheSize")]
+    pub fn js_get_cache_size(&self) -> usize {
+        self.get_cache_size()
+    }
+}
+
+#[cfg(feature = "wasm")]
+struct JsDocResolver {
+    callback: Function,
+}
+
+#[cfg(feature = "wasm")]
+#[async_trait(?Send)]
+impl AsyncDocResolver for JsDocResolver {
+    async fn resolve(&self, url: &str) -> Option<PublicVerificationDocument> {
+        let result = match self.callback.call1(&JsValue::NULL, &JsValue::from_str(url)) {
+            Ok(val) => val,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return None;
+            }
+        };
+
+        // Plain values and promises are awaited the same way
+        let value = match JsFuture::from(Promise::resolve(&result)).await {
+            Ok(val) => val,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return None;
+            }
+        };
+
+        match public_document_from_js(value, url) {
+            Ok(val) => val,
+            Err(error) => {
+                eprintln!("{}", error);
+                return None;
+            }
+        }
+    }
+}
+
+/// Reads a document a JS caller handed over. The `id` defaults to the url it was looked up by.
+#[cfg(feature = "wasm")]
+fn public_document_from_js(
+    value: JsValue,
+    url: &str,
+) -> Result<Option<PublicVerificationDocument>, FiError> {
+    if value.is_null() || value.is_undefined() {
+        return Ok(None);
+    }
+
+    // Wasm objects such as `PublicVerificationDocument` describe themselves through `toJSON`
+    let value = match Reflect::get(&value, &JsValue::from_str("toJSON")) {
+        Ok(to_json) if to_json.is_function() => match Function::from(to_json).call0(&value) {
+            Ok(val) => val,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(FiError::new("Document could not be converted"));
+            }
+        },
+        _ => value,
+    };
+
+    let method: Value = match from_js_value(value) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match PublicVerificationDocument::from_verification_method(&method, url) {
+        Err(error) => Err(error),
+        Ok(val) => Ok(Some(val)),
     }
 }
@@ -0,0 +1,317 @@
+//! Data Integrity proofs with selective disclosure, modelled on `ecdsa-sd-2023`.
+//!
+//! The issuer signs the proof options, the mandatory statements and a proof-scoped P-256 key,
+//! which signs every other statement on its own. The holder discloses a statement by keeping
+//! its signature. Derived proofs carry the same base signature and can be linked.
+//!
+//! Statements are not HMAC relabelled or shuffled and the proof value is JSON rather than CBOR,
+//! so the proofs are published under this library's own `fi-ecdsa-sd-2023` cryptosuite rather
+//! than `ecdsa-sd-2023`.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use fi_digital_signatures::algorithms::Algorithm;
+use serde_json::{json, Value};
+
+use crate::{
+    document::{PublicVerificationDocument, VerificationDocument},
+    error::FiError,
+    keys::KeyType,
+    proof::{verify_signature, CredentialProof, DataIntegrityProof},
+    signer::{InMemorySigner, Signer},
+    statements::{
+        check_pointers, credential_claims, credential_proof, decode_proof_value,
+        derived_credential, disclose, encode_proof_value, options_hash, proof_bytes,
+        proof_pointers, split_statements, statements_hash, with_required_pointers,
+    },
+    vc::VC,
+};
+
+pub const FI_ECDSA_SD_2023: &str = "fi-ecdsa-sd-2023";
+
+/// Adds a base proof signed by an ES256 or ES384 signer. Claims under the mandatory pointers
+/// are revealed in every derived proof, in addition to the required VC fields.
+pub fn add_base_proof(
+    vc: &mut VC,
+    signer: &dyn Signer,
+    purpose: String,
+    mandatory_pointers: &[String],
+) -> Result<(), FiError> {
+    if !matches!(signer.algorithm(), Algorithm::ES256 | Algorithm::ES384) {
+        return Err(FiError::new(
+            "fi-ecdsa-sd-2023 requires an ES256 or ES384 signer",
+        ));
+    }
+
+    let pointers = with_required_pointers(mandatory_pointers);
+
+    let claims = match credential_claims(vc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match check_pointers(&claims, &pointers) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let mut proof = DataIntegrityProof::new(
+        FI_ECDSA_SD_2023,
+        String::from(signer.verification_method()),
+        purpose,
+    );
+    let statements = split_statements(&claims, &pointers);
+
+    let ephemeral_doc = match VerificationDocument::generate(Algorithm::ES256) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let ephemeral_signer = match InMemorySigner::new(&ephemeral_doc, Algorithm::ES256) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let ephemeral_key = match ephemeral_doc.get_public_key() {
+        None => return Err(FiError::new("Failed to generate the proof key")),
+        Some(val) => val.clone(),
+    };
+
+    let mut signatures = Vec::new();
+    for statement in statements.selective.iter() {
+        match ephemeral_signer.sign(statement.as_bytes()) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => signatures.push(URL_SAFE_NO_PAD.encode(val)),
+        };
+    }
+
+    let base_input = match base_signing_input(&proof, &ephemeral_key, &statements.mandatory) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let base_signature = match signer.sign(base_input.as_bytes()) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    proof.set_proof_value(Some(encode_proof_value(&json!({
+        "baseSignature": URL_SAFE_NO_PAD.encode(base_signature),
+        "publicKey": URL_SAFE_NO_PAD.encode(ephemeral_key),
+        "signatures": signatures,
+        "mandatoryPointers": pointers,
+    }))));
+
+    *vc.get_proof_mut() = Some(CredentialProof::DataIntegrity(proof));
+    return Ok(());
+}
+
+/// Derives a credential revealing the mandatory claims and the claims under the selective
+/// pointers.
+pub fn derive_proof(vc: &VC, selective_pointers: &[String]) -> Result<VC, FiError> {
+    let proof = match credential_proof(vc, FI_ECDSA_SD_2023) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let proof_value = match decode_proof_value(proof) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let mandatory_pointers = match proof_pointers(&proof_value) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let signatures = match proof_signatures(&proof_value) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let claims = match credential_claims(vc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match check_pointers(&claims, selective_pointers) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    if split_statements(&claims, &mandatory_pointers)
+        .selective
+        .len()
+        != signatures.len()
+    {
+        return Err(FiError::new("Proof value does not match the credential"));
+    }
+
+    let (disclosed, selective_indexes) = disclose(&claims, &mandatory_pointers, selective_pointers);
+
+    let mut derived_value = proof_value.clone();
+    derived_value["signatures"] = selective_indexes
+        .iter()
+        .map(|index| Value::from(signatures[*index].clone()))
+        .collect();
+
+    let mut derived_proof = proof.clone();
+    derived_proof.set_proof_value(Some(encode_proof_value(&derived_value)));
+
+    derived_credential(disclosed, derived_proof)
+}
+
+/// Verifies a base or derived `fi-ecdsa-sd-2023` proof with the document's P-256 or P-384 key.
+pub(crate) fn verify_proof(
+    vc: &VC,
+    proof: &DataIntegrityProof,
+    doc: &PublicVerificationDocument,
+) -> Result<bool, FiError> {
+    let alg = match issuer_algorithm(doc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let proof_value = match decode_proof_value(proof) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let mandatory_pointers = match proof_pointers(&proof_value) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let signatures = match proof_signatures(&proof_value) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let ephemeral_key = match proof_bytes(&proof_value, "publicKey") {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let base_signature = match proof_value["baseSignature"].as_str() {
+        None => return Err(FiError::new("Proof value has no base signature")),
+        Some(val) => String::from(val),
+    };
+
+    let claims = match credential_claims(vc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let statements = split_statements(&claims, &mandatory_pointers);
+
+    // Every disclosed statement must come with the signature of the proof key
+    if statements.selective.len() != signatures.len() {
+        return Ok(false);
+    }
+
+    let base_input = match base_signing_input(proof, &ephemeral_key, &statements.mandatory) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match verify_signature(doc, alg, base_input, base_signature) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(false) => return Ok(false),
+        Ok(true) => {}
+    };
+
+    let ephemeral_doc = PublicVerificationDocument::new(String::new(), ephemeral_key);
+
+    for (statement, signature) in statements.selective.into_iter().zip(signatures) {
+        match verify_signature(&ephemeral_doc, Algorithm::ES256, statement, signature) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(false) => return Ok(false),
+            Ok(true) => {}
+        };
+    }
+
+    return Ok(true);
+}
+
+/// Input of the issuer signature, binding the proof options, the proof key and the mandatory
+/// statements.
+fn base_signing_input(
+    proof: &DataIntegrityProof,
+    ephemeral_key: &[u8],
+    mandatory: &[String],
+) -> Result<String, FiError> {
+    let mut input = match options_hash(proof) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    input.extend(ephemeral_key);
+    input.extend(statements_hash(mandatory));
+
+    return Ok(hex::encode(input));
+}
+
+fn issuer_algorithm(doc: &PublicVerificationDocument) -> Result<Algorithm, FiError> {
+    match (doc.key_type(), doc.get_public_key().len()) {
+        (Some(KeyType::P256), _) | (None, 33) | (None, 65) => Ok(Algorithm::ES256),
+        (Some(KeyType::P384), _) | (None, 49) | (None, 97) => Ok(Algorithm::ES384),
+        _ => Err(FiError::new(
+            "fi-ecdsa-sd-2023 requires a P-256 or P-384 key",
+        )),
+    }
+}
+
+fn proof_signatures(proof_value: &Value) -> Result<Vec<String>, FiError> {
+    match proof_value["signatures"].as_array() {
+        None => Err(FiError::new("Proof value has no signatures")),
+        Some(val) => Ok(val
+            .iter()
+            .filter_map(|val| val.as_str().map(String::from))
+            .collect()),
+    }
+}
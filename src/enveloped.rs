@@ -0,0 +1,185 @@
+//! Credentials secured by an enveloping mechanism (JWT, SD-JWT or COSE) and carried in a
+//! presentation as an `EnvelopedVerifiableCredential` data URL.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    cose::{CoseSign1, VC_CONTENT_TYPE, VC_COSE_TYPE},
+    document::PublicVerificationDocument,
+    error::FiError,
+    jose::{Jwt, SdJwt},
+    vc::VC,
+};
+
+pub const ENVELOPED_VC_TYPE: &str = "EnvelopedVerifiableCredential";
+pub const VC_JWT_MEDIA_TYPE: &str = "application/vc+jwt";
+pub const VC_SD_JWT_MEDIA_TYPE: &str = "application/vc+sd-jwt";
+
+const CREDENTIALS_V2_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+/// Decoded content of an enveloped credential.
+#[derive(Clone, Debug)]
+pub enum Envelope {
+    Jwt(Jwt),
+    SdJwt(SdJwt),
+    Cose(CoseSign1),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnvelopedCredential {
+    #[serde(rename = "@context")]
+    contexts: Vec<Value>,
+    id: String,
+    #[serde(rename = "type")]
+    _type: String,
+}
+
+impl EnvelopedCredential {
+    pub fn from_jwt(token: &str) -> Self {
+        EnvelopedCredential::new(format!("data:{},{}", VC_JWT_MEDIA_TYPE, token.trim()))
+    }
+
+    pub fn from_sd_jwt(token: &str) -> Self {
+        EnvelopedCredential::new(format!("data:{},{}", VC_SD_JWT_MEDIA_TYPE, token.trim()))
+    }
+
+    pub fn from_cose(bytes: &[u8]) -> Self {
+        EnvelopedCredential::new(format!(
+            "data:{};base64,{}",
+            VC_COSE_TYPE,
+            STANDARD.encode(bytes)
+        ))
+    }
+
+    fn new(id: String) -> Self {
+        EnvelopedCredential {
+            contexts: vec![Value::from(CREDENTIALS_V2_CONTEXT)],
+            id,
+            _type: String::from(ENVELOPED_VC_TYPE),
+        }
+    }
+
+    /// Data URL holding the secured credential.
+    pub fn get_id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn get_media_type(&self) -> Option<&str> {
+        self.id
+            .strip_prefix("data:")
+            .and_then(|val| val.split(',').next())
+            .map(|val| val.trim_end_matches(";base64"))
+    }
+
+    pub fn decode(&self) -> Result<Envelope, FiError> {
+        if self._type != ENVELOPED_VC_TYPE {
+            return Err(FiError::new(
+                "Credential is not an EnvelopedVerifiableCredential",
+            ));
+        }
+
+        let (header, data) = match self
+            .id
+            .strip_prefix("data:")
+            .and_then(|val| val.split_once(','))
+        {
+            None => return Err(FiError::new("Enveloped credential id must be a data URL")),
+            Some(val) => val,
+        };
+
+        let media_type = header.trim_end_matches(";base64");
+        let data = match header.ends_with(";base64") {
+            false => data.as_bytes().to_vec(),
+            true => match STANDARD.decode(data) {
+                Ok(val) => val,
+                Err(error) => {
+                    eprintln!("{}", error);
+                    return Err(FiError::new("Enveloped credential is not base64 encoded"));
+                }
+            },
+        };
+
+        match media_type {
+            VC_JWT_MEDIA_TYPE => match std::str::from_utf8(data.as_slice()) {
+                Err(_) => Err(FiError::new("Enveloped JWT is not valid UTF-8")),
+                Ok(val) => Jwt::decode(val).map(Envelope::Jwt),
+            },
+            VC_SD_JWT_MEDIA_TYPE => match std::str::from_utf8(data.as_slice()) {
+                Err(_) => Err(FiError::new("Enveloped SD-JWT is not valid UTF-8")),
+                Ok(val) => SdJwt::decode(val).map(Envelope::SdJwt),
+            },
+            VC_COSE_TYPE => CoseSign1::decode(data.as_slice()).map(Envelope::Cose),
+            _ => Err(FiError::new(
+                format!("Enveloped media type {} is not supported", media_type).as_str(),
+            )),
+        }
+    }
+
+    /// Credential inside the envelope, read without verifying it.
+    pub fn get_credential(&self) -> Result<VC, FiError> {
+        let value = match self.decode() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(Envelope::Jwt(val)) => val.get_payload().clone(),
+            Ok(Envelope::SdJwt(val)) => match val.get_claims() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+            Ok(Envelope::Cose(val)) => {
+                if val.get_content_type() != Some(VC_CONTENT_TYPE) {
+                    return Err(FiError::new(
+                        format!("COSE content type must be {}", VC_CONTENT_TYPE).as_str(),
+                    ));
+                }
+
+                match serde_json::from_slice(val.get_payload().as_deref().unwrap_or_default()) {
+                    Ok(val) => val,
+                    Err(error) => {
+                        eprintln!("{}", error);
+                        return Err(FiError::new("COSE payload is not valid JSON"));
+                    }
+                }
+            }
+        };
+
+        VC::from(value)
+    }
+
+    /// The `kid` naming the verification method of the issuer's key.
+    pub fn get_verification_method(&self) -> Result<String, FiError> {
+        let kid = match self.decode() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(Envelope::Jwt(val)) => val.get_kid().map(String::from),
+            Ok(Envelope::SdJwt(val)) => val.get_jwt().get_kid().map(String::from),
+            Ok(Envelope::Cose(val)) => val
+                .get_kid()
+                .and_then(|val| String::from_utf8(val.to_vec()).ok()),
+        };
+
+        match kid {
+            None => Err(FiError::new("Enveloped credential has no kid")),
+            Some(val) => Ok(val),
+        }
+    }
+
+    pub fn verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        match self.decode() {
+            Err(error) => Err(error),
+            Ok(Envelope::Jwt(val)) => val.verify(doc),
+            Ok(Envelope::SdJwt(val)) => match val.get_claims() {
+                // Disclosed claims are only placed where the signed payload holds their digest,
+                // and a key binding JWT must verify against the holder key in `cnf`
+                Err(error) => Err(error),
+                Ok(_) => val.get_jwt().verify(doc),
+            },
+            Ok(Envelope::Cose(val)) => val.verify(doc, None),
+        }
+    }
+}
@@ -0,0 +1,571 @@
+use std::collections::{HashMap, HashSet};
+
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use fi_digital_signatures::algorithms::Algorithm;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    document::PublicVerificationDocument, error::FiError, keys::KeyType, proof::verify_signature,
+    signer::Signer,
+};
+
+/// `typ` of a credential secured as a JWT, the payload is the credential itself.
+pub const VC_JWT_TYPE: &str = "vc+jwt";
+/// `typ` of a presentation secured as a JWT, the payload is the presentation itself.
+pub const VP_JWT_TYPE: &str = "vp+jwt";
+
+const SD_DIGEST_ALGORITHM: &str = "sha-256";
+/// `typ` of the key binding JWT a holder appends to an SD-JWT.
+pub const KB_JWT_TYPE: &str = "kb+jwt";
+
+/// Compact JWS with a JSON payload.
+#[derive(Clone, Debug)]
+pub struct Jwt {
+    header: Value,
+    payload: Value,
+    signing_input: String,
+    signature: String,
+}
+
+impl Jwt {
+    /// Signs the payload, naming the signer's verification method as the `kid`.
+    pub fn sign(payload: &Value, typ: &str, signer: &dyn Signer) -> Result<String, FiError> {
+        let header = json!({
+            "alg": signer.algorithm().to_str(),
+            "typ": typ,
+            "kid": signer.verification_method(),
+        });
+
+        Jwt::sign_with_header(&header, payload, signer)
+    }
+
+    /// Signs the payload with the signer's X.509 certificate chain, the signer's certificate
+    /// first, in the `x5c` header.
+    pub fn sign_with_x5c(
+        payload: &Value,
+        typ: &str,
+        signer: &dyn Signer,
+        x5c: &[Vec<u8>],
+    ) -> Result<String, FiError> {
+        let header = json!({
+            "alg": signer.algorithm().to_str(),
+            "typ": typ,
+            "kid": signer.verification_method(),
+            "x5c": x5c.iter().map(|val| STANDARD.encode(val)).collect::<Vec<String>>(),
+        });
+
+        Jwt::sign_with_header(&header, payload, signer)
+    }
+
+    fn sign_with_header(
+        header: &Value,
+        payload: &Value,
+        signer: &dyn Signer,
+    ) -> Result<String, FiError> {
+        let signing_input = format!("{}.{}", encode_json(header), encode_json(payload));
+
+        let signature = match signer.sign(signing_input.as_bytes()) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        return Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature)
+        ));
+    }
+
+    pub fn decode(token: &str) -> Result<Jwt, FiError> {
+        let parts: Vec<&str> = token.trim().split('.').collect();
+        if parts.len() != 3 {
+            return Err(FiError::new("JWT must consist of three parts"));
+        }
+
+        let header = match decode_json(parts[0]) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        if !header.is_object() {
+            return Err(FiError::new("JWT header must be an object"));
+        }
+
+        let payload = match decode_json(parts[1]) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        return Ok(Jwt {
+            header,
+            payload,
+            signing_input: format!("{}.{}", parts[0], parts[1]),
+            signature: String::from(parts[2]),
+        });
+    }
+
+    /// Verifies the signature with the document's public key. Only asymmetric algorithms are
+    /// accepted, so a public key can never be used as an HMAC secret, and the `alg` header must
+    /// match the key type when the document has one.
+    pub fn verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        let alg = match self.get_algorithm() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let key_type = match KeyType::from_algorithm(alg) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        // A key of unknown type verifies nothing
+        if doc.key_type() != Some(key_type) {
+            return Err(FiError::new(
+                "JWT algorithm does not match the verification key",
+            ));
+        }
+
+        verify_signature(doc, alg, self.signing_input.clone(), self.signature.clone())
+    }
+
+    /// Verifies the signature only when the `alg` header is in `allowed`.
+    pub fn verify_with_algorithms(
+        &self,
+        doc: &PublicVerificationDocument,
+        allowed: &[Algorithm],
+    ) -> Result<bool, FiError> {
+        let alg = match self.get_algorithm() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        if !allowed.contains(&alg) {
+            return Err(FiError::new(
+                format!("Algorithm {} is not allowed", alg.to_str()).as_str(),
+            ));
+        }
+
+        self.verify(doc)
+    }
+
+    pub fn get_algorithm(&self) -> Result<Algorithm, FiError> {
+        match self.header["alg"].as_str().and_then(Algorithm::from_str) {
+            None => Err(FiError::new("JWT algorithm is missing or not supported")),
+            Some(val) => Ok(val),
+        }
+    }
+
+    pub fn get_kid(&self) -> Option<&str> {
+        self.header["kid"].as_str()
+    }
+
+    /// DER encoded certificates of the `x5c` header, the signer's certificate first.
+    pub fn get_x5c(&self) -> Vec<Vec<u8>> {
+        match self.header["x5c"].as_array() {
+            None => Vec::new(),
+            Some(val) => val
+                .iter()
+                .filter_map(|item| item.as_str())
+                .filter_map(|item| STANDARD.decode(item).ok())
+                .collect(),
+        }
+    }
+
+    pub fn get_type(&self) -> Option<&str> {
+        self.header["typ"].as_str()
+    }
+
+    pub fn get_header(&self) -> &Value {
+        &self.header
+    }
+
+    pub fn get_payload(&self) -> &Value {
+        &self.payload
+    }
+}
+
+/// Claim an SD-JWT issuer left out of the signed payload, revealed by the holder.
+#[derive(Clone, Debug)]
+pub struct Disclosure {
+    encoded: String,
+    digest: String,
+    salt: String,
+    name: Option<String>,
+    value: Value,
+}
+
+impl Disclosure {
+    pub fn decode(encoded: &str) -> Result<Disclosure, FiError> {
+        let parts = match decode_json(encoded) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        // `[salt, name, value]` for object properties and `[salt, value]` for array elements
+        let (salt, name, value) = match parts.as_array().map(|val| val.as_slice()) {
+            Some([salt, name, value]) => (salt, Some(name), value),
+            Some([salt, value]) => (salt, None, value),
+            _ => return Err(FiError::new("Disclosure must be an array of two or three")),
+        };
+
+        let salt = match salt.as_str() {
+            None => return Err(FiError::new("Disclosure salt must be a string")),
+            Some(val) => String::from(val),
+        };
+
+        let name = match name.map(|val| val.as_str()) {
+            None => None,
+            Some(None) => return Err(FiError::new("Disclosure claim name must be a string")),
+            Some(Some(val)) => Some(String::from(val)),
+        };
+
+        return Ok(Disclosure {
+            encoded: String::from(encoded),
+            digest: URL_SAFE_NO_PAD.encode(Sha256::digest(encoded.as_bytes())),
+            salt,
+            name,
+            value: value.clone(),
+        });
+    }
+
+    pub fn get_encoded(&self) -> &String {
+        &self.encoded
+    }
+
+    pub fn get_digest(&self) -> &String {
+        &self.digest
+    }
+
+    pub fn get_salt(&self) -> &String {
+        &self.salt
+    }
+
+    pub fn get_name(&self) -> &Option<String> {
+        &self.name
+    }
+
+    pub fn get_value(&self) -> &Value {
+        &self.value
+    }
+}
+
+/// SD-JWT in its `<jwt>~<disclosure>~...~<key binding jwt>` serialization.
+#[derive(Clone, Debug)]
+pub struct SdJwt {
+    jwt: Jwt,
+    disclosures: Vec<Disclosure>,
+    key_binding_jwt: Option<Jwt>,
+    sd_hash: String,
+}
+
+impl SdJwt {
+    pub fn decode(token: &str) -> Result<SdJwt, FiError> {
+        let parts: Vec<&str> = token.trim().split('~').collect();
+        if parts.len() < 2 {
+            return Err(FiError::new("SD-JWT must contain at least one '~'"));
+        }
+
+        let jwt = match Jwt::decode(parts[0]) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let mut disclosures = Vec::new();
+        for part in parts[1..parts.len() - 1].iter() {
+            match Disclosure::decode(part) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => disclosures.push(val),
+            };
+        }
+
+        // The serialization ends with `~` unless a key binding JWT follows
+        let key_binding_jwt = match parts[parts.len() - 1] {
+            "" => None,
+            val => match Jwt::decode(val) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => Some(val),
+            },
+        };
+
+        // The key binding JWT signs the digest of everything before it
+        let presented = format!("{}~", parts[..parts.len() - 1].join("~"));
+
+        return Ok(SdJwt {
+            jwt,
+            disclosures,
+            key_binding_jwt,
+            sd_hash: URL_SAFE_NO_PAD.encode(Sha256::digest(presented.as_bytes())),
+        });
+    }
+
+    /// Payload of the issuer JWT with the disclosed claims put in place of their digests and the
+    /// digests of undisclosed claims removed. Fails on repeated disclosures, disclosures the
+    /// payload does not reference, and key binding JWTs that do not verify.
+    pub fn get_claims(&self) -> Result<Value, FiError> {
+        match self.verify_key_binding(None, None) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let mut claims = self.jwt.get_payload().clone();
+
+        match claims.as_object_mut() {
+            None => return Err(FiError::new("SD-JWT payload must be an object")),
+            Some(val) => {
+                if let Some(alg) = val.remove("_sd_alg") {
+                    if alg != SD_DIGEST_ALGORITHM {
+                        return Err(FiError::new("SD-JWT digest algorithm is not supported"));
+                    }
+                }
+            }
+        };
+
+        let mut disclosures: HashMap<&str, &Disclosure> = HashMap::new();
+        for disclosure in self.disclosures.iter() {
+            if disclosures
+                .insert(disclosure.digest.as_str(), disclosure)
+                .is_some()
+            {
+                return Err(FiError::new("Disclosure is repeated"));
+            }
+        }
+
+        let mut used = HashSet::new();
+        match disclose(&mut claims, &disclosures, &mut used) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        if used.len() != disclosures.len() {
+            return Err(FiError::new("Disclosure is not referenced by the SD-JWT"));
+        }
+
+        return Ok(claims);
+    }
+
+    /// Checks the key binding JWT against the holder key in the issuer's `cnf` claim: its
+    /// signature, `sd_hash` and, when given, `aud` and `nonce`. Without a `cnf` claim the SD-JWT
+    /// must not carry a key binding JWT.
+    pub fn verify_key_binding(
+        &self,
+        audience: Option<&str>,
+        nonce: Option<&str>,
+    ) -> Result<(), FiError> {
+        let confirmation = &self.jwt.get_payload()["cnf"];
+        let key_binding_jwt = match (confirmation.is_null(), self.key_binding_jwt.as_ref()) {
+            (true, None) => return Ok(()),
+            (true, Some(_)) => {
+                return Err(FiError::new(
+                    "Key binding JWT given for an SD-JWT without a cnf claim",
+                ))
+            }
+            (false, None) => return Err(FiError::new("SD-JWT requires a key binding JWT")),
+            (false, Some(val)) => val,
+        };
+
+        if key_binding_jwt.get_type() != Some(KB_JWT_TYPE) {
+            return Err(FiError::new(
+                format!("Key binding JWT typ must be {}", KB_JWT_TYPE).as_str(),
+            ));
+        }
+
+        let holder = match confirmation.get("jwk") {
+            None => {
+                return Err(FiError::new(
+                    "Key binding JWT can only be verified against a cnf jwk",
+                ))
+            }
+            Some(val) => match PublicVerificationDocument::from_jwk(String::new(), val) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        };
+
+        match key_binding_jwt.verify(&holder) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(false) => return Err(FiError::new("Key binding JWT signature does not match")),
+            Ok(true) => {}
+        };
+
+        let claims = key_binding_jwt.get_payload();
+        if claims["sd_hash"] != self.sd_hash.as_str() {
+            return Err(FiError::new("Key binding JWT sd_hash does not match"));
+        }
+
+        match (claims["aud"].as_str(), audience) {
+            (None, _) => return Err(FiError::new("Key binding JWT has no aud")),
+            (Some(val), Some(expected)) if val != expected => {
+                return Err(FiError::new("Key binding JWT audience does not match"))
+            }
+            _ => {}
+        };
+
+        match (claims["nonce"].as_str(), nonce) {
+            (None, _) => return Err(FiError::new("Key binding JWT has no nonce")),
+            (Some(val), Some(expected)) if val != expected => {
+                return Err(FiError::new("Key binding JWT nonce does not match"))
+            }
+            _ => {}
+        };
+
+        return Ok(());
+    }
+
+    pub fn get_jwt(&self) -> &Jwt {
+        &self.jwt
+    }
+
+    pub fn get_disclosures(&self) -> &Vec<Disclosure> {
+        &self.disclosures
+    }
+
+    pub fn get_key_binding_jwt(&self) -> &Option<Jwt> {
+        &self.key_binding_jwt
+    }
+}
+
+fn disclose<'a>(
+    value: &mut Value,
+    disclosures: &HashMap<&'a str, &'a Disclosure>,
+    used: &mut HashSet<&'a str>,
+) -> Result<(), FiError> {
+    match value {
+        Value::Object(map) => {
+            if let Some(digests) = map.remove("_sd") {
+                let digests = match digests.as_array() {
+                    None => return Err(FiError::new("_sd must be an array")),
+                    Some(val) => val.clone(),
+                };
+
+                for digest in digests.iter() {
+                    let disclosure = match digest.as_str().and_then(|val| disclosures.get(val)) {
+                        None => continue,
+                        Some(val) => val,
+                    };
+
+                    if !used.insert(disclosure.digest.as_str()) {
+                        return Err(FiError::new("Disclosure digest is referenced twice"));
+                    }
+
+                    let name = match disclosure.name.as_ref() {
+                        None => return Err(FiError::new("Array disclosure used for a property")),
+                        Some(val) => val,
+                    };
+
+                    if map.contains_key(name) {
+                        return Err(FiError::new("Disclosed claim is already present"));
+                    }
+
+                    map.insert(name.clone(), disclosure.value.clone());
+                }
+            }
+
+            for item in map.values_mut() {
+                match disclose(item, disclosures, used) {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                };
+            }
+        }
+        Value::Array(items) => {
+            let mut disclosed = Vec::new();
+
+            for item in items.drain(..) {
+                let digest = match item.as_object() {
+                    Some(obj) if obj.len() == 1 && obj.contains_key("...") => obj["..."].clone(),
+                    _ => {
+                        disclosed.push(item);
+                        continue;
+                    }
+                };
+
+                let disclosure = match digest.as_str().and_then(|val| disclosures.get(val)) {
+                    None => continue,
+                    Some(val) => val,
+                };
+
+                if !used.insert(disclosure.digest.as_str()) {
+                    return Err(FiError::new("Disclosure digest is referenced twice"));
+                }
+
+                if disclosure.name.is_some() {
+                    return Err(FiError::new(
+                        "Property disclosure used for an array element",
+                    ));
+                }
+
+                disclosed.push(disclosure.value.clone());
+            }
+
+            for item in disclosed.iter_mut() {
+                match disclose(item, disclosures, used) {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                };
+            }
+
+            *items = disclosed;
+        }
+        _ => {}
+    };
+
+    return Ok(());
+}
+
+fn encode_json(value: &Value) -> String {
+    URL_SAFE_NO_PAD.encode(value.to_string())
+}
+
+fn decode_json(value: &str) -> Result<Value, FiError> {
+    let bytes = match URL_SAFE_NO_PAD.decode(value) {
+        Ok(val) => val,
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Value is not base64url encoded"));
+        }
+    };
+
+    match serde_json::from_slice(bytes.as_slice()) {
+        Ok(val) => Ok(val),
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Value is not valid JSON"));
+        }
+    }
+}
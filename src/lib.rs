@@ -1,6 +1,28 @@
+pub mod batch;
+pub mod bbs;
+pub mod cache;
 pub mod constants;
+pub mod cose;
+pub mod dcql;
 pub mod document;
+pub mod ecdsa_sd;
+pub mod enveloped;
 pub mod error;
+pub mod jose;
+pub mod keys;
+pub mod mdoc;
+pub mod oauth;
+pub mod oid4vci;
+pub mod oid4vp;
+pub mod policy;
+pub mod presentation_exchange;
 pub mod proof;
+pub mod refresh;
+pub mod signer;
+mod statements;
+pub mod streaming;
 pub mod vc;
 pub mod vp;
+
+#[cfg(feature = "wasm")]
+mod wasm;
@@ -0,0 +1,493 @@
+//! ISO/IEC 18013-5 mobile documents (mdoc), e.g. mobile driving licences.
+//!
+//! Only issuer data authentication is covered: the issuer's COSE_Sign1 over the mobile
+//! security object (MSO), the MSO digests of the disclosed elements and its validity period.
+//! Device authentication and certificate chain validation are left to the caller.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use ciborium::Value;
+use serde_json::{json, Map};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::{
+    cose::{
+        cbor_to_json, decode_cbor, decode_embedded, encode_cbor, integer, map_text, CoseSign1,
+        ENCODED_CBOR_TAG,
+    },
+    document::PublicVerificationDocument,
+    error::FiError,
+};
+
+/// Document type of a mobile driving licence.
+pub const MDL_DOC_TYPE: &str = "org.iso.18013.5.1.mDL";
+
+/// Data element disclosed by the holder, with its encoding the MSO digest is computed over.
+#[derive(Clone, Debug)]
+pub struct IssuerSignedItem {
+    digest_id: u64,
+    element_identifier: String,
+    element_value: Value,
+    bytes: Vec<u8>,
+}
+
+impl IssuerSignedItem {
+    fn from_cbor(value: &Value) -> Result<IssuerSignedItem, FiError> {
+        let item = match decode_embedded(value) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        // The digest covers the tagged byte string as issued
+        let bytes = match value {
+            Value::Tag(_, _) => encode_cbor(value),
+            _ => encode_cbor(&Value::Tag(ENCODED_CBOR_TAG, Box::new(value.clone()))),
+        };
+
+        let digest_id = match map_text(&item, "digestID")
+            .and_then(integer)
+            .and_then(|val| u64::try_from(val).ok())
+        {
+            None => return Err(FiError::new("IssuerSignedItem has no digestID")),
+            Some(val) => val,
+        };
+
+        let element_identifier = match map_text(&item, "elementIdentifier").and_then(Value::as_text)
+        {
+            None => return Err(FiError::new("IssuerSignedItem has no elementIdentifier")),
+            Some(val) => String::from(val),
+        };
+
+        return Ok(IssuerSignedItem {
+            digest_id,
+            element_identifier,
+            element_value: match map_text(&item, "elementValue") {
+                None => return Err(FiError::new("IssuerSignedItem has no elementValue")),
+                Some(val) => val.clone(),
+            },
+            bytes: match bytes {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        });
+    }
+
+    pub fn get_digest_id(&self) -> u64 {
+        self.digest_id
+    }
+
+    pub fn get_element_identifier(&self) -> &String {
+        &self.element_identifier
+    }
+
+    pub fn get_element_value(&self) -> &Value {
+        &self.element_value
+    }
+
+    /// Element value as JSON, byte strings are base64url encoded.
+    pub fn get_json_value(&self) -> serde_json::Value {
+        cbor_to_json(&self.element_value)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidityInfo {
+    signed: DateTime<Utc>,
+    valid_from: DateTime<Utc>,
+    valid_until: DateTime<Utc>,
+}
+
+impl ValidityInfo {
+    fn from_cbor(value: &Value) -> Result<ValidityInfo, FiError> {
+        let date = |name: &str| match map_text(value, name).map(|val| match val {
+            Value::Tag(_, val) => val.as_ref(),
+            val => val,
+        }) {
+            Some(Value::Text(val)) => match DateTime::parse_from_rfc3339(val) {
+                Ok(val) => Ok(val.with_timezone(&Utc)),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    Err(FiError::new("MSO validity date is not RFC 3339"))
+                }
+            },
+            _ => Err(FiError::new("MSO validity info is incomplete")),
+        };
+
+        return Ok(ValidityInfo {
+            signed: match date("signed") {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+            valid_from: match date("validFrom") {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+            valid_until: match date("validUntil") {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        });
+    }
+
+    pub fn get_signed(&self) -> &DateTime<Utc> {
+        &self.signed
+    }
+
+    pub fn get_valid_from(&self) -> &DateTime<Utc> {
+        &self.valid_from
+    }
+
+    pub fn get_valid_until(&self) -> &DateTime<Utc> {
+        &self.valid_until
+    }
+}
+
+/// Mobile security object, the issuer signed digests of every data element.
+#[derive(Clone, Debug)]
+pub struct MobileSecurityObject {
+    digest_algorithm: String,
+    value_digests: HashMap<String, HashMap<u64, Vec<u8>>>,
+    doc_type: String,
+    validity_info: ValidityInfo,
+}
+
+impl MobileSecurityObject {
+    fn from_cbor(value: &Value) -> Result<MobileSecurityObject, FiError> {
+        let digest_algorithm = match map_text(value, "digestAlgorithm").and_then(Value::as_text) {
+            None => return Err(FiError::new("MSO has no digestAlgorithm")),
+            Some(val) => String::from(val),
+        };
+
+        let doc_type = match map_text(value, "docType").and_then(Value::as_text) {
+            None => return Err(FiError::new("MSO has no docType")),
+            Some(val) => String::from(val),
+        };
+
+        let mut value_digests = HashMap::new();
+        for (name_space, digests) in map_text(value, "valueDigests")
+            .and_then(Value::as_map)
+            .map(|val| val.as_slice())
+            .unwrap_or_default()
+        {
+            let name_space = match name_space.as_text() {
+                None => return Err(FiError::new("MSO name space must be a string")),
+                Some(val) => String::from(val),
+            };
+
+            let mut ids = HashMap::new();
+            for (id, digest) in digests
+                .as_map()
+                .map(|val| val.as_slice())
+                .unwrap_or_default()
+            {
+                match (
+                    integer(id).and_then(|val| u64::try_from(val).ok()),
+                    digest.as_bytes(),
+                ) {
+                    (Some(id), Some(digest)) => ids.insert(id, digest.clone()),
+                    _ => return Err(FiError::new("MSO value digests are malformed")),
+                };
+            }
+
+            value_digests.insert(name_space, ids);
+        }
+
+        let validity_info = match map_text(value, "validityInfo") {
+            None => return Err(FiError::new("MSO has no validityInfo")),
+            Some(val) => match ValidityInfo::from_cbor(val) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        };
+
+        return Ok(MobileSecurityObject {
+            digest_algorithm,
+            value_digests,
+            doc_type,
+            validity_info,
+        });
+    }
+
+    fn digest(&self, bytes: &[u8]) -> Result<Vec<u8>, FiError> {
+        match self.digest_algorithm.as_str() {
+            "SHA-256" => Ok(Sha256::digest(bytes).to_vec()),
+            "SHA-384" => Ok(Sha384::digest(bytes).to_vec()),
+            "SHA-512" => Ok(Sha512::digest(bytes).to_vec()),
+            _ => Err(FiError::new("MSO digest algorithm is not supported")),
+        }
+    }
+
+    pub fn get_digest_algorithm(&self) -> &String {
+        &self.digest_algorithm
+    }
+
+    pub fn get_doc_type(&self) -> &String {
+        &self.doc_type
+    }
+
+    pub fn get_validity_info(&self) -> &ValidityInfo {
+        &self.validity_info
+    }
+}
+
+/// Issuer signed mobile document with the data elements the holder disclosed.
+#[derive(Clone, Debug)]
+pub struct Mdoc {
+    doc_type: String,
+    name_spaces: BTreeMap<String, Vec<IssuerSignedItem>>,
+    issuer_auth: CoseSign1,
+    mso: MobileSecurityObject,
+}
+
+impl Mdoc {
+    /// Reads a CBOR encoded `IssuerSigned` structure, the document type is taken from the MSO.
+    pub fn decode_issuer_signed(bytes: &[u8]) -> Result<Mdoc, FiError> {
+        match decode_cbor(bytes) {
+            Err(error) => Err(error),
+            Ok(val) => Mdoc::from_issuer_signed(None, &val),
+        }
+    }
+
+    fn from_issuer_signed(doc_type: Option<String>, value: &Value) -> Result<Mdoc, FiError> {
+        let issuer_auth = match map_text(value, "issuerAuth") {
+            None => return Err(FiError::new("IssuerSigned has no issuerAuth")),
+            Some(val) => match CoseSign1::from_cbor(val.clone()) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+        };
+
+        let mso = match issuer_auth.get_payload() {
+            None => return Err(FiError::new("issuerAuth has no payload")),
+            Some(val) => match decode_cbor(val.as_slice()).and_then(|val| match val {
+                Value::Tag(_, _) => decode_embedded(&val),
+                val => Ok(val),
+            }) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => match MobileSecurityObject::from_cbor(&val) {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                },
+            },
+        };
+
+        let mut name_spaces = BTreeMap::new();
+        for (name_space, items) in map_text(value, "nameSpaces")
+            .and_then(Value::as_map)
+            .map(|val| val.as_slice())
+            .unwrap_or_default()
+        {
+            let name_space = match name_space.as_text() {
+                None => return Err(FiError::new("Name space must be a string")),
+                Some(val) => String::from(val),
+            };
+
+            let mut signed_items = Vec::new();
+            for item in items
+                .as_array()
+                .map(|val| val.as_slice())
+                .unwrap_or_default()
+            {
+                match IssuerSignedItem::from_cbor(item) {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => signed_items.push(val),
+                };
+            }
+
+            name_spaces.insert(name_space, signed_items);
+        }
+
+        return Ok(Mdoc {
+            doc_type: doc_type.unwrap_or(mso.doc_type.clone()),
+            name_spaces,
+            issuer_auth,
+            mso,
+        });
+    }
+
+    /// Verifies the issuer signature with the document's key, the digests of the disclosed
+    /// elements and that the MSO is valid now.
+    pub fn verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        self.verify_at(doc, Utc::now())
+    }
+
+    pub fn verify_at(
+        &self,
+        doc: &PublicVerificationDocument,
+        time: DateTime<Utc>,
+    ) -> Result<bool, FiError> {
+        match self.issuer_auth.verify(doc, None) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(false) => return Ok(false),
+            Ok(true) => {}
+        };
+
+        if self.mso.doc_type != self.doc_type {
+            return Ok(false);
+        }
+
+        let validity = &self.mso.validity_info;
+        if time < validity.valid_from || time > validity.valid_until {
+            return Ok(false);
+        }
+
+        for (name_space, items) in self.name_spaces.iter() {
+            let digests = match self.mso.value_digests.get(name_space) {
+                None => return Ok(false),
+                Some(val) => val,
+            };
+
+            for item in items.iter() {
+                let digest = match self.mso.digest(item.bytes.as_slice()) {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                };
+
+                if digests.get(&item.digest_id) != Some(&digest) {
+                    return Ok(false);
+                }
+            }
+        }
+
+        return Ok(true);
+    }
+
+    /// Disclosed elements as `{ name space: { element identifier: value } }`.
+    pub fn get_claims(&self) -> serde_json::Value {
+        let mut claims = Map::new();
+
+        for (name_space, items) in self.name_spaces.iter() {
+            let elements: Map<String, serde_json::Value> = items
+                .iter()
+                .map(|item| (item.element_identifier.clone(), item.get_json_value()))
+                .collect();
+            claims.insert(name_space.clone(), serde_json::Value::Object(elements));
+        }
+
+        return serde_json::Value::Object(claims);
+    }
+
+    /// Credential shaped JSON view of the disclosed elements, for code written against `VC`
+    /// objects. It carries no proof, verify the mdoc itself.
+    pub fn to_object(&self) -> serde_json::Value {
+        let validity = &self.mso.validity_info;
+
+        return json!({
+            "type": ["VerifiableCredential", self.doc_type],
+            "validFrom": validity.valid_from.to_rfc3339(),
+            "validUntil": validity.valid_until.to_rfc3339(),
+            "credentialSubject": self.get_claims(),
+        });
+    }
+
+    pub fn get_doc_type(&self) -> &String {
+        &self.doc_type
+    }
+
+    pub fn get_name_spaces(&self) -> &BTreeMap<String, Vec<IssuerSignedItem>> {
+        &self.name_spaces
+    }
+
+    pub fn get_issuer_auth(&self) -> &CoseSign1 {
+        &self.issuer_auth
+    }
+
+    pub fn get_mso(&self) -> &MobileSecurityObject {
+        &self.mso
+    }
+}
+
+/// `DeviceResponse` returned by an mdoc holder.
+#[derive(Clone, Debug)]
+pub struct DeviceResponse {
+    version: String,
+    documents: Vec<Mdoc>,
+    status: u64,
+}
+
+impl DeviceResponse {
+    pub fn decode(bytes: &[u8]) -> Result<DeviceResponse, FiError> {
+        let value = match decode_cbor(bytes) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let mut documents = Vec::new();
+        for document in map_text(&value, "documents")
+            .and_then(Value::as_array)
+            .map(|val| val.as_slice())
+            .unwrap_or_default()
+        {
+            let doc_type = match map_text(document, "docType").and_then(Value::as_text) {
+                None => return Err(FiError::new("Document has no docType")),
+                Some(val) => String::from(val),
+            };
+
+            let issuer_signed = match map_text(document, "issuerSigned") {
+                None => return Err(FiError::new("Document has no issuerSigned")),
+                Some(val) => val,
+            };
+
+            match Mdoc::from_issuer_signed(Some(doc_type), issuer_signed) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => documents.push(val),
+            };
+        }
+
+        return Ok(DeviceResponse {
+            version: match map_text(&value, "version").and_then(Value::as_text) {
+                None => return Err(FiError::new("DeviceResponse has no version")),
+                Some(val) => String::from(val),
+            },
+            documents,
+            status: match map_text(&value, "status")
+                .and_then(integer)
+                .and_then(|val| u64::try_from(val).ok())
+            {
+                None => return Err(FiError::new("DeviceResponse has no status")),
+                Some(val) => val,
+            },
+        });
+    }
+
+    pub fn get_version(&self) -> &String {
+        &self.version
+    }
+
+    pub fn get_documents(&self) -> &Vec<Mdoc> {
+        &self.documents
+    }
+
+    pub fn get_status(&self) -> u64 {
+        self.status
+    }
+}
@@ -0,0 +1,128 @@
+//! Helpers shared by the OpenID for Verifiable Credentials flows.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+
+use crate::error::FiError;
+
+/// Random value for codes, tokens and nonces.
+pub(crate) fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Random version 4 UUID as a `urn:uuid` identifier.
+pub fn random_urn() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    // Version 4, variant 1
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = hex::encode(bytes);
+    return format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    );
+}
+
+pub(crate) fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+pub(crate) fn percent_decode(value: &str) -> Result<String, FiError> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let byte = bytes
+                    .get(index + 1..index + 3)
+                    .and_then(|val| std::str::from_utf8(val).ok())
+                    .and_then(|val| u8::from_str_radix(val, 16).ok());
+                match byte {
+                    None => return Err(FiError::new("Invalid percent encoding")),
+                    Some(val) => decoded.push(val),
+                };
+                index += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            val => {
+                decoded.push(val);
+                index += 1;
+            }
+        };
+    }
+
+    match String::from_utf8(decoded) {
+        Ok(val) => Ok(val),
+        Err(_) => Err(FiError::new("Percent encoded value is not valid UTF-8")),
+    }
+}
+
+/// `application/x-www-form-urlencoded` serialization of the parameters.
+pub(crate) fn encode_query(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", percent_encode(name), percent_encode(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Decoded parameters of a query string, or of the query of a URI.
+pub(crate) fn decode_query(value: &str) -> Result<Vec<(String, String)>, FiError> {
+    let query = match value.split_once('?') {
+        None => value,
+        Some((_, val)) => val,
+    };
+
+    let mut params = Vec::new();
+    for pair in query.split('&').filter(|val| !val.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+        let name = match percent_decode(name) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        match percent_decode(value) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => params.push((name, val)),
+        };
+    }
+
+    return Ok(params);
+}
+
+pub(crate) fn query_param(value: &str, name: &str) -> Result<Option<String>, FiError> {
+    match decode_query(value) {
+        Err(error) => Err(error),
+        Ok(val) => Ok(val
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)),
+    }
+}
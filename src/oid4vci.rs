@@ -0,0 +1,686 @@
+//! OpenID for Verifiable Credential Issuance with the pre-authorized code flow.
+//!
+//! [`CredentialIssuer`] implements the token and credential endpoints in process, leaving the
+//! HTTP transport to the caller. Errors carry the OID4VCI error code before the description,
+//! e.g. `invalid_proof: ...`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    document::DocumentLoader,
+    error::FiError,
+    jose::Jwt,
+    oauth::{percent_encode, query_param, random_token, random_urn},
+    proof::FiProof,
+    signer::Signer,
+    vc::VC,
+};
+
+pub const PRE_AUTHORIZED_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:pre-authorized_code";
+/// `typ` of the wallet's key proof JWT.
+pub const PROOF_JWT_TYPE: &str = "openid4vci-proof+jwt";
+pub const JWT_VC_JSON_FORMAT: &str = "jwt_vc_json";
+pub const LDP_VC_FORMAT: &str = "ldp_vc";
+pub const MSO_MDOC_FORMAT: &str = "mso_mdoc";
+
+const CREDENTIAL_OFFER_URI: &str = "openid-credential-offer://?credential_offer=";
+const ACCESS_TOKEN_LIFETIME_SECONDS: i64 = 300;
+/// Accepted age of a key proof, and how far its `iat` may be in the future.
+const PROOF_MAX_AGE_SECONDS: i64 = 300;
+const PROOF_CLOCK_SKEW_SECONDS: i64 = 60;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreAuthorizedCodeGrant {
+    #[serde(rename = "pre-authorized_code")]
+    pre_authorized_code: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Grants {
+    #[serde(
+        rename = "urn:ietf:params:oauth:grant-type:pre-authorized_code",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pre_authorized_code: Option<PreAuthorizedCodeGrant>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialOffer {
+    credential_issuer: String,
+    credential_configuration_ids: Vec<String>,
+    #[serde(default)]
+    grants: Grants,
+}
+
+impl CredentialOffer {
+    pub fn new(
+        credential_issuer: String,
+        credential_configuration_ids: Vec<String>,
+        pre_authorized_code: Option<String>,
+    ) -> Self {
+        CredentialOffer {
+            credential_issuer,
+            credential_configuration_ids,
+            grants: Grants {
+                pre_authorized_code: pre_authorized_code.map(|val| PreAuthorizedCodeGrant {
+                    pre_authorized_code: val,
+                }),
+            },
+        }
+    }
+
+    pub fn get_credential_issuer(&self) -> &String {
+        &self.credential_issuer
+    }
+
+    pub fn get_credential_configuration_ids(&self) -> &Vec<String> {
+        &self.credential_configuration_ids
+    }
+
+    pub fn get_pre_authorized_code(&self) -> Option<&String> {
+        self.grants
+            .pre_authorized_code
+            .as_ref()
+            .map(|val| &val.pre_authorized_code)
+    }
+
+    /// Offer passed by value, as shown in a QR code or deep link.
+    pub fn to_uri(&self) -> Result<String, FiError> {
+        match serde_json::to_string(self) {
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Cannot serialize the credential offer"));
+            }
+            Ok(val) => Ok(format!("{}{}", CREDENTIAL_OFFER_URI, percent_encode(&val))),
+        }
+    }
+
+    pub fn from_uri(uri: &str) -> Result<Self, FiError> {
+        let decoded = match query_param(uri, "credential_offer") {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(None) => return Err(FiError::new("URI has no credential_offer parameter")),
+            Ok(Some(val)) => val,
+        };
+
+        match serde_json::from_str(decoded.as_str()) {
+            Ok(val) => Ok(val),
+            Err(error) => Err(FiError::new(error.to_string().as_str())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialDefinition {
+    #[serde(rename = "type")]
+    types: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofTypeMetadata {
+    proof_signing_alg_values_supported: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialConfiguration {
+    format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(default)]
+    cryptographic_binding_methods_supported: Vec<String>,
+    #[serde(default)]
+    credential_signing_alg_values_supported: Vec<String>,
+    #[serde(default)]
+    proof_types_supported: BTreeMap<String, ProofTypeMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credential_definition: Option<CredentialDefinition>,
+}
+
+impl CredentialConfiguration {
+    /// Configuration of a credential of the given types, bound to a `did` key proven with a
+    /// JWT signed by one of the proof algorithms.
+    pub fn new(
+        format: &str,
+        types: Vec<String>,
+        signing_algorithms: Vec<String>,
+        proof_algorithms: Vec<String>,
+    ) -> Self {
+        let mut proof_types_supported = BTreeMap::new();
+        proof_types_supported.insert(
+            String::from("jwt"),
+            ProofTypeMetadata {
+                proof_signing_alg_values_supported: proof_algorithms,
+            },
+        );
+
+        CredentialConfiguration {
+            format: String::from(format),
+            scope: None,
+            cryptographic_binding_methods_supported: vec![String::from("did")],
+            credential_signing_alg_values_supported: signing_algorithms,
+            proof_types_supported,
+            credential_definition: Some(CredentialDefinition { types }),
+        }
+    }
+
+    pub fn get_format(&self) -> &String {
+        &self.format
+    }
+
+    pub fn get_types(&self) -> Vec<String> {
+        match self.credential_definition.as_ref() {
+            None => Vec::new(),
+            Some(val) => val.types.clone(),
+        }
+    }
+
+    pub fn set_scope(&mut self, scope: Option<String>) {
+        self.scope = scope;
+    }
+
+    /// Algorithms a JWT key proof may be signed with, `None` when JWT proofs are not accepted.
+    pub fn get_proof_algorithms(&self) -> Option<&Vec<String>> {
+        self.proof_types_supported
+            .get("jwt")
+            .map(|val| &val.proof_signing_alg_values_supported)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialIssuerMetadata {
+    credential_issuer: String,
+    credential_endpoint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce_endpoint: Option<String>,
+    credential_configurations_supported: BTreeMap<String, CredentialConfiguration>,
+}
+
+impl CredentialIssuerMetadata {
+    pub fn new(credential_issuer: String, credential_endpoint: String) -> Self {
+        CredentialIssuerMetadata {
+            credential_issuer,
+            credential_endpoint,
+            nonce_endpoint: None,
+            credential_configurations_supported: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_configuration(&mut self, id: &str, configuration: CredentialConfiguration) {
+        self.credential_configurations_supported
+            .insert(String::from(id), configuration);
+    }
+
+    pub fn set_nonce_endpoint(&mut self, nonce_endpoint: Option<String>) {
+        self.nonce_endpoint = nonce_endpoint;
+    }
+
+    pub fn get_credential_issuer(&self) -> &String {
+        &self.credential_issuer
+    }
+
+    pub fn get_credential_endpoint(&self) -> &String {
+        &self.credential_endpoint
+    }
+
+    pub fn get_configuration(&self, id: &str) -> Option<&CredentialConfiguration> {
+        self.credential_configurations_supported.get(id)
+    }
+
+    pub fn get_configurations(&self) -> &BTreeMap<String, CredentialConfiguration> {
+        &self.credential_configurations_supported
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenRequest {
+    grant_type: String,
+    #[serde(rename = "pre-authorized_code")]
+    pre_authorized_code: String,
+}
+
+impl TokenRequest {
+    pub fn new(pre_authorized_code: String) -> Self {
+        TokenRequest {
+            grant_type: String::from(PRE_AUTHORIZED_CODE_GRANT),
+            pre_authorized_code,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    access_token: String,
+    token_type: String,
+    expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    c_nonce: Option<String>,
+}
+
+impl TokenResponse {
+    pub fn get_access_token(&self) -> &String {
+        &self.access_token
+    }
+
+    pub fn get_c_nonce(&self) -> &Option<String> {
+        &self.c_nonce
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyProof {
+    proof_type: String,
+    jwt: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialRequest {
+    credential_configuration_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof: Option<KeyProof>,
+}
+
+impl CredentialRequest {
+    pub fn new(credential_configuration_id: String, proof_jwt: Option<String>) -> Self {
+        CredentialRequest {
+            credential_configuration_id,
+            proof: proof_jwt.map(|jwt| KeyProof {
+                proof_type: String::from("jwt"),
+                jwt,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssuedCredential {
+    credential: Value,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialResponse {
+    credentials: Vec<IssuedCredential>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    c_nonce: Option<String>,
+}
+
+impl CredentialResponse {
+    /// Issued credentials, JWT strings or JSON objects depending on the format.
+    pub fn get_credentials(&self) -> Vec<&Value> {
+        self.credentials.iter().map(|val| &val.credential).collect()
+    }
+
+    /// Nonce for the key proof of the next credential request.
+    pub fn get_c_nonce(&self) -> &Option<String> {
+        &self.c_nonce
+    }
+}
+
+/// Creates the wallet's proof of possession of the key the credential will be bound to. The
+/// signer's verification method is the `kid`.
+pub fn create_key_proof(
+    signer: &dyn Signer,
+    credential_issuer: &str,
+    nonce: Option<&str>,
+) -> Result<String, FiError> {
+    let mut claims = json!({
+        "aud": credential_issuer,
+        "iat": Utc::now().timestamp(),
+    });
+
+    if let Some(nonce) = nonce {
+        claims["nonce"] = Value::from(nonce);
+    }
+
+    Jwt::sign(&claims, PROOF_JWT_TYPE, signer)
+}
+
+struct PendingIssuance {
+    configuration_ids: Vec<String>,
+    claims: Value,
+}
+
+struct IssuanceSession {
+    configuration_ids: Vec<String>,
+    claims: Value,
+    c_nonce: String,
+    expires: DateTime<Utc>,
+}
+
+/// Issuer side of the pre-authorized code flow. Holder keys named by key proofs are resolved
+/// through the loader.
+pub struct CredentialIssuer {
+    metadata: CredentialIssuerMetadata,
+    signer: Box<dyn Signer>,
+    loader: DocumentLoader,
+    offers: HashMap<String, PendingIssuance>,
+    sessions: HashMap<String, IssuanceSession>,
+    access_token_lifetime: Duration,
+}
+
+impl CredentialIssuer {
+    pub fn new(
+        metadata: CredentialIssuerMetadata,
+        signer: Box<dyn Signer>,
+        loader: DocumentLoader,
+    ) -> Self {
+        CredentialIssuer {
+            metadata,
+            signer,
+            loader,
+            offers: HashMap::new(),
+            sessions: HashMap::new(),
+            access_token_lifetime: Duration::seconds(ACCESS_TOKEN_LIFETIME_SECONDS),
+        }
+    }
+
+    pub fn get_metadata(&self) -> &CredentialIssuerMetadata {
+        &self.metadata
+    }
+
+    /// How long an access token can be used, five minutes by default.
+    pub fn set_access_token_lifetime(&mut self, lifetime: Duration) {
+        self.access_token_lifetime = lifetime;
+    }
+
+    /// Number of access tokens that can still be redeemed for a credential.
+    pub fn get_session_count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    fn purge_expired_sessions(&mut self) {
+        let now = Utc::now();
+        self.sessions.retain(|_, session| session.expires > now);
+    }
+
+    /// Offers the credentials with a single use pre-authorized code. The claims become the
+    /// credential subject of every issued credential.
+    pub fn create_offer(
+        &mut self,
+        configuration_ids: Vec<String>,
+        claims: Value,
+    ) -> Result<CredentialOffer, FiError> {
+        if let Some(id) = configuration_ids
+            .iter()
+            .find(|id| self.metadata.get_configuration(id).is_none())
+        {
+            return Err(FiError::new(
+                format!("Credential configuration {} is not supported", id).as_str(),
+            ));
+        }
+
+        if !claims.is_object() {
+            return Err(FiError::new("Credential claims must be an object"));
+        }
+
+        let code = random_token();
+        self.offers.insert(
+            code.clone(),
+            PendingIssuance {
+                configuration_ids: configuration_ids.clone(),
+                claims,
+            },
+        );
+
+        return Ok(CredentialOffer::new(
+            self.metadata.credential_issuer.clone(),
+            configuration_ids,
+            Some(code),
+        ));
+    }
+
+    /// Token endpoint, exchanging the pre-authorized code for an access token and a `c_nonce`.
+    pub fn token(&mut self, request: &TokenRequest) -> Result<TokenResponse, FiError> {
+        if request.grant_type != PRE_AUTHORIZED_CODE_GRANT {
+            return Err(FiError::new(
+                "unsupported_grant_type: Only the pre-authorized code grant is supported",
+            ));
+        }
+
+        let pending = match self.offers.remove(&request.pre_authorized_code) {
+            None => {
+                return Err(FiError::new(
+                    "invalid_grant: The pre-authorized code is unknown or was already used",
+                ))
+            }
+            Some(val) => val,
+        };
+
+        self.purge_expired_sessions();
+
+        let access_token = random_token();
+        let c_nonce = random_token();
+        self.sessions.insert(
+            access_token.clone(),
+            IssuanceSession {
+                configuration_ids: pending.configuration_ids,
+                claims: pending.claims,
+                c_nonce: c_nonce.clone(),
+                expires: Utc::now() + self.access_token_lifetime,
+            },
+        );
+
+        return Ok(TokenResponse {
+            access_token,
+            token_type: String::from("Bearer"),
+            expires_in: self.access_token_lifetime.num_seconds(),
+            c_nonce: Some(c_nonce),
+        });
+    }
+
+    /// Credential endpoint. The key proof must be signed for this issuer with the latest
+    /// `c_nonce`, and the credential subject is the DID of the proven key. Each offered
+    /// credential is issued once, and the access token is spent with the last of them.
+    pub fn credential(
+        &mut self,
+        access_token: &str,
+        request: &CredentialRequest,
+    ) -> Result<CredentialResponse, FiError> {
+        self.purge_expired_sessions();
+
+        let session = match self.sessions.get_mut(access_token) {
+            Some(val) => val,
+            _ => {
+                return Err(FiError::new(
+                    "invalid_token: The access token is unknown or expired",
+                ))
+            }
+        };
+
+        if !session
+            .configuration_ids
+            .contains(&request.credential_configuration_id)
+        {
+            return Err(FiError::new(
+                "invalid_credential_request: The credential was not offered",
+            ));
+        }
+
+        let configuration = match self
+            .metadata
+            .get_configuration(request.credential_configuration_id.as_str())
+        {
+            None => return Err(FiError::new(
+                "unknown_credential_configuration: The credential configuration is not supported",
+            )),
+            Some(val) => val,
+        };
+
+        let proof_jwt = match request.proof.as_ref() {
+            Some(val) if val.proof_type == "jwt" => val.jwt.as_str(),
+            _ => return Err(FiError::new("invalid_proof: A jwt key proof is required")),
+        };
+
+        let c_nonce = session.c_nonce.clone();
+        let claims = session.claims.clone();
+
+        let holder_key = match verify_key_proof(
+            proof_jwt,
+            configuration,
+            self.metadata.credential_issuer.as_str(),
+            c_nonce.as_str(),
+            &self.loader,
+        ) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let credential = match issue_credential(
+            self.signer.as_ref(),
+            configuration,
+            claims,
+            holder_key.as_str(),
+        ) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        session
+            .configuration_ids
+            .retain(|id| *id != request.credential_configuration_id);
+        if session.configuration_ids.is_empty() {
+            self.sessions.remove(access_token);
+            return Ok(CredentialResponse {
+                credentials: vec![IssuedCredential { credential }],
+                c_nonce: None,
+            });
+        }
+
+        // A nonce proves possession for one credential only
+        session.c_nonce = random_token();
+
+        return Ok(CredentialResponse {
+            credentials: vec![IssuedCredential { credential }],
+            c_nonce: Some(session.c_nonce.clone()),
+        });
+    }
+}
+
+/// Verifies the key proof and returns the `kid` of the proven key.
+fn verify_key_proof(
+    proof_jwt: &str,
+    configuration: &CredentialConfiguration,
+    credential_issuer: &str,
+    c_nonce: &str,
+    loader: &DocumentLoader,
+) -> Result<String, FiError> {
+    let jwt = match Jwt::decode(proof_jwt) {
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("invalid_proof: The key proof is not a JWT"));
+        }
+        Ok(val) => val,
+    };
+
+    if jwt.get_type() != Some(PROOF_JWT_TYPE) {
+        return Err(FiError::new(
+            "invalid_proof: The key proof typ must be openid4vci-proof+jwt",
+        ));
+    }
+
+    let alg = jwt.get_header()["alg"].as_str().unwrap_or_default();
+    if let Some(algorithms) = configuration.get_proof_algorithms() {
+        if !algorithms.iter().any(|val| val == alg) {
+            return Err(FiError::new(
+                "invalid_proof: The key proof algorithm is not supported",
+            ));
+        }
+    }
+
+    let claims = jwt.get_payload();
+    if claims["aud"] != credential_issuer {
+        return Err(FiError::new(
+            "invalid_proof: The key proof audience is not this issuer",
+        ));
+    }
+
+    if claims["nonce"] != c_nonce {
+        return Err(FiError::new(
+            "invalid_nonce: The key proof nonce is missing or stale",
+        ));
+    }
+
+    let now = Utc::now().timestamp();
+    match claims["iat"].as_i64() {
+        Some(val)
+            if val <= now + PROOF_CLOCK_SKEW_SECONDS && val >= now - PROOF_MAX_AGE_SECONDS => {}
+        _ => {
+            return Err(FiError::new(
+                "invalid_proof: The key proof iat is missing or out of range",
+            ))
+        }
+    };
+
+    let kid = match jwt.get_kid() {
+        None => return Err(FiError::new("invalid_proof: The key proof has no kid")),
+        Some(val) => String::from(val),
+    };
+
+    let doc = match loader.get_verification_document(kid.as_str()) {
+        None => {
+            return Err(FiError::new(
+                "invalid_proof: The key proof kid could not be resolved",
+            ))
+        }
+        Some(val) => val,
+    };
+
+    match jwt.verify(&doc) {
+        Ok(true) => Ok(kid),
+        _ => Err(FiError::new(
+            "invalid_proof: The key proof signature does not match",
+        )),
+    }
+}
+
+fn issue_credential(
+    signer: &dyn Signer,
+    configuration: &CredentialConfiguration,
+    mut claims: Value,
+    holder_key: &str,
+) -> Result<Value, FiError> {
+    let issuer = signer
+        .verification_method()
+        .split('#')
+        .next()
+        .unwrap_or_default();
+    let holder = holder_key.split('#').next().unwrap_or_default();
+
+    claims["id"] = Value::from(holder);
+
+    let mut vc = VC::new(random_urn(), Value::from(issuer), None, None, None);
+    for _type in configuration.get_types() {
+        if _type != "VerifiableCredential" {
+            vc.add_type(_type);
+        }
+    }
+    vc.set_credential_subject(claims);
+    vc.set_confirmation(Some(json!({ "kid": holder_key })));
+
+    match configuration.format.as_str() {
+        JWT_VC_JSON_FORMAT => vc.to_jwt(signer).map(Value::from),
+        LDP_VC_FORMAT => {
+            match vc.sign(
+                signer,
+                FiProof::new(signer.algorithm(), String::from("assertionMethod")),
+            ) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+            vc.to_object()
+        }
+        _ => Err(FiError::new(
+            "unsupported_credential_format: The credential format cannot be issued",
+        )),
+    }
+}
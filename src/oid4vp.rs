@@ -0,0 +1,478 @@
+//! OpenID for Verifiable Presentations with `vp_token` responses.
+//!
+//! The wallet answers with a `vp+jwt` presentation carrying the request's `nonce` and the
+//! verifier's `client_id` as `aud`, so the response cannot be replayed to another request.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    dcql::DcqlQuery,
+    document::DocumentLoader,
+    error::FiError,
+    jose::{Jwt, VP_JWT_TYPE},
+    oauth::{decode_query, encode_query, random_token},
+    presentation_exchange::PresentationDefinition,
+    signer::Signer,
+    vp::VP,
+};
+
+pub const VP_TOKEN_RESPONSE_TYPE: &str = "vp_token";
+pub const DIRECT_POST_RESPONSE_MODE: &str = "direct_post";
+pub const JWT_VP_JSON_FORMAT: &str = "jwt_vp_json";
+
+const AUTHORIZATION_REQUEST_URI: &str = "openid4vp://";
+/// Claims added to the presentation in the `vp_token`, removed again when it is read.
+const BINDING_CLAIMS: [&str; 3] = ["nonce", "aud", "iat"];
+/// Accepted age of a `vp_token`, and how far its `iat` may be in the future.
+const RESPONSE_MAX_AGE_SECONDS: i64 = 300;
+const RESPONSE_CLOCK_SKEW_SECONDS: i64 = 60;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuthorizationRequest {
+    response_type: String,
+    client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_uri: Option<String>,
+    nonce: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presentation_definition: Option<PresentationDefinition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dcql_query: Option<DcqlQuery>,
+}
+
+impl AuthorizationRequest {
+    /// Request answered by a `direct_post` to the response URI, with a fresh nonce and state.
+    pub fn new(client_id: String, response_uri: String) -> Self {
+        AuthorizationRequest {
+            response_type: String::from(VP_TOKEN_RESPONSE_TYPE),
+            client_id,
+            response_mode: Some(String::from(DIRECT_POST_RESPONSE_MODE)),
+            response_uri: Some(response_uri),
+            nonce: random_token(),
+            state: Some(random_token()),
+            presentation_definition: None,
+            dcql_query: None,
+        }
+    }
+
+    pub fn get_client_id(&self) -> &String {
+        &self.client_id
+    }
+
+    pub fn get_response_uri(&self) -> &Option<String> {
+        &self.response_uri
+    }
+
+    pub fn get_nonce(&self) -> &String {
+        &self.nonce
+    }
+
+    pub fn get_state(&self) -> &Option<String> {
+        &self.state
+    }
+
+    pub fn get_presentation_definition(&self) -> &Option<PresentationDefinition> {
+        &self.presentation_definition
+    }
+
+    pub fn set_presentation_definition(
+        &mut self,
+        presentation_definition: Option<PresentationDefinition>,
+    ) {
+        self.presentation_definition = presentation_definition;
+    }
+
+    pub fn get_dcql_query(&self) -> &Option<DcqlQuery> {
+        &self.dcql_query
+    }
+
+    pub fn set_dcql_query(&mut self, dcql_query: Option<DcqlQuery>) {
+        self.dcql_query = dcql_query;
+    }
+
+    /// Request passed by value in an `openid4vp://` URI.
+    pub fn to_uri(&self) -> Result<String, FiError> {
+        let params = match serde_json::to_value(self) {
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Cannot serialize the authorization request"));
+            }
+            Ok(val) => query_values(&val),
+        };
+
+        return Ok(format!(
+            "{}?{}",
+            AUTHORIZATION_REQUEST_URI,
+            encode_query(params.as_slice())
+        ));
+    }
+
+    pub fn from_uri(uri: &str) -> Result<Self, FiError> {
+        let params = match decode_query(uri) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let request = match from_query_values(params, &["presentation_definition", "dcql_query"]) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        match serde_json::from_value::<AuthorizationRequest>(request) {
+            Err(error) => Err(FiError::new(error.to_string().as_str())),
+            Ok(val) if val.response_type != VP_TOKEN_RESPONSE_TYPE => Err(FiError::new(
+                "Only vp_token authorization requests are supported",
+            )),
+            Ok(val) => Ok(val),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DescriptorMapEntry {
+    id: String,
+    format: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_nested: Option<Box<DescriptorMapEntry>>,
+}
+
+impl DescriptorMapEntry {
+    pub fn new(id: String, format: String, path: String) -> Self {
+        DescriptorMapEntry {
+            id,
+            format,
+            path,
+            path_nested: None,
+        }
+    }
+
+    pub fn set_path_nested(&mut self, path_nested: Option<DescriptorMapEntry>) {
+        self.path_nested = path_nested.map(Box::new);
+    }
+
+    pub fn get_id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn get_format(&self) -> &String {
+        &self.format
+    }
+
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn get_path_nested(&self) -> Option<&DescriptorMapEntry> {
+        self.path_nested.as_deref()
+    }
+}
+
+/// Maps the input descriptors of a presentation definition to the credentials in the
+/// `vp_token`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PresentationSubmission {
+    id: String,
+    definition_id: String,
+    descriptor_map: Vec<DescriptorMapEntry>,
+}
+
+impl PresentationSubmission {
+    pub fn new(definition_id: String) -> Self {
+        PresentationSubmission {
+            id: random_token(),
+            definition_id,
+            descriptor_map: Vec::new(),
+        }
+    }
+
+    /// Maps the input descriptor to the credential at `index` of the `vp_token` presentation.
+    pub fn add_credential(&mut self, descriptor_id: &str, credential_format: &str, index: usize) {
+        let mut entry = DescriptorMapEntry::new(
+            String::from(descriptor_id),
+            String::from(JWT_VP_JSON_FORMAT),
+            String::from("$"),
+        );
+        entry.set_path_nested(Some(DescriptorMapEntry::new(
+            String::from(descriptor_id),
+            String::from(credential_format),
+            format!("$.verifiableCredential[{}]", index),
+        )));
+
+        self.descriptor_map.push(entry);
+    }
+
+    pub fn add_descriptor(&mut self, entry: DescriptorMapEntry) {
+        self.descriptor_map.push(entry);
+    }
+
+    pub fn get_definition_id(&self) -> &String {
+        &self.definition_id
+    }
+
+    pub fn get_descriptor_map(&self) -> &Vec<DescriptorMapEntry> {
+        &self.descriptor_map
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuthorizationResponse {
+    vp_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presentation_submission: Option<PresentationSubmission>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+}
+
+impl AuthorizationResponse {
+    pub fn get_vp_token(&self) -> &String {
+        &self.vp_token
+    }
+
+    pub fn get_presentation_submission(&self) -> &Option<PresentationSubmission> {
+        &self.presentation_submission
+    }
+
+    pub fn get_state(&self) -> &Option<String> {
+        &self.state
+    }
+
+    /// Form encoded body of the `direct_post` to the response URI.
+    pub fn to_form(&self) -> Result<String, FiError> {
+        match serde_json::to_value(self) {
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Cannot serialize the authorization response"));
+            }
+            Ok(val) => Ok(encode_query(query_values(&val).as_slice())),
+        }
+    }
+
+    pub fn from_form(form: &str) -> Result<Self, FiError> {
+        let params = match decode_query(form) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        match from_query_values(params, &["presentation_submission"]) {
+            Err(error) => Err(error),
+            Ok(val) => match serde_json::from_value(val) {
+                Ok(val) => Ok(val),
+                Err(error) => Err(FiError::new(error.to_string().as_str())),
+            },
+        }
+    }
+}
+
+/// Wallet side: secures the presentation for the request and returns the response to post.
+pub fn create_response(
+    request: &AuthorizationRequest,
+    vp: &VP,
+    signer: &dyn Signer,
+    presentation_submission: Option<PresentationSubmission>,
+) -> Result<AuthorizationResponse, FiError> {
+    let mut payload = match vp.get_signable_content() {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    payload["nonce"] = Value::from(request.nonce.clone());
+    payload["aud"] = Value::from(request.client_id.clone());
+    payload["iat"] = Value::from(Utc::now().timestamp());
+
+    let vp_token = match Jwt::sign(&payload, VP_JWT_TYPE, signer) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    return Ok(AuthorizationResponse {
+        vp_token,
+        presentation_submission,
+        state: request.state.clone(),
+    });
+}
+
+/// Verifier side: checks the response was made for the request by the presentation's holder
+/// within the last five minutes, and that every credential in it verifies. Returns the
+/// presentation.
+pub fn verify_response(
+    request: &AuthorizationRequest,
+    response: &AuthorizationResponse,
+    loader: &DocumentLoader,
+) -> Result<VP, FiError> {
+    if request.state != response.state {
+        return Err(FiError::new("Response state does not match the request"));
+    }
+
+    let jwt = match Jwt::decode(response.vp_token.as_str()) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let payload = jwt.get_payload();
+    if payload["nonce"] != request.nonce.as_str() {
+        return Err(FiError::new("vp_token nonce does not match the request"));
+    }
+
+    if payload["aud"] != request.client_id.as_str() {
+        return Err(FiError::new("vp_token audience is not the verifier"));
+    }
+
+    let now = Utc::now().timestamp();
+    match payload["iat"].as_i64() {
+        Some(val)
+            if val <= now + RESPONSE_CLOCK_SKEW_SECONDS
+                && val >= now - RESPONSE_MAX_AGE_SECONDS => {}
+        _ => return Err(FiError::new("vp_token iat is missing or out of range")),
+    };
+
+    let verification_method = match jwt.get_kid() {
+        None => return Err(FiError::new("vp_token has no kid")),
+        Some(val) => val,
+    };
+
+    let doc = match loader.get_verification_document(verification_method) {
+        None => return Err(FiError::new("Verification document could not be resolved")),
+        Some(val) => val,
+    };
+
+    match jwt.verify(&doc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(false) => return Err(FiError::new("vp_token signature does not match")),
+        Ok(true) => {}
+    };
+
+    let mut presentation = payload.clone();
+    if let Some(val) = presentation.as_object_mut() {
+        for claim in BINDING_CLAIMS {
+            val.remove(claim);
+        }
+    }
+
+    let vp = match VP::from(presentation) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match vp.check_holder_binding(&doc) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match check_submission(request, response, &vp) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    if let Some(query) = request.dcql_query.as_ref() {
+        match query.evaluate_presentation(&vp) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(false) => {
+                return Err(FiError::new("Presentation does not satisfy the DCQL query"));
+            }
+            Ok(true) => {}
+        };
+    }
+
+    match vp.verify_credentials(loader) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(false) => return Err(FiError::new("A presented credential does not verify")),
+        Ok(true) => {}
+    };
+
+    return Ok(vp);
+}
+
+/// The submission must answer the request's definition with credentials satisfying it.
+fn check_submission(
+    request: &AuthorizationRequest,
+    response: &AuthorizationResponse,
+    vp: &VP,
+) -> Result<(), FiError> {
+    let definition = match request.presentation_definition.as_ref() {
+        None => return Ok(()),
+        Some(val) => val,
+    };
+
+    let submission = match response.presentation_submission.as_ref() {
+        None => return Err(FiError::new("Response has no presentation submission")),
+        Some(val) => val,
+    };
+
+    match definition.evaluate_presentation(vp, submission) {
+        Err(error) => Err(error),
+        Ok(false) => Err(FiError::new(
+            "Presentation does not satisfy the presentation definition",
+        )),
+        Ok(true) => Ok(()),
+    }
+}
+
+/// String parameters of a JSON object, with object values serialized as JSON.
+fn query_values(value: &Value) -> Vec<(String, String)> {
+    match value.as_object() {
+        None => Vec::new(),
+        Some(map) => map
+            .iter()
+            .map(|(name, value)| match value {
+                Value::String(val) => (name.clone(), val.clone()),
+                other => (name.clone(), other.to_string()),
+            })
+            .collect(),
+    }
+}
+
+fn from_query_values(
+    params: Vec<(String, String)>,
+    json_params: &[&str],
+) -> Result<Value, FiError> {
+    let mut object = serde_json::Map::new();
+
+    for (name, value) in params {
+        if json_params.contains(&name.as_str()) {
+            match serde_json::from_str(value.as_str()) {
+                Err(error) => {
+                    eprintln!("{}", error);
+                    return Err(FiError::new(
+                        format!("Parameter {} is not valid JSON", name).as_str(),
+                    ));
+                }
+                Ok(val) => object.insert(name, val),
+            };
+        } else {
+            object.insert(name, Value::from(value));
+        }
+    }
+
+    return Ok(Value::Object(object));
+}
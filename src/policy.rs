@@ -0,0 +1,511 @@
+//! Verification policies: whether a credential is accepted, beyond its signature being valid.
+//!
+//! A [`Policy`] lists trusted issuers per credential type, the types and contexts a
+//! credential must have, its maximum age, a status check and the algorithms it may be secured
+//! with. Evaluating a credential or presentation returns every rule it fails.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    document::{DocumentLoader, PublicVerificationDocument},
+    enveloped::Envelope,
+    error::FiError,
+    keys,
+    proof::CredentialProof,
+    vc::VC,
+    vp::{PresentedCredential, VP},
+};
+
+/// Checks the entries of a credential's `credentialStatus`, e.g. against a status list.
+#[cfg(not(feature = "wasm"))]
+pub trait StatusChecker: Send + Sync {
+    /// Whether the credential is still valid according to the status entry.
+    fn check(&self, credential_status: &Value) -> Result<bool, FiError>;
+}
+
+/// Checks the entries of a credential's `credentialStatus`, e.g. against a status list.
+#[cfg(feature = "wasm")]
+pub trait StatusChecker {
+    /// Whether the credential is still valid according to the status entry.
+    fn check(&self, credential_status: &Value) -> Result<bool, FiError>;
+}
+
+/// Who is trusted to issue a credential type.
+#[derive(Clone, Debug)]
+pub enum TrustedIssuer {
+    /// The credential's issuer, whose verification method must belong to the DID.
+    Did(String),
+    /// The public key the credential's signature verifies with.
+    Key(Vec<u8>),
+    /// DER encoded root certificate the credential's `x5c` / `x5chain` must lead to.
+    Certificate(Vec<u8>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyRule {
+    Signature,
+    TrustedIssuer,
+    RequiredType,
+    RequiredContext,
+    MaxAge,
+    Status,
+    Algorithm,
+    HolderBinding,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PolicyFailure {
+    rule: PolicyRule,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credential: Option<String>,
+    message: String,
+}
+
+impl PolicyFailure {
+    fn new(rule: PolicyRule, credential: Option<&String>, message: String) -> Self {
+        return PolicyFailure {
+            rule,
+            credential: credential.cloned(),
+            message,
+        };
+    }
+
+    pub fn get_rule(&self) -> PolicyRule {
+        self.rule
+    }
+
+    /// Id of the failing credential, `None` for rules on the presentation itself.
+    pub fn get_credential(&self) -> &Option<String> {
+        &self.credential
+    }
+
+    pub fn get_message(&self) -> &String {
+        &self.message
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PolicyResult {
+    verified: bool,
+    failures: Vec<PolicyFailure>,
+}
+
+impl PolicyResult {
+    fn new(failures: Vec<PolicyFailure>) -> Self {
+        return PolicyResult {
+            verified: failures.is_empty(),
+            failures,
+        };
+    }
+
+    pub fn is_verified(&self) -> bool {
+        self.verified
+    }
+
+    pub fn get_failures(&self) -> &Vec<PolicyFailure> {
+        &self.failures
+    }
+
+    pub fn has_failed(&self, rule: PolicyRule) -> bool {
+        self.failures.iter().any(|val| val.rule == rule)
+    }
+}
+
+/// Signature, algorithm and certificate chain of a credential's securing mechanism.
+struct Securing {
+    verification_method: Option<String>,
+    algorithm: Option<String>,
+    x5chain: Vec<Vec<u8>>,
+}
+
+#[derive(Default)]
+pub struct Policy {
+    trusted_issuers: HashMap<String, Vec<TrustedIssuer>>,
+    required_types: Vec<String>,
+    required_contexts: Vec<String>,
+    max_age: Option<Duration>,
+    status_checker: Option<Box<dyn StatusChecker>>,
+    allowed_algorithms: Option<Vec<String>>,
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Policy::default()
+    }
+
+    /// Trusts the issuer for credentials of the type. Once any issuer is trusted, credentials
+    /// of types without trusted issuers fail the `trusted_issuer` rule.
+    pub fn add_trusted_issuer(&mut self, credential_type: &str, issuer: TrustedIssuer) {
+        self.trusted_issuers
+            .entry(String::from(credential_type))
+            .or_default()
+            .push(issuer);
+    }
+
+    pub fn add_required_type(&mut self, _type: &str) {
+        self.required_types.push(String::from(_type));
+    }
+
+    pub fn add_required_context(&mut self, context: &str) {
+        self.required_contexts.push(String::from(context));
+    }
+
+    /// Longest time since `validFrom` a credential is accepted for.
+    pub fn set_max_age(&mut self, max_age: Option<Duration>) {
+        self.max_age = max_age;
+    }
+
+    /// With a status checker, credentials must have a `credentialStatus` that passes it.
+    pub fn set_status_checker(&mut self, status_checker: Option<Box<dyn StatusChecker>>) {
+        self.status_checker = status_checker;
+    }
+
+    /// JOSE algorithm names, or cryptosuite names for Data Integrity proofs. `None` allows
+    /// every algorithm.
+    pub fn set_allowed_algorithms(&mut self, allowed_algorithms: Option<Vec<String>>) {
+        self.allowed_algorithms = allowed_algorithms;
+    }
+
+    pub fn evaluate_credential(
+        &self,
+        credential: &PresentedCredential,
+        loader: &DocumentLoader,
+    ) -> PolicyResult {
+        PolicyResult::new(self.credential_failures(credential, loader))
+    }
+
+    /// Evaluates the presentation's proof, the holder binding of its credentials and every
+    /// credential it carries.
+    pub fn evaluate_presentation(&self, vp: &VP, loader: &DocumentLoader) -> PolicyResult {
+        let mut failures = Vec::new();
+
+        match vp.get_proof() {
+            None => failures.push(PolicyFailure::new(
+                PolicyRule::Signature,
+                None,
+                String::from("The presentation has no proof"),
+            )),
+            Some(proof) => {
+                if let Some(failure) = self.algorithm_failure(Some(proof.get_algorithm()), None) {
+                    failures.push(failure);
+                }
+
+                let doc = proof
+                    .get_verification_method()
+                    .as_ref()
+                    .and_then(|val| loader.get_verification_document(val.as_str()));
+
+                match doc.as_ref().map(|val| vp.verify(val)) {
+                    None => failures.push(PolicyFailure::new(
+                        PolicyRule::Signature,
+                        None,
+                        String::from("Verification document could not be resolved"),
+                    )),
+                    Some(Ok(true)) => {}
+                    Some(Ok(false)) => failures.push(PolicyFailure::new(
+                        PolicyRule::Signature,
+                        None,
+                        String::from("The presentation signature does not match"),
+                    )),
+                    // `VP::verify` only checks the holder binding once the signature matched
+                    Some(Err(error)) => {
+                        match doc.as_ref().map(|val| vp.check_holder_binding(val)) {
+                            Some(Err(_)) => failures.push(PolicyFailure::new(
+                                PolicyRule::HolderBinding,
+                                None,
+                                error.to_string(),
+                            )),
+                            _ => failures.push(PolicyFailure::new(
+                                PolicyRule::Signature,
+                                None,
+                                error.to_string(),
+                            )),
+                        }
+                    }
+                };
+            }
+        };
+
+        for credential in vp.get_verifiable_credentials().iter() {
+            failures.extend(self.credential_failures(credential, loader));
+        }
+
+        return PolicyResult::new(failures);
+    }
+
+    fn credential_failures(
+        &self,
+        credential: &PresentedCredential,
+        loader: &DocumentLoader,
+    ) -> Vec<PolicyFailure> {
+        let vc = match credential.get_credential() {
+            Err(error) => {
+                return vec![PolicyFailure::new(
+                    PolicyRule::Signature,
+                    None,
+                    error.to_string(),
+                )];
+            }
+            Ok(val) => val,
+        };
+
+        let securing = match securing(credential) {
+            Err(error) => {
+                return vec![PolicyFailure::new(
+                    PolicyRule::Signature,
+                    Some(vc.get_id()),
+                    error.to_string(),
+                )];
+            }
+            Ok(val) => val,
+        };
+
+        let mut failures = Vec::new();
+        let id = Some(vc.get_id());
+
+        // The issuer's key only comes from its certificate once the chain leads to a trusted
+        // root, otherwise the verification method is resolved and the certificate is ignored
+        let doc = match securing.x5chain.first() {
+            Some(certificate) if self.trusts_chain(&vc, &securing) => {
+                PublicVerificationDocument::from_certificate(String::new(), certificate).ok()
+            }
+            _ => securing
+                .verification_method
+                .as_ref()
+                .and_then(|val| loader.get_verification_document(val.as_str())),
+        };
+
+        match doc.as_ref().map(|val| credential.verify(val)) {
+            None => failures.push(PolicyFailure::new(
+                PolicyRule::Signature,
+                id,
+                String::from("Verification document could not be resolved"),
+            )),
+            Some(Ok(true)) => {}
+            Some(Ok(false)) => failures.push(PolicyFailure::new(
+                PolicyRule::Signature,
+                id,
+                String::from("The credential signature does not match"),
+            )),
+            Some(Err(error)) => {
+                failures.push(PolicyFailure::new(
+                    PolicyRule::Signature,
+                    id,
+                    error.to_string(),
+                ));
+            }
+        };
+
+        if let Some(failure) = self.algorithm_failure(securing.algorithm.as_ref(), id) {
+            failures.push(failure);
+        }
+
+        if let Some(failure) = self.issuer_failure(&vc, &securing, doc.as_ref()) {
+            failures.push(failure);
+        }
+
+        for _type in self.required_types.iter() {
+            if !vc.get_types().contains(_type) {
+                failures.push(PolicyFailure::new(
+                    PolicyRule::RequiredType,
+                    id,
+                    format!("The credential is not a {}", _type),
+                ));
+            }
+        }
+
+        for context in self.required_contexts.iter() {
+            if !vc.get_contexts().contains(&Value::from(context.as_str())) {
+                failures.push(PolicyFailure::new(
+                    PolicyRule::RequiredContext,
+                    id,
+                    format!("The credential does not use the {} context", context),
+                ));
+            }
+        }
+
+        if let Some(max_age) = self.max_age {
+            let too_old = match DateTime::parse_from_rfc3339(vc.get_valid_from().as_str()) {
+                Err(_) => true,
+                Ok(val) => Utc::now().signed_duration_since(val) > max_age,
+            };
+            if too_old {
+                failures.push(PolicyFailure::new(
+                    PolicyRule::MaxAge,
+                    id,
+                    format!(
+                        "The credential is older than {} seconds",
+                        max_age.num_seconds()
+                    ),
+                ));
+            }
+        }
+
+        if let Some(checker) = self.status_checker.as_ref() {
+            if let Some(failure) = status_failure(checker.as_ref(), &vc) {
+                failures.push(failure);
+            }
+        }
+
+        return failures;
+    }
+
+    fn algorithm_failure(
+        &self,
+        algorithm: Option<&String>,
+        credential: Option<&String>,
+    ) -> Option<PolicyFailure> {
+        match (self.allowed_algorithms.as_ref(), algorithm) {
+            (None, _) => None,
+            (Some(allowed), Some(val)) if allowed.contains(val) => None,
+            (Some(_), Some(val)) => Some(PolicyFailure::new(
+                PolicyRule::Algorithm,
+                credential,
+                format!("Algorithm {} is not allowed", val),
+            )),
+            (Some(_), None) => Some(PolicyFailure::new(
+                PolicyRule::Algorithm,
+                credential,
+                String::from("The algorithm could not be determined"),
+            )),
+        }
+    }
+
+    /// Whether the credential's certificate chain leads to a root trusted for one of its types.
+    fn trusts_chain(&self, vc: &VC, securing: &Securing) -> bool {
+        vc.get_types()
+            .iter()
+            .filter_map(|_type| self.trusted_issuers.get(_type))
+            .flatten()
+            .any(|trusted| match trusted {
+                TrustedIssuer::Certificate(root) => {
+                    keys::verify_certificate_chain(securing.x5chain.as_slice(), root)
+                        .unwrap_or(false)
+                }
+                _ => false,
+            })
+    }
+
+    fn issuer_failure(
+        &self,
+        vc: &VC,
+        securing: &Securing,
+        doc: Option<&PublicVerificationDocument>,
+    ) -> Option<PolicyFailure> {
+        if self.trusted_issuers.is_empty() {
+            return None;
+        }
+
+        let issuer = match vc.get_issuer() {
+            Value::String(val) => Some(val.as_str()),
+            val => val["id"].as_str(),
+        };
+
+        let trusted = vc
+            .get_types()
+            .iter()
+            .filter_map(|_type| self.trusted_issuers.get(_type))
+            .flatten()
+            .any(|trusted| match trusted {
+                TrustedIssuer::Did(did) => {
+                    issuer == Some(did.as_str())
+                        && securing
+                            .verification_method
+                            .as_ref()
+                            .and_then(|val| val.split('#').next())
+                            == Some(did.as_str())
+                }
+                TrustedIssuer::Key(public_key) => {
+                    doc.is_some_and(|val| val.get_public_key() == public_key)
+                }
+                TrustedIssuer::Certificate(root) => {
+                    keys::verify_certificate_chain(securing.x5chain.as_slice(), root)
+                        .unwrap_or(false)
+                }
+            });
+
+        match trusted {
+            true => None,
+            false => Some(PolicyFailure::new(
+                PolicyRule::TrustedIssuer,
+                Some(vc.get_id()),
+                format!(
+                    "{} is not a trusted issuer of the credential",
+                    issuer.unwrap_or("The issuer")
+                ),
+            )),
+        }
+    }
+}
+
+fn status_failure(checker: &dyn StatusChecker, vc: &VC) -> Option<PolicyFailure> {
+    let entries = match vc.get_credential_status() {
+        None | Some(Value::Null) => {
+            return Some(PolicyFailure::new(
+                PolicyRule::Status,
+                Some(vc.get_id()),
+                String::from("The credential has no status"),
+            ))
+        }
+        Some(Value::Array(val)) => val.iter().collect(),
+        Some(val) => vec![val],
+    };
+
+    for entry in entries {
+        let message = match checker.check(entry) {
+            Ok(true) => continue,
+            Ok(false) => String::from("The credential status check failed"),
+            Err(error) => error.to_string(),
+        };
+        return Some(PolicyFailure::new(
+            PolicyRule::Status,
+            Some(vc.get_id()),
+            message,
+        ));
+    }
+
+    return None;
+}
+
+fn securing(credential: &PresentedCredential) -> Result<Securing, FiError> {
+    let envelope = match credential {
+        PresentedCredential::Embedded(val) => {
+            let algorithm = match val.get_proof() {
+                None => None,
+                Some(CredentialProof::Fi(proof)) => Some(proof.get_algorithm().clone()),
+                Some(CredentialProof::DataIntegrity(proof)) => {
+                    Some(proof.get_cryptosuite().clone())
+                }
+            };
+
+            return Ok(Securing {
+                verification_method: credential.get_verification_method().ok(),
+                algorithm,
+                x5chain: Vec::new(),
+            });
+        }
+        PresentedCredential::Enveloped(val) => match val.decode() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        },
+    };
+
+    let (algorithm, x5chain) = match &envelope {
+        Envelope::Jwt(val) => (val.get_algorithm(), val.get_x5c()),
+        Envelope::SdJwt(val) => (val.get_jwt().get_algorithm(), val.get_jwt().get_x5c()),
+        Envelope::Cose(val) => (val.get_algorithm(), val.get_x5chain()),
+    };
+
+    return Ok(Securing {
+        verification_method: credential.get_verification_method().ok(),
+        algorithm: algorithm.ok().map(|val| String::from(val.to_str())),
+        x5chain,
+    });
+}
@@ -0,0 +1,588 @@
+//! DIF Presentation Exchange v2: selecting credentials for a presentation definition and
+//! checking a presentation against it.
+//!
+//! Field filters support the JSON Schema keywords used in presentation definitions: `type`,
+//! `const`, `enum`, numeric and length bounds, `pattern`, `format` with `formatMinimum` and
+//! friends for dates, `contains`, `items`, `properties`, `required` and the `allOf`, `anyOf`,
+//! `oneOf` and `not` combinators. Other keywords are ignored. `limit_disclosure` is not applied.
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, NaiveDate};
+use jsonpath_rust::JsonPath;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    cose::VC_COSE_TYPE,
+    enveloped::{VC_JWT_MEDIA_TYPE, VC_SD_JWT_MEDIA_TYPE},
+    error::FiError,
+    oid4vci::{JWT_VC_JSON_FORMAT, LDP_VC_FORMAT},
+    oid4vp::PresentationSubmission,
+    vp::{PresentedCredential, VP},
+};
+
+pub const SD_JWT_VC_FORMAT: &str = "vc+sd-jwt";
+pub const COSE_VC_FORMAT: &str = "vc+cose";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    path: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<Value>,
+    #[serde(default)]
+    optional: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Constraints {
+    #[serde(default)]
+    fields: Vec<Field>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit_disclosure: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputDescriptor {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purpose: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    group: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Map<String, Value>>,
+    #[serde(default)]
+    constraints: Constraints,
+}
+
+impl InputDescriptor {
+    pub fn get_id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn get_group(&self) -> &Vec<String> {
+        &self.group
+    }
+
+    /// Every required field has a value at one of its paths passing the field's filter.
+    pub fn matches(&self, credential: &Value) -> bool {
+        self.constraints
+            .fields
+            .iter()
+            .filter(|field| !field.optional)
+            .all(|field| {
+                field.path.iter().any(|path| {
+                    credential.query(path.as_str()).is_ok_and(|values| {
+                        values.iter().any(|value| match field.filter.as_ref() {
+                            None => true,
+                            Some(filter) => schema_matches(filter, value),
+                        })
+                    })
+                })
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubmissionRequirement {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from_nested: Option<Vec<SubmissionRequirement>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PresentationDefinition {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purpose: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Map<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submission_requirements: Option<Vec<SubmissionRequirement>>,
+    input_descriptors: Vec<InputDescriptor>,
+}
+
+impl PresentationDefinition {
+    pub fn from(value: Value) -> Result<Self, FiError> {
+        match serde_json::from_value(value) {
+            Ok(val) => Ok(val),
+            Err(error) => Err(FiError::new(error.to_string().as_str())),
+        }
+    }
+
+    pub fn get_id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn get_input_descriptors(&self) -> &Vec<InputDescriptor> {
+        &self.input_descriptors
+    }
+
+    /// Indexes of the credentials matching each input descriptor, in descriptor order.
+    pub fn match_credentials(
+        &self,
+        credentials: &[PresentedCredential],
+    ) -> Result<Vec<(String, Vec<usize>)>, FiError> {
+        let mut objects = Vec::new();
+        for credential in credentials.iter() {
+            let object = match credential.get_credential() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => match val.to_object() {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                },
+            };
+
+            let format = match credential_format(credential) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+
+            objects.push((object, format));
+        }
+
+        return Ok(self
+            .input_descriptors
+            .iter()
+            .map(|descriptor| {
+                let matches = objects
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (object, format))| {
+                        self.accepts_format(descriptor, format) && descriptor.matches(object)
+                    })
+                    .map(|(index, _)| index)
+                    .collect();
+                (descriptor.id.clone(), matches)
+            })
+            .collect());
+    }
+
+    /// Selects credentials satisfying the definition and its submission requirements. Returns
+    /// them in presentation order with the submission mapping descriptors to them.
+    pub fn create_submission(
+        &self,
+        credentials: &[PresentedCredential],
+    ) -> Result<(Vec<PresentedCredential>, PresentationSubmission), FiError> {
+        let matches = match self.match_credentials(credentials) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let satisfied: Vec<&str> = matches
+            .iter()
+            .filter(|(_, indexes)| !indexes.is_empty())
+            .map(|(id, _)| id.as_str())
+            .collect();
+
+        let selected = match self.select_descriptors(&satisfied) {
+            None => {
+                return Err(FiError::new(
+                    "The credentials do not satisfy the presentation definition",
+                ))
+            }
+            Some(val) => val,
+        };
+
+        let mut presented: Vec<usize> = Vec::new();
+        let mut submission = PresentationSubmission::new(self.id.clone());
+
+        for (id, indexes) in matches.iter() {
+            if !selected.contains(id.as_str()) {
+                continue;
+            }
+
+            let index = indexes[0];
+            let position = match presented.iter().position(|val| *val == index) {
+                Some(val) => val,
+                None => {
+                    presented.push(index);
+                    presented.len() - 1
+                }
+            };
+
+            let format = match credential_format(&credentials[index]) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+
+            submission.add_credential(id.as_str(), format, position);
+        }
+
+        let presented = presented
+            .into_iter()
+            .map(|index| credentials[index].clone())
+            .collect();
+
+        return Ok((presented, submission));
+    }
+
+    /// Checks the submission answers this definition, every descriptor it maps is matched by
+    /// the credential it points at and the mapped descriptors satisfy the requirements.
+    pub fn evaluate_presentation(
+        &self,
+        vp: &VP,
+        submission: &PresentationSubmission,
+    ) -> Result<bool, FiError> {
+        if submission.get_definition_id() != &self.id {
+            return Ok(false);
+        }
+
+        let credentials = vp.get_verifiable_credentials();
+        let mut satisfied = Vec::new();
+
+        for entry in submission.get_descriptor_map().iter() {
+            let descriptor = match self
+                .input_descriptors
+                .iter()
+                .find(|val| &val.id == entry.get_id())
+            {
+                None => return Ok(false),
+                Some(val) => val,
+            };
+
+            let path = entry
+                .get_path_nested()
+                .map(|val| val.get_path())
+                .unwrap_or(entry.get_path());
+
+            let credential = match credential_index(path).and_then(|val| credentials.get(val)) {
+                None => return Ok(false),
+                Some(val) => val,
+            };
+
+            let object = match credential.get_credential() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => match val.to_object() {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                },
+            };
+
+            let format = match credential_format(credential) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+
+            if !self.accepts_format(descriptor, format) || !descriptor.matches(&object) {
+                return Ok(false);
+            }
+
+            satisfied.push(descriptor.id.as_str());
+        }
+
+        return Ok(self.select_descriptors(&satisfied).is_some());
+    }
+
+    /// Descriptors to present among the satisfied ones, `None` when the requirements cannot
+    /// be met. Without submission requirements every descriptor is required.
+    fn select_descriptors<'a>(&'a self, satisfied: &[&str]) -> Option<BTreeSet<&'a str>> {
+        let requirements = match self.submission_requirements.as_ref() {
+            None => {
+                return match self
+                    .input_descriptors
+                    .iter()
+                    .all(|val| satisfied.contains(&val.id.as_str()))
+                {
+                    true => Some(
+                        self.input_descriptors
+                            .iter()
+                            .map(|val| val.id.as_str())
+                            .collect(),
+                    ),
+                    false => None,
+                };
+            }
+            Some(val) => val,
+        };
+
+        let mut selected = BTreeSet::new();
+        for requirement in requirements.iter() {
+            match self.select_requirement(requirement, satisfied) {
+                None => return None,
+                Some(val) => selected.extend(val),
+            };
+        }
+
+        return Some(selected);
+    }
+
+    fn select_requirement<'a>(
+        &'a self,
+        requirement: &SubmissionRequirement,
+        satisfied: &[&str],
+    ) -> Option<BTreeSet<&'a str>> {
+        // Each option is a descriptor of the group, or a nested requirement
+        let (options, required): (Vec<BTreeSet<&'a str>>, usize) =
+            match (requirement.from.as_ref(), requirement.from_nested.as_ref()) {
+                (Some(group), _) => {
+                    let members: Vec<&'a str> = self
+                        .input_descriptors
+                        .iter()
+                        .filter(|val| val.group.contains(group))
+                        .map(|val| val.id.as_str())
+                        .collect();
+                    let options = members
+                        .iter()
+                        .filter(|id| satisfied.contains(id))
+                        .map(|id| BTreeSet::from([*id]))
+                        .collect();
+                    (options, members.len())
+                }
+                (None, Some(nested)) => {
+                    let options = nested
+                        .iter()
+                        .filter_map(|val| self.select_requirement(val, satisfied))
+                        .collect();
+                    (options, nested.len())
+                }
+                (None, None) => return None,
+            };
+
+        let count = match requirement.rule.as_str() {
+            "all" if options.len() == required => options.len(),
+            "pick" => {
+                let available = options.len();
+                match (requirement.count, requirement.min, requirement.max) {
+                    (Some(count), _, _) if available >= count => count,
+                    (None, min, max) if available >= min.unwrap_or(0) => {
+                        available.min(max.unwrap_or(available))
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        return Some(options.into_iter().take(count).flatten().collect());
+    }
+
+    fn accepts_format(&self, descriptor: &InputDescriptor, format: &str) -> bool {
+        match descriptor.format.as_ref().or(self.format.as_ref()) {
+            None => true,
+            Some(val) => val.contains_key(format),
+        }
+    }
+}
+
+/// Presentation Exchange format designation of the credential.
+pub fn credential_format(credential: &PresentedCredential) -> Result<&'static str, FiError> {
+    match credential {
+        PresentedCredential::Embedded(_) => Ok(LDP_VC_FORMAT),
+        PresentedCredential::Enveloped(val) => match val.get_media_type() {
+            Some(VC_JWT_MEDIA_TYPE) => Ok(JWT_VC_JSON_FORMAT),
+            Some(VC_SD_JWT_MEDIA_TYPE) => Ok(SD_JWT_VC_FORMAT),
+            Some(VC_COSE_TYPE) => Ok(COSE_VC_FORMAT),
+            _ => Err(FiError::new("Enveloped media type is not supported")),
+        },
+    }
+}
+
+/// Index of the credential a `$.verifiableCredential[n]` path points at.
+fn credential_index(path: &str) -> Option<usize> {
+    path.strip_prefix("$.verifiableCredential[")
+        .and_then(|val| val.strip_suffix(']'))
+        .and_then(|val| val.parse::<usize>().ok())
+}
+
+/// Whether the value is valid against the JSON Schema subset described in the module docs.
+pub fn schema_matches(schema: &Value, value: &Value) -> bool {
+    let schema = match schema {
+        Value::Bool(val) => return *val,
+        Value::Object(val) => val,
+        _ => return true,
+    };
+
+    for (keyword, expected) in schema.iter() {
+        let valid = match keyword.as_str() {
+            "type" => match expected {
+                Value::String(val) => is_type(val, value),
+                Value::Array(val) => val
+                    .iter()
+                    .any(|val| val.as_str().is_some_and(|val| is_type(val, value))),
+                _ => true,
+            },
+            "const" => numbers_equal(expected, value),
+            "enum" => expected
+                .as_array()
+                .is_some_and(|val| val.iter().any(|val| numbers_equal(val, value))),
+            "minimum" => compare_numbers(value, expected, |a, b| a >= b),
+            "maximum" => compare_numbers(value, expected, |a, b| a <= b),
+            "exclusiveMinimum" => compare_numbers(value, expected, |a, b| a > b),
+            "exclusiveMaximum" => compare_numbers(value, expected, |a, b| a < b),
+            "minLength" => compare_length(value, expected, |a, b| a >= b),
+            "maxLength" => compare_length(value, expected, |a, b| a <= b),
+            "pattern" => match (value.as_str(), expected.as_str()) {
+                (Some(val), Some(pattern)) => {
+                    Regex::new(pattern).is_ok_and(|regex| regex.is_match(val))
+                }
+                _ => true,
+            },
+            "format" => match (value.as_str(), expected.as_str()) {
+                (Some(val), Some(format @ ("date" | "date-time"))) => {
+                    parse_date(val, format).is_some()
+                }
+                _ => true,
+            },
+            "formatMinimum" => compare_dates(schema, value, expected, |a, b| a >= b),
+            "formatMaximum" => compare_dates(schema, value, expected, |a, b| a <= b),
+            "formatExclusiveMinimum" => compare_dates(schema, value, expected, |a, b| a > b),
+            "formatExclusiveMaximum" => compare_dates(schema, value, expected, |a, b| a < b),
+            "contains" => match value.as_array() {
+                None => true,
+                Some(val) => val.iter().any(|item| schema_matches(expected, item)),
+            },
+            "items" => match value.as_array() {
+                None => true,
+                Some(val) => val.iter().all(|item| schema_matches(expected, item)),
+            },
+            "minItems" => match (value.as_array(), expected.as_u64()) {
+                (Some(val), Some(min)) => val.len() as u64 >= min,
+                _ => true,
+            },
+            "maxItems" => match (value.as_array(), expected.as_u64()) {
+                (Some(val), Some(max)) => val.len() as u64 <= max,
+                _ => true,
+            },
+            "required" => match (value.as_object(), expected.as_array()) {
+                (Some(object), Some(names)) => names
+                    .iter()
+                    .all(|name| name.as_str().is_some_and(|val| object.contains_key(val))),
+                _ => true,
+            },
+            "properties" => match (value.as_object(), expected.as_object()) {
+                (Some(object), Some(properties)) => {
+                    properties
+                        .iter()
+                        .all(|(name, schema)| match object.get(name) {
+                            None => true,
+                            Some(val) => schema_matches(schema, val),
+                        })
+                }
+                _ => true,
+            },
+            "allOf" => expected
+                .as_array()
+                .is_some_and(|val| val.iter().all(|schema| schema_matches(schema, value))),
+            "anyOf" => expected
+                .as_array()
+                .is_some_and(|val| val.iter().any(|schema| schema_matches(schema, value))),
+            "oneOf" => expected.as_array().is_some_and(|val| {
+                val.iter()
+                    .filter(|schema| schema_matches(schema, value))
+                    .count()
+                    == 1
+            }),
+            "not" => !schema_matches(expected, value),
+            _ => true,
+        };
+
+        if !valid {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+fn is_type(name: &str, value: &Value) -> bool {
+    match name {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|val| val.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+/// Equality where `1` and `1.0` are the same number.
+fn numbers_equal(expected: &Value, value: &Value) -> bool {
+    match (expected.as_f64(), value.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => expected == value,
+    }
+}
+
+fn compare_numbers(value: &Value, bound: &Value, compare: fn(f64, f64) -> bool) -> bool {
+    match (value.as_f64(), bound.as_f64()) {
+        (Some(val), Some(bound)) => compare(val, bound),
+        _ => !value.is_number(),
+    }
+}
+
+fn compare_length(value: &Value, bound: &Value, compare: fn(u64, u64) -> bool) -> bool {
+    match (value.as_str(), bound.as_u64()) {
+        (Some(val), Some(bound)) => compare(val.chars().count() as u64, bound),
+        _ => true,
+    }
+}
+
+fn compare_dates(
+    schema: &Map<String, Value>,
+    value: &Value,
+    bound: &Value,
+    compare: fn(i64, i64) -> bool,
+) -> bool {
+    let format = match schema.get("format").and_then(|val| val.as_str()) {
+        Some(val @ ("date" | "date-time")) => val,
+        _ => return true,
+    };
+
+    match (
+        value.as_str().and_then(|val| parse_date(val, format)),
+        bound.as_str().and_then(|val| parse_date(val, format)),
+    ) {
+        (Some(val), Some(bound)) => compare(val, bound),
+        (None, _) => !value.is_string(),
+        _ => false,
+    }
+}
+
+/// Seconds since the epoch of a `date` or `date-time` string.
+fn parse_date(value: &str, format: &str) -> Option<i64> {
+    match format {
+        "date" => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .and_then(|val| val.and_hms_opt(0, 0, 0))
+            .map(|val| val.and_utc().timestamp()),
+        _ => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|val| val.timestamp()),
+    }
+}
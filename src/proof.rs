@@ -1,16 +1,22 @@
-use chrono::Utc;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
 use fi_digital_signatures::{
-    algorithms::Algorithm, signer::get_signing_key, verifier::get_verifying_key,
+    algorithms::Algorithm, crypto::VerifyFromKey, verifier::get_verifying_key,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 #[cfg(feature = "wasm")]
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen::prelude::wasm_bindgen;
 
-use crate::{document::VerificationDocument, error::FiError};
+use crate::{document::PublicVerificationDocument, error::FiError, signer::Signer};
+
+/// Seconds the clocks of signer and verifier may differ by when `created` and `expires` are
+/// checked.
+const PROOF_CLOCK_SKEW_SECONDS: i64 = 60;
 
 pub trait Proof {
-    fn sign(&mut self, doc: &mut VerificationDocument, content: String) -> Result<(), FiError>;
-    fn verify(&self, doc: &mut VerificationDocument, content: String) -> Result<bool, FiError>;
+    fn sign(&mut self, signer: &dyn Signer, content: String) -> Result<(), FiError>;
+    fn verify(&self, doc: &PublicVerificationDocument, content: String) -> Result<bool, FiError>;
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -18,80 +24,109 @@ pub struct FiProof {
     #[serde(rename = "type")]
     _type: String,
     created: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
     algorithm: String,
     #[serde(rename = "proofPurpose")]
     proof_purpose: String,
+    #[serde(rename = "verificationMethod", skip_serializing_if = "Option::is_none")]
+    verification_method: Option<String>,
     jws: Option<String>,
 }
 
 impl Proof for FiProof {
-    fn sign(&mut self, doc: &mut VerificationDocument, content: String) -> Result<(), FiError> {
-        let key_bytes = match doc.get_private_key_mut() {
-            None => {
-                return Err(FiError::new(
-                    "No private key was found in the VerificationDocument",
-                ))
-            }
-            Some(val) => val,
-        };
-
+    fn sign(&mut self, signer: &dyn Signer, content: String) -> Result<(), FiError> {
         let alg = match Algorithm::from_str(self.algorithm.as_str()) {
             Some(val) => val,
             None => return Err(FiError::new("Algorithm cannot be identified.")),
         };
 
-        let signing_key = match get_signing_key(alg, key_bytes.as_mut_slice()) {
-            Ok(val) => val,
+        if alg != signer.algorithm() {
+            return Err(FiError::new(
+                "Signer algorithm does not match the proof algorithm",
+            ));
+        }
+
+        self.verification_method = Some(String::from(signer.verification_method()));
+        let signing_input = match self.signing_options() {
             Err(error) => {
-                eprintln!("{}", error);
-                return Err(FiError::new("Failed to get signing key"));
+                return Err(error);
             }
+            Ok(val) => format!("{}.{}", val, content),
         };
 
-        match signing_key.sign(content, alg) {
-            Ok(val) => {
-                self.jws = Some(val);
-                Ok(())
-            }
+        let signature = match signer.sign(signing_input.as_bytes()) {
             Err(error) => {
-                eprintln!("{}", error);
-                return Err(FiError::new("Failed to sign content"));
+                return Err(error);
             }
-        }
+            Ok(val) => val,
+        };
+
+        self.jws = Some(URL_SAFE_NO_PAD.encode(signature));
+        return Ok(());
     }
 
-    fn verify(&self, doc: &mut VerificationDocument, content: String) -> Result<bool, FiError> {
-        let key_bytes = match doc.get_public_key_mut() {
-            None => {
-                return Err(FiError::new(
-                    "No publuc key was found in the VerificationDocument",
-                ))
-            }
-            Some(val) => val,
-        };
+    fn verify(&self, doc: &PublicVerificationDocument, content: String) -> Result<bool, FiError> {
+        self.verify_with_algorithms(doc, content, doc.get_algorithms().as_slice())
+    }
+}
 
-        let alg = match Algorithm::from_str(self.algorithm.as_str()) {
-            None => return Err(FiError::new("Provided algorithm is no supported")),
-            Some(val) => val,
-        };
+/// Verifies a base64url encoded signature over the content with the document's public key.
+pub(crate) fn verify_signature(
+    doc: &PublicVerificationDocument,
+    alg: Algorithm,
+    content: String,
+    signature: String,
+) -> Result<bool, FiError> {
+    VerifyingKeys::new(doc).verify(alg, content, signature)
+}
 
-        let verifying_key = match get_verifying_key(alg, key_bytes.as_mut_slice()) {
-            Ok(val) => val,
-            Err(error) => {
-                eprintln!("{}", error);
-                return Err(FiError::new("Failed to get signing key"));
-            }
+/// The document's public key, parsed once per algorithm and reused across signatures.
+pub(crate) struct VerifyingKeys<'a> {
+    doc: &'a PublicVerificationDocument,
+    keys: Vec<(Algorithm, Box<dyn VerifyFromKey>)>,
+}
+
+impl<'a> VerifyingKeys<'a> {
+    pub(crate) fn new(doc: &'a PublicVerificationDocument) -> Self {
+        return VerifyingKeys {
+            doc,
+            keys: Vec::new(),
         };
+    }
 
-        match self.jws.clone() {
-            Some(val) => match verifying_key.verify(content, val, alg) {
-                Ok(val) => Ok(val),
+    pub(crate) fn get_document(&self) -> &'a PublicVerificationDocument {
+        self.doc
+    }
+
+    pub(crate) fn verify(
+        &mut self,
+        alg: Algorithm,
+        content: String,
+        signature: String,
+    ) -> Result<bool, FiError> {
+        if !self.keys.iter().any(|(val, _)| *val == alg) {
+            let mut key_bytes = self.doc.get_public_key().clone();
+            match get_verifying_key(alg, key_bytes.as_mut_slice()) {
+                Ok(val) => self.keys.push((alg, val)),
                 Err(error) => {
                     eprintln!("{}", error);
-                    return Err(FiError::new("Failed to verify content"));
+                    return Err(FiError::new("Failed to get signing key"));
                 }
-            },
-            None => {
+            };
+        }
+
+        let verifying_key = match self.keys.iter().find(|(val, _)| *val == alg) {
+            None => return Err(FiError::new("Failed to get signing key")),
+            Some((_, val)) => val,
+        };
+
+        match verifying_key.verify(content, signature, alg) {
+            Ok(val) => Ok(val),
+            Err(error) => {
+                eprintln!("{}", error);
                 return Err(FiError::new("Failed to verify content"));
             }
         }
@@ -106,54 +141,257 @@ impl FiProof {
             algorithm: String::from(alg.to_str()),
             proof_purpose: purpose,
             created: datetime.to_string(),
+            expires: None,
+            nonce: None,
+            verification_method: None,
             jws: None,
         };
     }
-}
 
-#[cfg(feature = "wasm")]
-#[wasm_bindgen]
-pub enum ProofType {
-    FiProof,
-}
+    pub fn get_verification_method(&self) -> &Option<String> {
+        &self.verification_method
+    }
 
-#[cfg(feature = "wasm")]
-impl ProofType {
-    pub fn sign(
+    pub fn get_algorithm(&self) -> &String {
+        &self.algorithm
+    }
+
+    pub fn get_proof_purpose(&self) -> &String {
+        &self.proof_purpose
+    }
+
+    pub fn get_jws(&self) -> &Option<String> {
+        &self.jws
+    }
+
+    pub fn get_created(&self) -> &String {
+        &self.created
+    }
+
+    pub fn get_expires(&self) -> &Option<String> {
+        &self.expires
+    }
+
+    pub fn set_expires(&mut self, expires: Option<DateTime<Utc>>) {
+        self.expires = expires.map(|val| val.to_rfc3339());
+    }
+
+    pub fn get_nonce(&self) -> &Option<String> {
+        &self.nonce
+    }
+
+    pub fn set_nonce(&mut self, nonce: Option<String>) {
+        self.nonce = nonce;
+    }
+
+    /// Fails unless the proof carries the nonce or challenge the verifier expects. The nonce is
+    /// signed, so this only holds once the proof itself verifies.
+    pub fn check_nonce(&self, expected: &str) -> Result<(), FiError> {
+        match self.nonce.as_deref() {
+            None => Err(FiError::new("The proof has no nonce")),
+            Some(val) if val != expected => Err(FiError::new("Proof nonce does not match")),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Verifies the proof only when its algorithm is one the verification key supports and is
+    /// in `allowed`. The algorithm named by the proof is never trusted on its own.
+    pub fn verify_with_algorithms(
         &self,
-        alg: Algorithm,
-        purpose: String,
-        doc: &mut VerificationDocument,
+        doc: &PublicVerificationDocument,
         content: String,
-    ) -> Result<JsValue, FiError> {
-        match self {
-            ProofType::FiProof => {
-                let mut proof = FiProof::new(alg, purpose);
-                _ = proof.sign(doc, content);
+        allowed: &[Algorithm],
+    ) -> Result<bool, FiError> {
+        self.verify_with_keys(&mut VerifyingKeys::new(doc), content, allowed)
+    }
 
-                match serde_wasm_bindgen::to_value(&proof) {
-                    Ok(val) => return Ok(val),
-                    Err(err) => return Err(FiError::new(err.to_string().as_str())),
-                }
+    pub(crate) fn verify_with_keys(
+        &self,
+        keys: &mut VerifyingKeys,
+        content: String,
+        allowed: &[Algorithm],
+    ) -> Result<bool, FiError> {
+        let alg = match self.check(keys.doc, allowed) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        let signing_input = match self.signing_options() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => format!("{}.{}", val, content),
+        };
+
+        match self.jws.clone() {
+            Some(val) => keys.verify(alg, signing_input, val),
+            None => {
+                return Err(FiError::new("Failed to verify content"));
             }
         }
     }
 
-    pub fn verify(
+    /// The algorithm to verify the proof with, once it is allowed for the key and the proof is
+    /// within its validity period.
+    pub(crate) fn check(
         &self,
-        doc: &mut VerificationDocument,
-        content: String,
-        proof: JsValue,
-    ) -> Result<bool, FiError> {
-        match self {
-            ProofType::FiProof => {
-                let fi_proof: FiProof = match serde_wasm_bindgen::from_value(proof) {
-                    Err(error) => return Err(FiError::new(error.to_string().as_str())),
-                    Ok(val) => val,
-                };
+        doc: &PublicVerificationDocument,
+        allowed: &[Algorithm],
+    ) -> Result<Algorithm, FiError> {
+        let alg = match Algorithm::from_str(self.algorithm.as_str()) {
+            None => return Err(FiError::new("Provided algorithm is no supported")),
+            Some(val) => val,
+        };
 
-                fi_proof.verify(doc, content)
+        if !doc.get_algorithms().contains(&alg) {
+            return Err(FiError::new(
+                "Proof algorithm does not match the verification key",
+            ));
+        }
+
+        if !allowed.contains(&alg) {
+            return Err(FiError::new(
+                format!("Algorithm {} is not allowed", alg.to_str()).as_str(),
+            ));
+        }
+
+        match self.check_validity() {
+            Err(error) => Err(error),
+            Ok(()) => Ok(alg),
+        }
+    }
+
+    /// The proof options without the signature. The signing input is the options, a `.` and
+    /// the secured content.
+    pub(crate) fn signing_options(&self) -> Result<String, FiError> {
+        let mut options = match serde_json::to_value(self) {
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Failed to serialize the proof options"));
             }
+            Ok(val) => val,
+        };
+
+        options.as_object_mut().unwrap().remove("jws");
+        return Ok(options.to_string());
+    }
+
+    fn check_validity(&self) -> Result<(), FiError> {
+        let now = Utc::now();
+        let skew = Duration::seconds(PROOF_CLOCK_SKEW_SECONDS);
+        match DateTime::parse_from_rfc3339(self.created.as_str()) {
+            Err(_) => return Err(FiError::new("Proof creation time is invalid")),
+            Ok(val) if val > now + skew => {
+                return Err(FiError::new("Proof was created in the future"))
+            }
+            Ok(_) => {}
+        };
+
+        match self
+            .expires
+            .as_ref()
+            .map(|val| DateTime::parse_from_rfc3339(val))
+        {
+            None => Ok(()),
+            Some(Err(_)) => Err(FiError::new("Proof expiration time is invalid")),
+            Some(Ok(val)) if val < now - skew => Err(FiError::new("Proof has expired")),
+            Some(Ok(_)) => Ok(()),
+        }
+    }
+}
+
+/// W3C Data Integrity proof, secured by the cryptosuite named in `cryptosuite`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DataIntegrityProof {
+    #[serde(rename = "type")]
+    _type: String,
+    cryptosuite: String,
+    created: String,
+    #[serde(rename = "verificationMethod")]
+    verification_method: Option<String>,
+    #[serde(rename = "proofPurpose")]
+    proof_purpose: String,
+    #[serde(rename = "proofValue", skip_serializing_if = "Option::is_none")]
+    proof_value: Option<String>,
+}
+
+impl DataIntegrityProof {
+    pub fn new(cryptosuite: &str, verification_method: String, purpose: String) -> Self {
+        return DataIntegrityProof {
+            _type: String::from("DataIntegrityProof"),
+            cryptosuite: String::from(cryptosuite),
+            created: Utc::now().to_rfc3339(),
+            verification_method: Some(verification_method),
+            proof_purpose: purpose,
+            proof_value: None,
+        };
+    }
+
+    /// Proof configuration the cryptosuite signs, i.e. the proof without its `proofValue`.
+    pub fn get_options(&self) -> Result<Value, FiError> {
+        let mut options = self.clone();
+        options.proof_value = None;
+
+        match serde_json::to_value(options) {
+            Ok(val) => Ok(val),
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Cannot create value object from proof"));
+            }
+        }
+    }
+
+    pub fn get_cryptosuite(&self) -> &String {
+        &self.cryptosuite
+    }
+
+    pub fn get_verification_method(&self) -> &Option<String> {
+        &self.verification_method
+    }
+
+    pub fn get_proof_purpose(&self) -> &String {
+        &self.proof_purpose
+    }
+
+    pub fn get_proof_value(&self) -> &Option<String> {
+        &self.proof_value
+    }
+
+    pub fn set_proof_value(&mut self, proof_value: Option<String>) {
+        self.proof_value = proof_value;
+    }
+}
+
+/// Proof attached to a credential, either a `FiProof` or a Data Integrity proof.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum CredentialProof {
+    Fi(FiProof),
+    DataIntegrity(DataIntegrityProof),
+}
+
+impl CredentialProof {
+    pub fn get_verification_method(&self) -> &Option<String> {
+        match self {
+            CredentialProof::Fi(val) => val.get_verification_method(),
+            CredentialProof::DataIntegrity(val) => val.get_verification_method(),
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub enum ProofType {
+    FiProof,
+}
+
+#[cfg(feature = "wasm")]
+impl ProofType {
+    pub fn new_proof(&self, alg: Algorithm, purpose: String) -> FiProof {
+        match self {
+            ProofType::FiProof => FiProof::new(alg, purpose),
         }
     }
 }
@@ -0,0 +1,281 @@
+//! Credential refresh with the VC Refresh 2021 service.
+//!
+//! A wallet finds the credentials about to expire with [`expiring_credentials`] and asks the
+//! refresh service of their issuer for a new one with a presentation signed over a challenge of
+//! the service, see [`create_refresh_request`]. [`RefreshIssuer`] implements the service in
+//! process, leaving the HTTP transport to the caller.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    document::DocumentLoader,
+    error::FiError,
+    oauth::{random_token, random_urn},
+    policy::StatusChecker,
+    proof::FiProof,
+    signer::Signer,
+    vc::VC,
+    vp::VP,
+};
+
+pub const REFRESH_SERVICE_2021: &str = "VerifiableCredentialRefreshService2021";
+pub const MANUAL_REFRESH_SERVICE: &str = "ManualRefreshService2018";
+
+/// Purpose of the proof over a refresh request.
+const REFRESH_PROOF_PURPOSE: &str = "authentication";
+const CHALLENGE_LIFETIME_SECONDS: i64 = 300;
+
+/// Where and how a credential is refreshed, the `refreshService` of a credential.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum RefreshService {
+    /// The holder posts a refresh request presentation to `url`.
+    #[serde(rename = "VerifiableCredentialRefreshService2021")]
+    Refresh2021 { url: String },
+    /// The holder refreshes the credential out of band, e.g. on the page at `id`.
+    #[serde(rename = "ManualRefreshService2018")]
+    Manual { id: String },
+}
+
+impl RefreshService {
+    pub fn get_url(&self) -> &String {
+        match self {
+            RefreshService::Refresh2021 { url } => url,
+            RefreshService::Manual { id } => id,
+        }
+    }
+}
+
+impl From<RefreshService> for Value {
+    fn from(service: RefreshService) -> Self {
+        serde_json::to_value(service).unwrap_or(Value::Null)
+    }
+}
+
+/// The credentials whose `validUntil` is within `within` from now and that can be refreshed
+/// with a VC Refresh 2021 service.
+pub fn expiring_credentials(credentials: &[VC], within: Duration) -> Vec<&VC> {
+    let deadline = Utc::now() + within;
+    credentials
+        .iter()
+        .filter(|vc| match vc.get_valid_until().as_ref() {
+            None => false,
+            Some(val) => match DateTime::parse_from_rfc3339(val) {
+                Err(_) => true,
+                Ok(val) => val < deadline,
+            },
+        })
+        .filter(|vc| refresh_url(vc).is_some())
+        .collect()
+}
+
+/// The URL of the credential's VC Refresh 2021 service.
+pub fn refresh_url(credential: &VC) -> Option<String> {
+    credential
+        .get_refresh_services()
+        .into_iter()
+        .find_map(|service| match service {
+            RefreshService::Refresh2021 { url } => Some(url),
+            RefreshService::Manual { .. } => None,
+        })
+}
+
+/// Presents the credential to its refresh service, signed by the holder over the service's
+/// challenge.
+pub fn create_refresh_request(
+    credential: &VC,
+    signer: &dyn Signer,
+    challenge: &str,
+) -> Result<VP, FiError> {
+    if refresh_url(credential).is_none() {
+        return Err(FiError::new("The credential has no refresh service"));
+    }
+
+    let holder = signer.verification_method().split('#').next();
+    let mut vp = VP::new(random_urn(), holder.map(String::from));
+    vp.add_verifiable_credentials(credential.clone());
+
+    let mut proof = FiProof::new(signer.algorithm(), String::from(REFRESH_PROOF_PURPOSE));
+    proof.set_nonce(Some(String::from(challenge)));
+    proof.set_expires(Some(
+        Utc::now() + Duration::seconds(CHALLENGE_LIFETIME_SECONDS),
+    ));
+
+    match vp.sign(signer, proof) {
+        Err(error) => Err(error),
+        Ok(()) => Ok(vp),
+    }
+}
+
+/// Issuer side of the refresh service. Holder and issuer keys are resolved through the loader,
+/// refreshed credentials are valid for `validity` from the time of the refresh.
+pub struct RefreshIssuer {
+    signer: Box<dyn Signer>,
+    loader: DocumentLoader,
+    validity: Duration,
+    challenges: HashMap<String, DateTime<Utc>>,
+    status_checker: Option<Box<dyn StatusChecker>>,
+}
+
+impl RefreshIssuer {
+    pub fn new(signer: Box<dyn Signer>, loader: DocumentLoader, validity: Duration) -> Self {
+        RefreshIssuer {
+            signer,
+            loader,
+            validity,
+            challenges: HashMap::new(),
+            status_checker: None,
+        }
+    }
+
+    /// Credentials whose status the checker rejects are not refreshed.
+    pub fn set_status_checker(&mut self, status_checker: Option<Box<dyn StatusChecker>>) {
+        self.status_checker = status_checker;
+    }
+
+    /// A single use challenge the refresh request must be signed over.
+    pub fn create_challenge(&mut self) -> String {
+        let now = Utc::now();
+        self.challenges.retain(|_, expires| *expires > now);
+
+        let challenge = random_token();
+        self.challenges.insert(
+            challenge.clone(),
+            now + Duration::seconds(CHALLENGE_LIFETIME_SECONDS),
+        );
+        return challenge;
+    }
+
+    fn is_pending(&self, challenge: &str) -> bool {
+        match self.challenges.get(challenge) {
+            None => false,
+            Some(val) => *val > Utc::now(),
+        }
+    }
+
+    /// Verifies the refresh request and re-issues its credential with a new validity period.
+    /// The credential may already have expired, but it must have been issued by this issuer to
+    /// the holder signing the request.
+    pub fn refresh(&mut self, request: &VP) -> Result<VC, FiError> {
+        let proof = match request.get_proof() {
+            None => return Err(FiError::new("The refresh request is not signed")),
+            Some(val) => val,
+        };
+
+        if proof.get_proof_purpose() != REFRESH_PROOF_PURPOSE {
+            return Err(FiError::new(
+                "The refresh request must be signed for authentication",
+            ));
+        }
+
+        // The challenge is only consumed once the whole request verifies, so a request that
+        // replays or guesses it cannot make the holder's own request fail
+        let challenge = match proof.get_nonce() {
+            Some(val) if self.is_pending(val) => val,
+            _ => {
+                return Err(FiError::new(
+                    "The refresh request challenge is unknown, used or expired",
+                ))
+            }
+        };
+
+        let holder_method = match proof.get_verification_method() {
+            None => return Err(FiError::new("The proof has no verification method")),
+            Some(val) => val,
+        };
+
+        let holder_doc = match self.loader.get_verification_document(holder_method) {
+            None => return Err(FiError::new("Verification document could not be resolved")),
+            Some(val) => val,
+        };
+
+        // Also checks that a credential bound to a holder key is presented with that key
+        match request.verify(&holder_doc) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(false) => return Err(FiError::new("The refresh request signature is invalid")),
+            Ok(true) => {}
+        };
+
+        let presented = match request.get_verifiable_credentials().as_slice() {
+            [val] => val,
+            _ => {
+                return Err(FiError::new(
+                    "A refresh request must present exactly one credential",
+                ))
+            }
+        };
+
+        match presented.get_verification_method() {
+            Ok(val) if val == self.signer.verification_method() => {}
+            _ => return Err(FiError::new("The credential was not issued by this issuer")),
+        };
+
+        let issuer_doc = match self
+            .loader
+            .get_verification_document(self.signer.verification_method())
+        {
+            None => return Err(FiError::new("Verification document could not be resolved")),
+            Some(val) => val,
+        };
+
+        match presented.verify(&issuer_doc) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(false) => return Err(FiError::new("The credential signature is invalid")),
+            Ok(true) => {}
+        };
+
+        let mut credential = match presented.get_credential() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        if refresh_url(&credential).is_none() {
+            return Err(FiError::new("The credential has no refresh service"));
+        }
+
+        let holder = holder_method.split('#').next();
+        if credential.get_confirmation().is_none()
+            && credential.get_credential_subject()["id"].as_str() != holder
+        {
+            return Err(FiError::new("The credential was issued to another holder"));
+        }
+
+        if let (Some(checker), Some(status)) = (
+            self.status_checker.as_ref(),
+            credential.get_credential_status().as_ref(),
+        ) {
+            match checker.check(status) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(false) => {
+                    return Err(FiError::new(
+                        "The credential status does not allow a refresh",
+                    ))
+                }
+                Ok(true) => {}
+            };
+        }
+
+        self.challenges.remove(challenge);
+
+        let now = Utc::now();
+        credential.set_valid_from(now);
+        credential.set_valid_until(Some(now + self.validity));
+        let proof = FiProof::new(self.signer.algorithm(), String::from("assertionMethod"));
+        match credential.sign(self.signer.as_ref(), proof) {
+            Err(error) => Err(error),
+            Ok(()) => Ok(credential),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use fi_digital_signatures::algorithms::Algorithm;
+
+use crate::{
+    document::{SecretKey, SigningDocument, VerificationDocument},
+    error::FiError,
+    keys::SigningKey,
+};
+
+/// Produces signatures on behalf of a verification method without handing out the key.
+pub trait Signer {
+    /// Sign the content and return the raw signature bytes
+    fn sign(&self, content: &[u8]) -> Result<Vec<u8>, FiError>;
+
+    /// Algorithm the signatures are produced with
+    fn algorithm(&self) -> Algorithm;
+
+    /// Id of the verification method that verifies the signatures
+    fn verification_method(&self) -> &str;
+}
+
+/// Lookup of [`Signer`]s by verification method id.
+pub trait KeyStore {
+    fn get_signer(&self, verification_method: &str) -> Option<&dyn Signer>;
+}
+
+/// [`Signer`] backed by a private key held in process memory. The key is parsed once and
+/// erased when the signer is dropped. It is `Send` and `Sync`, so one signer can be shared by
+/// the threads of a batch.
+pub struct InMemorySigner {
+    key: SigningKey,
+    algorithm: Algorithm,
+    verification_method: String,
+}
+
+impl InMemorySigner {
+    pub fn new(doc: &VerificationDocument, alg: Algorithm) -> Result<Self, FiError> {
+        match doc.get_private_key() {
+            None => Err(FiError::new(
+                "No private key was found in the VerificationDocument",
+            )),
+            Some(val) => InMemorySigner::from_key(doc.get_id(), val, alg),
+        }
+    }
+
+    pub fn from_signing_document(doc: &SigningDocument, alg: Algorithm) -> Result<Self, FiError> {
+        InMemorySigner::from_key(doc.get_id(), doc.get_private_key(), alg)
+    }
+
+    fn from_key(id: &str, private_key: &SecretKey, alg: Algorithm) -> Result<Self, FiError> {
+        let key = match SigningKey::new(alg, private_key.as_bytes()) {
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Failed to get signing key"));
+            }
+            Ok(val) => val,
+        };
+
+        return Ok(InMemorySigner {
+            key,
+            algorithm: alg,
+            verification_method: String::from(id),
+        });
+    }
+}
+
+impl Signer for InMemorySigner {
+    fn sign(&self, content: &[u8]) -> Result<Vec<u8>, FiError> {
+        self.key.sign(content)
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn verification_method(&self) -> &str {
+        self.verification_method.as_str()
+    }
+}
+
+/// [`KeyStore`] keeping its signers in a map keyed by verification method id.
+pub struct InMemoryKeyStore {
+    signers: HashMap<String, Box<dyn Signer>>,
+}
+
+impl InMemoryKeyStore {
+    pub fn new() -> Self {
+        return InMemoryKeyStore {
+            signers: HashMap::new(),
+        };
+    }
+
+    pub fn add_signer(&mut self, signer: Box<dyn Signer>) {
+        self.signers
+            .insert(String::from(signer.verification_method()), signer);
+    }
+
+    pub fn remove_signer(&mut self, verification_method: &str) -> Option<Box<dyn Signer>> {
+        self.signers.remove(verification_method)
+    }
+}
+
+impl Default for InMemoryKeyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyStore for InMemoryKeyStore {
+    fn get_signer(&self, verification_method: &str) -> Option<&dyn Signer> {
+        self.signers
+            .get(verification_method)
+            .map(|signer| signer.as_ref())
+    }
+}
@@ -0,0 +1,262 @@
+//! Statements selective disclosure cryptosuites sign, one per JSON pointer to a leaf value of
+//! the credential. Arrays are leaves and `null` values are not signed. Statements are JSON
+//! pointers rather than RDF canonicalized quads, so proofs only interoperate with this library.
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::FiError,
+    proof::{CredentialProof, DataIntegrityProof},
+    vc::VC,
+};
+
+/// Pointers always revealed, as a credential cannot be represented without them.
+const REQUIRED_POINTERS: [&str; 5] = ["/@context", "/type", "/id", "/issuer", "/validFrom"];
+
+/// Credential statements split by the mandatory pointers, in pointer order.
+pub(crate) struct Statements {
+    pub(crate) mandatory: Vec<String>,
+    pub(crate) selective: Vec<String>,
+}
+
+/// The required VC fields followed by the issuer's mandatory pointers.
+pub(crate) fn with_required_pointers(pointers: &[String]) -> Vec<String> {
+    let mut mandatory: Vec<String> = REQUIRED_POINTERS
+        .iter()
+        .map(|val| val.to_string())
+        .collect();
+
+    for pointer in pointers.iter() {
+        if !mandatory.contains(pointer) {
+            mandatory.push(pointer.clone());
+        }
+    }
+
+    return mandatory;
+}
+
+/// Credential without its proof as leaf values keyed and sorted by JSON pointer.
+pub(crate) fn credential_claims(vc: &VC) -> Result<BTreeMap<String, Value>, FiError> {
+    let content = match vc.get_signable_content() {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let mut claims = BTreeMap::new();
+    collect_claims(&content, String::new(), &mut claims);
+    return Ok(claims);
+}
+
+fn collect_claims(value: &Value, pointer: String, claims: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Null => {}
+        Value::Object(map) if !map.is_empty() => {
+            for (key, item) in map.iter() {
+                let key = key.replace('~', "~0").replace('/', "~1");
+                collect_claims(item, format!("{}/{}", pointer, key), claims);
+            }
+        }
+        _ => {
+            claims.insert(pointer, value.clone());
+        }
+    };
+}
+
+pub(crate) fn split_statements(
+    claims: &BTreeMap<String, Value>,
+    mandatory_pointers: &[String],
+) -> Statements {
+    let mut statements = Statements {
+        mandatory: Vec::new(),
+        selective: Vec::new(),
+    };
+
+    for (pointer, value) in claims.iter() {
+        let statement = json!([pointer, value]).to_string();
+
+        if is_covered(pointer, mandatory_pointers) {
+            statements.mandatory.push(statement);
+        } else {
+            statements.selective.push(statement);
+        }
+    }
+
+    return statements;
+}
+
+/// Hash of the proof options, i.e. the proof without its `proofValue`.
+pub(crate) fn options_hash(proof: &DataIntegrityProof) -> Result<Vec<u8>, FiError> {
+    match proof.get_options() {
+        Err(error) => Err(error),
+        Ok(val) => Ok(Sha256::digest(val.to_string().as_bytes()).to_vec()),
+    }
+}
+
+pub(crate) fn statements_hash(statements: &[String]) -> Vec<u8> {
+    Sha256::digest(statements.join("\n").as_bytes()).to_vec()
+}
+
+/// A pointer covers the claims under it, and a whole array claim when it points into the array.
+pub(crate) fn is_covered(pointer: &str, pointers: &[String]) -> bool {
+    pointers.iter().any(|val| {
+        val == pointer
+            || pointer.starts_with(format!("{}/", val).as_str())
+            || val.starts_with(format!("{}/", pointer).as_str())
+    })
+}
+
+pub(crate) fn check_pointers(
+    claims: &BTreeMap<String, Value>,
+    pointers: &[String],
+) -> Result<(), FiError> {
+    for pointer in pointers.iter() {
+        if !claims
+            .keys()
+            .any(|val| is_covered(val, std::slice::from_ref(pointer)))
+        {
+            return Err(FiError::new(
+                format!("JSON pointer {} does not match any claim", pointer).as_str(),
+            ));
+        }
+    }
+
+    return Ok(());
+}
+
+/// Claims under the mandatory and selective pointers, with the indexes of the selected claims
+/// among the non-mandatory ones.
+pub(crate) fn disclose(
+    claims: &BTreeMap<String, Value>,
+    mandatory_pointers: &[String],
+    selective_pointers: &[String],
+) -> (Map<String, Value>, Vec<usize>) {
+    let mut disclosed = Map::new();
+    let mut selective_indexes = Vec::new();
+    let mut index = 0;
+
+    for (pointer, value) in claims.iter() {
+        if is_covered(pointer, mandatory_pointers) {
+            insert_pointer(&mut disclosed, pointer, value.clone());
+            continue;
+        }
+
+        if is_covered(pointer, selective_pointers) {
+            insert_pointer(&mut disclosed, pointer, value.clone());
+            selective_indexes.push(index);
+        }
+
+        index += 1;
+    }
+
+    return (disclosed, selective_indexes);
+}
+
+fn insert_pointer(target: &mut Map<String, Value>, pointer: &str, value: Value) {
+    let keys: Vec<String> = pointer
+        .split('/')
+        .skip(1)
+        .map(|val| val.replace("~1", "/").replace("~0", "~"))
+        .collect();
+
+    let mut current = target;
+    for key in keys[..keys.len() - 1].iter() {
+        let entry = current
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+
+        current = match entry.as_object_mut() {
+            None => return,
+            Some(val) => val,
+        };
+    }
+
+    current.insert(keys[keys.len() - 1].clone(), value);
+}
+
+/// Credential made of the disclosed claims, secured by the derived proof.
+pub(crate) fn derived_credential(
+    disclosed: Map<String, Value>,
+    proof: DataIntegrityProof,
+) -> Result<VC, FiError> {
+    let mut derived = match VC::from(Value::Object(disclosed)) {
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new(
+                "Disclosed claims do not form a credential, the credentialSubject must be disclosed",
+            ));
+        }
+        Ok(val) => val,
+    };
+
+    *derived.get_proof_mut() = Some(CredentialProof::DataIntegrity(proof));
+    return Ok(derived);
+}
+
+pub(crate) fn credential_proof<'a>(
+    vc: &'a VC,
+    cryptosuite: &str,
+) -> Result<&'a DataIntegrityProof, FiError> {
+    match vc.get_proof() {
+        Some(CredentialProof::DataIntegrity(val)) if val.get_cryptosuite() == cryptosuite => {
+            Ok(val)
+        }
+        _ => Err(FiError::new(
+            format!("Credential has no {} proof", cryptosuite).as_str(),
+        )),
+    }
+}
+
+pub(crate) fn encode_proof_value(value: &Value) -> String {
+    format!("u{}", URL_SAFE_NO_PAD.encode(value.to_string()))
+}
+
+pub(crate) fn decode_proof_value(proof: &DataIntegrityProof) -> Result<Value, FiError> {
+    let encoded = match proof.get_proof_value().as_deref() {
+        Some(val) if val.starts_with('u') => &val[1..],
+        _ => return Err(FiError::new("Proof value must be base64url multibase")),
+    };
+
+    let bytes = match URL_SAFE_NO_PAD.decode(encoded) {
+        Ok(val) => val,
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Proof value is not base64url encoded"));
+        }
+    };
+
+    match serde_json::from_slice(bytes.as_slice()) {
+        Ok(val) => Ok(val),
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Proof value is not valid JSON"));
+        }
+    }
+}
+
+pub(crate) fn proof_bytes(proof_value: &Value, name: &str) -> Result<Vec<u8>, FiError> {
+    match proof_value[name]
+        .as_str()
+        .map(|val| URL_SAFE_NO_PAD.decode(val))
+    {
+        Some(Ok(val)) => Ok(val),
+        _ => Err(FiError::new(
+            format!("Proof value {} is missing or not base64url encoded", name).as_str(),
+        )),
+    }
+}
+
+pub(crate) fn proof_pointers(proof_value: &Value) -> Result<Vec<String>, FiError> {
+    match proof_value["mandatoryPointers"].as_array() {
+        None => Err(FiError::new("Proof value has no mandatory pointers")),
+        Some(val) => Ok(val
+            .iter()
+            .filter_map(|val| val.as_str().map(String::from))
+            .collect()),
+    }
+}
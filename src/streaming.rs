@@ -0,0 +1,578 @@
+//! Verification of presentations straight from their JSON text.
+//!
+//! A [`BorrowedVP`] parses the presentation once into a tree that borrows its strings from the
+//! input. The canonical form a proof signs is written from that tree instead of re-serializing
+//! the presentation, and for ECDSA it is hashed while it is written. EdDSA and RSA sign the whole
+//! message, which is then written into a single buffer.
+
+use std::{borrow::Cow, fmt, io::Write};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use fi_digital_signatures::algorithms::Algorithm;
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::{Map, Number, Value};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::{
+    document::{DocumentLoader, PublicVerificationDocument},
+    error::FiError,
+    keys,
+    proof::{verify_signature, FiProof},
+    vp::{check_holder, check_verification_method, PresentedCredential},
+};
+
+/// JSON value whose strings are borrowed from the parsed text, unless they contain escapes.
+/// Object members are sorted by key, the order of the canonical form.
+enum Node<'a> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Cow<'a, str>),
+    Array(Vec<Node<'a>>),
+    Object(Vec<(Cow<'a, str>, Node<'a>)>),
+}
+
+impl<'a> Node<'a> {
+    fn get(&self, key: &str) -> Option<&Node<'a>> {
+        match self {
+            Node::Object(entries) => entries
+                .binary_search_by(|(name, _)| name.as_ref().cmp(key))
+                .ok()
+                .map(|index| &entries[index].1),
+            _ => None,
+        }
+    }
+
+    /// Writes the value as compact JSON with sorted keys, leaving out the member `skip`.
+    fn write<W: Write>(&self, out: &mut W, skip: Option<&str>) -> Result<(), FiError> {
+        match self {
+            Node::Null => write_bytes(out, b"null"),
+            Node::Bool(val) => write_bytes(out, if *val { b"true" } else { b"false" }),
+            Node::Number(val) => write_json(out, val),
+            Node::String(val) => write_json(out, val.as_ref()),
+            Node::Array(items) => {
+                match write_bytes(out, b"[") {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                };
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        match write_bytes(out, b",") {
+                            Err(error) => {
+                                return Err(error);
+                            }
+                            Ok(val) => val,
+                        };
+                    }
+                    match item.write(out, None) {
+                        Err(error) => {
+                            return Err(error);
+                        }
+                        Ok(val) => val,
+                    };
+                }
+                write_bytes(out, b"]")
+            }
+            Node::Object(entries) => {
+                match write_bytes(out, b"{") {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                };
+                let mut first = true;
+                for (name, item) in entries.iter() {
+                    if Some(name.as_ref()) == skip {
+                        continue;
+                    }
+                    if !first {
+                        match write_bytes(out, b",") {
+                            Err(error) => {
+                                return Err(error);
+                            }
+                            Ok(val) => val,
+                        };
+                    }
+                    first = false;
+                    match write_json(out, name.as_ref()) {
+                        Err(error) => {
+                            return Err(error);
+                        }
+                        Ok(val) => val,
+                    };
+                    match write_bytes(out, b":") {
+                        Err(error) => {
+                            return Err(error);
+                        }
+                        Ok(val) => val,
+                    };
+                    match item.write(out, None) {
+                        Err(error) => {
+                            return Err(error);
+                        }
+                        Ok(val) => val,
+                    };
+                }
+                write_bytes(out, b"}")
+            }
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Node::Null => Value::Null,
+            Node::Bool(val) => Value::Bool(*val),
+            Node::Number(val) => Value::Number(val.clone()),
+            Node::String(val) => Value::String(val.to_string()),
+            Node::Array(items) => Value::Array(items.iter().map(Node::to_value).collect()),
+            Node::Object(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(name, item)| (name.to_string(), item.to_value()))
+                    .collect::<Map<String, Value>>(),
+            ),
+        }
+    }
+}
+
+fn write_bytes<W: Write>(out: &mut W, bytes: &[u8]) -> Result<(), FiError> {
+    match out.write_all(bytes) {
+        Ok(val) => Ok(val),
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Failed to write the signing input"));
+        }
+    }
+}
+
+fn write_json<W: Write, T: serde::Serialize + ?Sized>(
+    out: &mut W,
+    value: &T,
+) -> Result<(), FiError> {
+    match serde_json::to_writer(&mut *out, value) {
+        Ok(val) => Ok(val),
+        Err(error) => {
+            eprintln!("{}", error);
+            return Err(FiError::new("Failed to write the signing input"));
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Node<'de>, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, val: bool) -> Result<Node<'de>, E> {
+        Ok(Node::Bool(val))
+    }
+
+    fn visit_i64<E: de::Error>(self, val: i64) -> Result<Node<'de>, E> {
+        Ok(Node::Number(Number::from(val)))
+    }
+
+    fn visit_u64<E: de::Error>(self, val: u64) -> Result<Node<'de>, E> {
+        Ok(Node::Number(Number::from(val)))
+    }
+
+    fn visit_f64<E: de::Error>(self, val: f64) -> Result<Node<'de>, E> {
+        Ok(Number::from_f64(val).map_or(Node::Null, Node::Number))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, val: &'de str) -> Result<Node<'de>, E> {
+        Ok(Node::String(Cow::Borrowed(val)))
+    }
+
+    fn visit_str<E: de::Error>(self, val: &str) -> Result<Node<'de>, E> {
+        Ok(Node::String(Cow::Owned(String::from(val))))
+    }
+
+    fn visit_string<E: de::Error>(self, val: String) -> Result<Node<'de>, E> {
+        Ok(Node::String(Cow::Owned(val)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node<'de>, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        loop {
+            match seq.next_element() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(Some(item)) => items.push(item),
+                Ok(None) => break,
+            };
+        }
+        Ok(Node::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node<'de>, A::Error> {
+        let mut entries: Vec<(Cow<'de, str>, Node<'de>)> = Vec::new();
+        loop {
+            let name = match map.next_key() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(Some(Key(val))) => val,
+                Ok(None) => break,
+            };
+            match map.next_value() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => entries.push((name, val)),
+            };
+        }
+
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(de::Error::custom("duplicate key in JSON object"));
+        }
+        Ok(Node::Object(entries))
+    }
+}
+
+/// Object key, borrowed from the parsed text when it has no escapes.
+struct Key<'a>(Cow<'a, str>);
+
+impl<'de> Deserialize<'de> for Key<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+struct KeyVisitor;
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Key<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object key")
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, val: &'de str) -> Result<Key<'de>, E> {
+        Ok(Key(Cow::Borrowed(val)))
+    }
+
+    fn visit_str<E: de::Error>(self, val: &str) -> Result<Key<'de>, E> {
+        Ok(Key(Cow::Owned(String::from(val))))
+    }
+
+    fn visit_string<E: de::Error>(self, val: String) -> Result<Key<'de>, E> {
+        Ok(Key(Cow::Owned(val)))
+    }
+}
+
+/// Receives the signing input of a proof, hashing it right away where the algorithm allows.
+enum SigningInput {
+    Message(Vec<u8>),
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl SigningInput {
+    fn new(alg: Algorithm) -> Self {
+        match alg {
+            Algorithm::ES256 => SigningInput::Sha256(Sha256::new()),
+            Algorithm::ES384 => SigningInput::Sha384(Sha384::new()),
+            Algorithm::ES512 => SigningInput::Sha512(Sha512::new()),
+            _ => SigningInput::Message(Vec::new()),
+        }
+    }
+
+    fn verify(
+        self,
+        doc: &PublicVerificationDocument,
+        alg: Algorithm,
+        signature: &str,
+    ) -> Result<bool, FiError> {
+        let digest = match self {
+            SigningInput::Message(val) => {
+                return match String::from_utf8(val) {
+                    Err(_) => Err(FiError::new("Failed to verify content")),
+                    Ok(content) => verify_signature(doc, alg, content, String::from(signature)),
+                };
+            }
+            SigningInput::Sha256(val) => val.finalize().to_vec(),
+            SigningInput::Sha384(val) => val.finalize().to_vec(),
+            SigningInput::Sha512(val) => val.finalize().to_vec(),
+        };
+
+        match URL_SAFE_NO_PAD.decode(signature) {
+            Err(_) => Err(FiError::new("Failed to verify content")),
+            Ok(val) => keys::verify_prehash(
+                alg,
+                doc.get_public_key().as_slice(),
+                digest.as_slice(),
+                val.as_slice(),
+            ),
+        }
+    }
+}
+
+impl Write for SigningInput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            SigningInput::Message(val) => val.extend_from_slice(buf),
+            SigningInput::Sha256(val) => val.update(buf),
+            SigningInput::Sha384(val) => val.update(buf),
+            SigningInput::Sha512(val) => val.update(buf),
+        };
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A presentation verified from its JSON text, with the same results as [`crate::vp::VP`]
+/// for presentations secured by this crate.
+pub struct BorrowedVP<'a> {
+    root: Node<'a>,
+}
+
+impl<'a> BorrowedVP<'a> {
+    pub fn parse(json: &'a str) -> Result<BorrowedVP<'a>, FiError> {
+        match serde_json::from_str::<Node>(json) {
+            Err(error) => Err(FiError::new(error.to_string().as_str())),
+            Ok(root @ Node::Object(_)) => Ok(BorrowedVP { root }),
+            Ok(_) => Err(FiError::new("The presentation is not a JSON object")),
+        }
+    }
+
+    pub fn verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        self.verify_with_algorithms(doc, doc.get_algorithms().as_slice())
+    }
+
+    /// Like [`BorrowedVP::verify`], but only accepts a proof signed with one of `allowed`.
+    pub fn verify_with_algorithms(
+        &self,
+        doc: &PublicVerificationDocument,
+        allowed: &[Algorithm],
+    ) -> Result<bool, FiError> {
+        let proof = match self.root.get("proof").map(read_proof) {
+            None => return Err(FiError::new("No proof was found")),
+            Some(Err(error)) => {
+                return Err(error);
+            }
+            Some(Ok(val)) => val,
+        };
+
+        match verify_node(&self.root, &proof, doc, allowed) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(false) => return Ok(false),
+            Ok(true) => {}
+        };
+
+        match check_verification_method(proof.get_verification_method(), doc) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        match self.check_holder_binding(doc) {
+            Err(error) => Err(error),
+            Ok(()) => Ok(true),
+        }
+    }
+
+    /// Fails when the presentation names a holder other than the controller of the signing
+    /// key, or a credential bound to a holder key was not presented with that key.
+    pub fn check_holder_binding(&self, doc: &PublicVerificationDocument) -> Result<(), FiError> {
+        let holder = match self.root.get("holder") {
+            None | Some(Node::Null) => None,
+            Some(Node::String(val)) => Some(val.as_ref()),
+            Some(_) => return Err(FiError::new("The presentation holder must be a string")),
+        };
+
+        match check_holder(holder, doc) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        for credential in self.credentials() {
+            // Only credentials with a `cnf` claim are bound, embedded ones show it directly
+            if credential.get("proof").is_some() && credential.get("cnf").is_none() {
+                continue;
+            }
+
+            let vc = match read_credential(credential).and_then(|val| val.get_credential()) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+
+            match vc.check_holder_binding(doc) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+        }
+
+        return Ok(());
+    }
+
+    /// Verifies every credential against the document of its verification method. Embedded
+    /// credentials with a `FiProof` are verified in place, other ones are read in full.
+    pub fn verify_credentials(&self, loader: &DocumentLoader) -> Result<bool, FiError> {
+        for credential in self.credentials() {
+            let result = match credential.get("proof") {
+                Some(proof) if proof.get("cryptosuite").is_none() => {
+                    verify_embedded(credential, proof, loader)
+                }
+                _ => verify_presented(credential, loader),
+            };
+
+            match result {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(false) => return Ok(false),
+                Ok(true) => {}
+            };
+        }
+
+        return Ok(true);
+    }
+
+    fn credentials(&self) -> &[Node<'a>] {
+        match self.root.get("verifiableCredential") {
+            Some(Node::Array(items)) => items.as_slice(),
+            _ => &[],
+        }
+    }
+}
+
+fn read_proof(node: &Node) -> Result<FiProof, FiError> {
+    match serde_json::from_value(node.to_value()) {
+        Err(error) => Err(FiError::new(error.to_string().as_str())),
+        Ok(val) => Ok(val),
+    }
+}
+
+fn read_credential(node: &Node) -> Result<PresentedCredential, FiError> {
+    match serde_json::from_value(node.to_value()) {
+        Err(error) => Err(FiError::new(error.to_string().as_str())),
+        Ok(val) => Ok(val),
+    }
+}
+
+/// Verifies the proof over the node without its `proof` member.
+fn verify_node(
+    node: &Node,
+    proof: &FiProof,
+    doc: &PublicVerificationDocument,
+    allowed: &[Algorithm],
+) -> Result<bool, FiError> {
+    let alg = match proof.check(doc, allowed) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let options = match proof.signing_options() {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let signature = match proof.get_jws() {
+        None => return Err(FiError::new("Failed to verify content")),
+        Some(val) => val,
+    };
+
+    let mut input = SigningInput::new(alg);
+    match write_bytes(&mut input, options.as_bytes()) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+    match write_bytes(&mut input, b".") {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+    match node.write(&mut input, Some("proof")) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    input.verify(doc, alg, signature.as_str())
+}
+
+fn verify_embedded(
+    credential: &Node,
+    proof: &Node,
+    loader: &DocumentLoader,
+) -> Result<bool, FiError> {
+    let proof = match read_proof(proof) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let verification_method = match proof.get_verification_method() {
+        None => return Err(FiError::new("The proof has no verification method")),
+        Some(val) => val,
+    };
+
+    match loader.get_verification_document(verification_method.as_str()) {
+        None => Err(FiError::new("Verification document could not be resolved")),
+        Some(doc) => verify_node(credential, &proof, &doc, doc.get_algorithms().as_slice()),
+    }
+}
+
+fn verify_presented(credential: &Node, loader: &DocumentLoader) -> Result<bool, FiError> {
+    let credential = match read_credential(credential) {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    let verification_method = match credential.get_verification_method() {
+        Err(error) => {
+            return Err(error);
+        }
+        Ok(val) => val,
+    };
+
+    match loader.get_verification_document(verification_method.as_str()) {
+        None => Err(FiError::new("Verification document could not be resolved")),
+        Some(doc) => credential.verify(&doc),
+    }
+}
@@ -1,31 +1,36 @@
-#[cfg(not(feature = "wasm"))]
-use std::borrow::{Borrow, BorrowMut};
-use std::collections::HashMap;
-
-#[cfg(not(feature = "wasm"))]
-use crate::constants::FIELD_CASTING_ERROR;
-#[cfg(feature = "wasm")]
-use crate::proof::ProofType;
-#[cfg(not(feature = "wasm"))]
-use crate::proof::{FiProof, Proof};
-use crate::{document::VerificationDocument, error::FiError};
-#[cfg(not(feature = "wasm"))]
-use chrono::DateTime;
-use chrono::Utc;
+use std::{
+    borrow::{Borrow, BorrowMut},
+    collections::HashMap,
+};
+
+use crate::{
+    bbs,
+    constants::FIELD_CASTING_ERROR,
+    cose::{verified_json_payload, CoseSign1, VC_CONTENT_TYPE, VC_COSE_TYPE},
+    document::{DocumentLoader, PublicVerificationDocument},
+    ecdsa_sd,
+    error::FiError,
+    jose::{Jwt, VC_JWT_TYPE},
+    proof::{CredentialProof, FiProof, Proof, VerifyingKeys},
+    refresh::RefreshService,
+    signer::Signer,
+};
 #[cfg(feature = "wasm")]
+use crate::{
+    document::{SigningDocument, VerificationDocument},
+    proof::ProofType,
+    signer::InMemorySigner,
+    wasm::{from_js_value, optional_js_value, to_js_value},
+};
+use chrono::{DateTime, Utc};
 use fi_digital_signatures::algorithms::Algorithm;
-#[cfg(feature = "wasm")]
-use js_sys::{Array, Object};
-#[cfg(not(feature = "wasm"))]
 use serde::{Deserialize, Serialize};
-#[cfg(not(feature = "wasm"))]
-use serde_json::Value;
+use serde_json::{json, Value};
 use wasm_bindgen::prelude::wasm_bindgen;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::JsValue;
 
-#[cfg(not(feature = "wasm"))]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[wasm_bindgen]
 pub struct VC {
     #[serde(rename = "@context")]
@@ -54,17 +59,14 @@ pub struct VC {
     refresh_service: Option<Value>,
     #[serde(rename = "termsOfUse", skip_serializing_if = "Option::is_none")]
     terms_of_use: Option<Value>,
+    #[serde(rename = "cnf", skip_serializing_if = "Option::is_none")]
+    confirmation: Option<Value>,
     #[serde(rename = "proof", skip_serializing_if = "Option::is_none")]
-    proof: Option<FiProof>,
-    #[serde(skip_serializing, skip_deserializing)]
+    proof: Option<CredentialProof>,
+    #[serde(flatten)]
     optional_fields: HashMap<String, Box<Value>>,
 }
 
-#[cfg(feature = "wasm")]
-#[wasm_bindgen]
-pub struct VC(HashMap<String, Box<JsValue>>);
-
-#[cfg(not(feature = "wasm"))]
 impl VC {
     pub fn new(
         id: String,
@@ -84,13 +86,11 @@ impl VC {
             description,
             issuer,
             valid_from: datetime,
-            valid_until: match valid_until {
-                Some(val) => Some(val.to_rfc3339()),
-                None => None,
-            },
+            valid_until: valid_until.map(|val| val.to_rfc3339()),
             credential_status: None,
             optional_fields: HashMap::new(),
             credential_schema: None,
+            confirmation: None,
             proof: None,
             refresh_service: None,
             terms_of_use: None,
@@ -101,26 +101,17 @@ impl VC {
         vc
     }
 
+    /// Adds an issuer, turning a single issuer into an array of issuers.
     pub fn add_issuer(&mut self, issuer: Value) -> Result<(), FiError> {
-        if self.issuer.is_array() {
-            let arr = match self.issuer.as_array_mut() {
-                Some(val) => val,
-                None => return Err(FiError::new(FIELD_CASTING_ERROR)),
-            };
-
-            arr.push(issuer);
-
-            self.issuer = match serde_json::to_value(arr) {
-                Ok(val) => val,
-                Err(error) => {
-                    eprint!("{}", error);
-                    return Err(FiError::new(FIELD_CASTING_ERROR));
-                }
-            };
-        } else {
-            return Err(FiError::new(FIELD_CASTING_ERROR));
+        if !self.issuer.is_array() {
+            self.issuer = Value::Array(vec![self.issuer.take()]);
         }
 
+        match self.issuer.as_array_mut() {
+            None => return Err(FiError::new(FIELD_CASTING_ERROR)),
+            Some(val) => val.push(issuer),
+        };
+
         return Ok(());
     }
 
@@ -136,10 +127,18 @@ impl VC {
         self.contexts.push(context)
     }
 
+    pub fn add_type(&mut self, _type: String) {
+        self.types.push(_type);
+    }
+
     pub fn set_types(&mut self, types: Vec<String>) {
         self.types = types;
     }
 
+    pub fn set_credential_subject(&mut self, credential_subject: Value) {
+        self.credential_subject = credential_subject;
+    }
+
     pub fn set_credential_status(&mut self, credential_status: Option<Value>) {
         self.credential_status = credential_status;
     }
@@ -152,6 +151,14 @@ impl VC {
         self.valid_until = expire;
     }
 
+    pub fn set_valid_until(&mut self, valid_until: Option<DateTime<Utc>>) {
+        self.valid_until = valid_until.map(|val| val.to_rfc3339());
+    }
+
+    pub fn set_valid_from(&mut self, valid_from: DateTime<Utc>) {
+        self.valid_from = valid_from.to_rfc3339();
+    }
+
     pub fn set_terms_of_use(&mut self, terms_of_use: Option<Value>) {
         self.terms_of_use = terms_of_use;
     }
@@ -164,19 +171,131 @@ impl VC {
         self.evidence = evidence;
     }
 
-    pub fn get_proof(&self) -> &Option<FiProof> {
+    pub fn get_id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn get_contexts(&self) -> &Vec<Value> {
+        &self.contexts
+    }
+
+    pub fn get_types(&self) -> &Vec<String> {
+        &self.types
+    }
+
+    pub fn get_issuer(&self) -> &Value {
+        &self.issuer
+    }
+
+    pub fn get_valid_from(&self) -> &String {
+        &self.valid_from
+    }
+
+    pub fn get_valid_until(&self) -> &Option<String> {
+        &self.valid_until
+    }
+
+    pub fn get_credential_subject(&self) -> &Value {
+        &self.credential_subject
+    }
+
+    pub fn get_refresh_service(&self) -> &Option<Value> {
+        &self.refresh_service
+    }
+
+    /// The refresh services of a known type, read from one service or an array of them.
+    pub fn get_refresh_services(&self) -> Vec<RefreshService> {
+        let services = match self.refresh_service.as_ref() {
+            None => return Vec::new(),
+            Some(Value::Array(val)) => val.iter().collect::<Vec<&Value>>(),
+            Some(val) => vec![val],
+        };
+
+        services
+            .into_iter()
+            .filter_map(|val| serde_json::from_value(val.clone()).ok())
+            .collect()
+    }
+
+    pub fn get_credential_status(&self) -> &Option<Value> {
+        &self.credential_status
+    }
+
+    /// Binds the credential to a holder key with a `cnf` claim, either `{"jwk": ...}` or
+    /// `{"kid": ...}` naming a verification method or a DID.
+    pub fn set_confirmation(&mut self, confirmation: Option<Value>) {
+        self.confirmation = confirmation;
+    }
+
+    pub fn get_confirmation(&self) -> &Option<Value> {
+        &self.confirmation
+    }
+
+    /// Binds the credential to the document's public key.
+    pub fn bind_to_key(&mut self, doc: &PublicVerificationDocument) -> Result<(), FiError> {
+        let jwk = match doc.to_jwk() {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        self.confirmation = Some(json!({ "jwk": jwk }));
+        return Ok(());
+    }
+
+    /// Binds the credential to any key of the credential subject's DID.
+    pub fn bind_to_subject(&mut self) -> Result<(), FiError> {
+        let subject = match self.credential_subject["id"].as_str() {
+            None => return Err(FiError::new("The credential subject has no id")),
+            Some(val) => val,
+        };
+
+        self.confirmation = Some(json!({ "kid": subject }));
+        return Ok(());
+    }
+
+    /// Fails when the credential is bound to a key other than the document's. Credentials
+    /// without a `cnf` claim are not bound to a holder.
+    pub fn check_holder_binding(&self, doc: &PublicVerificationDocument) -> Result<(), FiError> {
+        let confirmation = match self.confirmation.as_ref() {
+            None => return Ok(()),
+            Some(val) => val,
+        };
+
+        let bound = if let Some(jwk) = confirmation.get("jwk") {
+            match PublicVerificationDocument::from_jwk(String::new(), jwk) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val.get_public_key() == doc.get_public_key(),
+            }
+        } else if let Some(kid) = confirmation["kid"].as_str() {
+            let id = doc.get_id().as_str();
+            id == kid || (!kid.contains('#') && id.split('#').next() == Some(kid))
+        } else {
+            return Err(FiError::new(
+                format!("Credential {} has an unsupported cnf claim", self.id).as_str(),
+            ));
+        };
+
+        match bound {
+            true => Ok(()),
+            false => Err(FiError::new(
+                format!("Credential {} is bound to another holder", self.id).as_str(),
+            )),
+        }
+    }
+
+    pub fn get_proof(&self) -> &Option<CredentialProof> {
         self.proof.borrow()
     }
 
-    pub fn get_proof_mut(&mut self) -> &mut Option<FiProof> {
+    pub fn get_proof_mut(&mut self) -> &mut Option<CredentialProof> {
         self.proof.borrow_mut()
     }
 
-    pub fn sign(
-        &mut self,
-        doc: &mut VerificationDocument,
-        mut proof: FiProof,
-    ) -> Result<(), FiError> {
+    pub fn sign(&mut self, signer: &dyn Signer, mut proof: FiProof) -> Result<(), FiError> {
         let signable_values = match self.get_signable_content() {
             Err(error) => {
                 return Err(error);
@@ -184,18 +303,58 @@ impl VC {
             Ok(val) => val,
         };
 
-        match proof.sign(doc, signable_values.to_string()) {
+        match proof.sign(signer, signable_values.to_string()) {
             Err(error) => {
                 return Err(error);
             }
             Ok(val) => val,
         };
 
-        self.proof = Some(proof);
+        self.proof = Some(CredentialProof::Fi(proof));
         return Ok(());
     }
 
-    pub fn verify(&mut self, doc: &mut VerificationDocument) -> Result<bool, FiError> {
+    pub fn verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        self.verify_with_algorithms(doc, doc.get_algorithms().as_slice())
+    }
+
+    /// Like [`VC::verify`], but only accepts an embedded proof signed with one of `allowed`.
+    /// Data Integrity proofs are bound to their cryptosuite instead.
+    pub fn verify_with_algorithms(
+        &self,
+        doc: &PublicVerificationDocument,
+        allowed: &[Algorithm],
+    ) -> Result<bool, FiError> {
+        self.verify_with_keys(&mut VerifyingKeys::new(doc), allowed)
+    }
+
+    /// Like [`VC::verify`], but also fails unless the proof was signed over `nonce`.
+    pub fn verify_with_nonce(
+        &self,
+        doc: &PublicVerificationDocument,
+        nonce: &str,
+    ) -> Result<bool, FiError> {
+        match self.proof.as_ref() {
+            Some(CredentialProof::Fi(val)) => match val.check_nonce(nonce) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+            Some(CredentialProof::DataIntegrity(_)) => {
+                return Err(FiError::new("The proof has no nonce"))
+            }
+            None => return Err(FiError::new("No proof was found")),
+        };
+
+        self.verify(doc)
+    }
+
+    pub(crate) fn verify_with_keys(
+        &self,
+        keys: &mut VerifyingKeys,
+        allowed: &[Algorithm],
+    ) -> Result<bool, FiError> {
         let signable_values = match self.get_signable_content() {
             Err(error) => {
                 return Err(error);
@@ -203,16 +362,44 @@ impl VC {
             Ok(val) => val,
         };
 
-        let proof = match self.proof.as_mut() {
-            None => return Err(FiError::new("Cannot get proof as a mutable reference")),
+        match self.proof.as_ref() {
+            None => Err(FiError::new("No proof was found")),
+            Some(CredentialProof::Fi(val)) => {
+                val.verify_with_keys(keys, signable_values.to_string(), allowed)
+            }
+            Some(CredentialProof::DataIntegrity(val)) => match val.get_cryptosuite().as_str() {
+                bbs::FI_BBS_2023 => bbs::verify_proof(self, val, keys.get_document()),
+                ecdsa_sd::FI_ECDSA_SD_2023 => {
+                    ecdsa_sd::verify_proof(self, val, keys.get_document())
+                }
+                _ => Err(FiError::new("Cryptosuite is not supported")),
+            },
+        }
+    }
+
+    /// Resolves the proof's verification method through the loader and verifies against it.
+    pub async fn verify_async(&self, loader: &DocumentLoader) -> Result<bool, FiError> {
+        let verification_method = match self.proof.as_ref() {
+            None => return Err(FiError::new("No proof was found")),
+            Some(val) => match val.get_verification_method() {
+                None => return Err(FiError::new("The proof has no verification method")),
+                Some(val) => val.clone(),
+            },
+        };
+
+        let doc = match loader
+            .get_verification_document_async(verification_method.as_str())
+            .await
+        {
+            None => return Err(FiError::new("Verification document could not be resolved")),
             Some(val) => val,
         };
 
-        proof.verify(doc, signable_values.to_string())
+        self.verify(&doc)
     }
 
-    pub fn to_object(&mut self) -> Result<Value, FiError> {
-        let mut value = match serde_json::to_value(&self) {
+    pub fn to_object(&self) -> Result<Value, FiError> {
+        let value = match serde_json::to_value(self) {
             Err(error) => {
                 eprintln!("{}", error);
                 return Err(FiError::new("Cannot create value object from VC"));
@@ -220,16 +407,10 @@ impl VC {
             Ok(val) => val,
         };
 
-        let obj = value.as_object_mut().unwrap();
-
-        self.optional_fields.iter_mut().for_each(move |(key, val)| {
-            obj.insert(String::from(key), val.take());
-        });
-
         return Ok(value);
     }
 
-    pub fn get_signable_content(&mut self) -> Result<Value, FiError> {
+    pub fn get_signable_content(&self) -> Result<Value, FiError> {
         let mut val = match self.to_object() {
             Err(error) => {
                 return Err(error);
@@ -242,19 +423,54 @@ impl VC {
         return Ok(val);
     }
 
+    /// Secures the credential as a `vc+jwt`, whose payload is the credential without
+    /// its proof.
+    pub fn to_jwt(&self, signer: &dyn Signer) -> Result<String, FiError> {
+        match self.get_signable_content() {
+            Err(error) => Err(error),
+            Ok(val) => Jwt::sign(&val, VC_JWT_TYPE, signer),
+        }
+    }
+
+    /// Secures the credential as a `vc+jwt` carrying the issuer's X.509 certificate chain, the
+    /// issuer's certificate first.
+    pub fn to_jwt_with_x5c(&self, signer: &dyn Signer, x5c: &[Vec<u8>]) -> Result<String, FiError> {
+        match self.get_signable_content() {
+            Err(error) => Err(error),
+            Ok(val) => Jwt::sign_with_x5c(&val, VC_JWT_TYPE, signer, x5c),
+        }
+    }
+
+    /// Secures the credential as a `vc+cose` COSE_Sign1 message, whose payload is the
+    /// credential JSON without its proof.
+    pub fn to_cose(&self, signer: &dyn Signer) -> Result<Vec<u8>, FiError> {
+        match self.get_signable_content() {
+            Err(error) => Err(error),
+            Ok(val) => CoseSign1::sign(
+                val.to_string().as_bytes(),
+                VC_COSE_TYPE,
+                VC_CONTENT_TYPE,
+                signer,
+            ),
+        }
+    }
+
+    /// Reads a credential secured with COSE, failing unless the signature verifies with the
+    /// document's key.
+    pub fn from_cose(bytes: &[u8], doc: &PublicVerificationDocument) -> Result<Self, FiError> {
+        match verified_json_payload(bytes, VC_CONTENT_TYPE, doc) {
+            Err(error) => Err(error),
+            Ok(val) => VC::from(val),
+        }
+    }
+
     pub fn add_field(&mut self, key: &str, val: Value) {
         self.optional_fields
             .insert(String::from(key), Box::new(val));
     }
 
     pub fn from(value: Value) -> Result<Self, FiError> {
-        let mut map: HashMap<String, Box<Value>> = HashMap::new();
-
-        match serde_ignored::deserialize(&value, |path| {
-            let _path = path.to_string();
-            let value_to_save = value[&_path].clone();
-            map.insert(_path, Box::new(value_to_save));
-        }) {
+        match serde_json::from_value(value) {
             Ok(val) => return Ok(val),
             Err(error) => return Err(FiError::new(error.to_string().as_str())),
         };
@@ -265,254 +481,301 @@ impl VC {
 #[wasm_bindgen]
 impl VC {
     #[wasm_bindgen(constructor)]
-    pub fn new(
+    pub fn js_new(
         id: String,
         issuer: JsValue,
         name: JsValue,
         description: JsValue,
         valid_until: JsValue,
         contexts: Vec<String>,
-    ) -> VC {
-        let datetime = Utc::now().to_rfc3339();
-        let mut vc: HashMap<String, Box<JsValue>> = HashMap::new();
-
-        let mut types: Vec<JsValue> = Vec::new();
-        types.push(JsValue::from_str("VerifiableCredential"));
-
-        vc.insert(String::from("type"), Box::new(JsValue::from(types)));
-        vc.insert(String::from("@context"), Box::new(JsValue::from(contexts)));
-        vc.insert(String::from("credentialSubject"), Box::new(JsValue::NULL));
-        vc.insert(String::from("evidence"), Box::new(JsValue::NULL));
-        vc.insert(String::from("id"), Box::new(JsValue::from(id)));
-        vc.insert(String::from("name"), Box::new(name));
-        vc.insert(String::from("description"), Box::new(description));
-        vc.insert(String::from("issuer"), Box::new(issuer));
-        vc.insert(String::from("validFrom"), Box::new(JsValue::from(datetime)));
-        vc.insert(String::from("validUntil"), Box::new(valid_until));
-        vc.insert(String::from("credentialStatus"), Box::new(JsValue::NULL));
-        vc.insert(String::from("credentialSchema"), Box::new(JsValue::NULL));
-        vc.insert(String::from("proof"), Box::new(JsValue::null()));
-        vc.insert(String::from("termsOfUse"), Box::new(JsValue::NULL));
-        vc.insert(String::from("refreshService"), Box::new(JsValue::NULL));
-
-        VC(vc)
-    }
-
-    #[wasm_bindgen(js_name = "addIsser")]
-    pub fn add_issuer(&mut self, issuer: JsValue) -> Result<(), FiError> {
-        if self.0["issuer"].is_array() {
-            let arr: Array = js_sys::Array::from(&*self.0["issuer"]);
-
-            arr.push(&issuer.clone());
-
-            self.0
-                .insert(String::from("issuer"), Box::new(JsValue::from(arr)));
-        } else {
-            let arr = js_sys::Array::new();
-            arr.push(&self.0["issuer"].to_owned());
-            arr.push(&issuer.clone());
+    ) -> Result<VC, FiError> {
+        let mut vc = VC::new(
+            id,
+            match from_js_value(issuer) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+            match optional_js_value(name) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+            match optional_js_value(description) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            },
+            None,
+        );
 
-            self.0
-                .insert(String::from("issuer"), Box::new(JsValue::from(arr)));
-        }
+        vc.valid_until = valid_until.as_string();
+        vc.contexts = contexts.into_iter().map(Value::from).collect();
 
-        return Ok(());
+        return Ok(vc);
     }
 
-    #[wasm_bindgen(js_name = "setIsser")]
-    pub fn set_issuer(&mut self, issuer: JsValue) {
-        self.0.insert(String::from("issuer"), Box::new(issuer));
+    #[wasm_bindgen(js_name = "addIssuer")]
+    pub fn js_add_issuer(&mut self, issuer: JsValue) -> Result<(), FiError> {
+        match from_js_value(issuer) {
+            Err(error) => Err(error),
+            Ok(val) => self.add_issuer(val),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "setIssuer")]
+    pub fn js_set_issuer(&mut self, issuer: JsValue) -> Result<(), FiError> {
+        match from_js_value(issuer) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_issuer(val);
+                Ok(())
+            }
+        }
     }
 
     #[wasm_bindgen(js_name = "setContext")]
-    pub fn set_context(&mut self, contexts: JsValue) {
-        self.0.insert(String::from("@context"), Box::new(contexts));
+    pub fn js_set_context(&mut self, contexts: JsValue) -> Result<(), FiError> {
+        match from_js_value(contexts) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_context(val);
+                Ok(())
+            }
+        }
     }
 
     #[wasm_bindgen(js_name = "addContext")]
-    pub fn add_context(&mut self, context: JsValue) -> Result<(), FiError> {
-        if self.0["@context"].is_array() {
-            let arr: Array = js_sys::Array::from(&*self.0["@context"]);
-
-            arr.push(&context.clone());
-
-            self.0
-                .insert(String::from("@context"), Box::new(JsValue::from(arr)));
-        } else {
-            let arr = js_sys::Array::new();
-            arr.push(&self.0["@context"].to_owned());
-            arr.push(&context.clone());
-
-            self.0
-                .insert(String::from("@context"), Box::new(JsValue::from(arr)));
+    pub fn js_add_context(&mut self, context: JsValue) -> Result<(), FiError> {
+        match from_js_value(context) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.add_context(val);
+                Ok(())
+            }
         }
-
-        return Ok(());
     }
 
     #[wasm_bindgen(js_name = "addType")]
-    pub fn add_type(&mut self, _type: JsValue) -> Result<(), FiError> {
-        if self.0["type"].is_array() {
-            let arr: Array = js_sys::Array::from(&*self.0["type"]);
-
-            arr.push(&_type.clone());
-
-            self.0
-                .insert(String::from("type"), Box::new(JsValue::from(arr)));
-        } else {
-            let arr = js_sys::Array::new();
-            arr.push(&self.0["type"].to_owned());
-            arr.push(&_type.clone());
-
-            self.0
-                .insert(String::from("type"), Box::new(JsValue::from(arr)));
-        }
-
-        return Ok(());
+    pub fn js_add_type(&mut self, _type: String) {
+        self.add_type(_type);
     }
 
     #[wasm_bindgen(js_name = "setType")]
-    pub fn set_type(&mut self, _type: JsValue) {
-        self.0.insert(String::from("type"), Box::new(_type));
+    pub fn js_set_type(&mut self, types: Vec<String>) {
+        self.set_types(types);
+    }
+
+    #[wasm_bindgen(js_name = "setCredentialSubject")]
+    pub fn js_set_credential_subject(
+        &mut self,
+        credential_subject: JsValue,
+    ) -> Result<(), FiError> {
+        match from_js_value(credential_subject) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_credential_subject(val);
+                Ok(())
+            }
+        }
     }
 
     #[wasm_bindgen(js_name = "setCredentialStatus")]
-    pub fn set_credential_status(&mut self, credential_status: JsValue) {
-        self.0.insert(
-            String::from("credentialStatus"),
-            Box::new(credential_status),
-        );
+    pub fn js_set_credential_status(&mut self, credential_status: JsValue) -> Result<(), FiError> {
+        match optional_js_value(credential_status) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_credential_status(val);
+                Ok(())
+            }
+        }
     }
 
     #[wasm_bindgen(js_name = "setCredentialSchemas")]
-    pub fn set_credential_schemas(&mut self, credential_schema: JsValue) {
-        self.0.insert(
-            String::from("credentialSchema"),
-            Box::new(credential_schema),
-        );
+    pub fn js_set_credential_schemas(&mut self, credential_schema: JsValue) -> Result<(), FiError> {
+        match optional_js_value(credential_schema) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_credential_schemas(val);
+                Ok(())
+            }
+        }
+    }
+
+    #[wasm_bindgen(js_name = "setValidUntil")]
+    pub fn js_set_valid_until(&mut self, valid_until: Option<String>) {
+        self.set_expire(valid_until);
     }
 
     #[wasm_bindgen(js_name = "setExpire")]
-    pub fn set_expire(&mut self, expire: JsValue) {
-        self.0.insert(String::from("expire"), Box::new(expire));
+    pub fn js_set_expire(&mut self, expire: Option<String>) {
+        self.set_expire(expire);
     }
 
     #[wasm_bindgen(js_name = "setTermsOfUse")]
-    pub fn set_terms_of_use(&mut self, terms_of_use: JsValue) {
-        self.0
-            .insert(String::from("termsOfUse"), Box::new(terms_of_use));
+    pub fn js_set_terms_of_use(&mut self, terms_of_use: JsValue) -> Result<(), FiError> {
+        match optional_js_value(terms_of_use) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_terms_of_use(val);
+                Ok(())
+            }
+        }
     }
 
     #[wasm_bindgen(js_name = "setRefreshService")]
-    pub fn set_refresh_service(&mut self, refresh_service: JsValue) {
-        self.0
-            .insert(String::from("refreshService"), Box::new(refresh_service));
+    pub fn js_set_refresh_service(&mut self, refresh_service: JsValue) -> Result<(), FiError> {
+        match optional_js_value(refresh_service) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_refresh_service(val);
+                Ok(())
+            }
+        }
     }
 
     #[wasm_bindgen(js_name = "setEvidence")]
-    pub fn set_evidence(&mut self, evidence: JsValue) {
-        self.0.insert(String::from("issuer"), Box::new(evidence));
+    pub fn js_set_evidence(&mut self, evidence: JsValue) -> Result<(), FiError> {
+        match optional_js_value(evidence) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_evidence(val);
+                Ok(())
+            }
+        }
+    }
+
+    #[wasm_bindgen(js_name = "setConfirmation")]
+    pub fn js_set_confirmation(&mut self, confirmation: JsValue) -> Result<(), FiError> {
+        match optional_js_value(confirmation) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_confirmation(val);
+                Ok(())
+            }
+        }
     }
 
     #[wasm_bindgen(js_name = "getProof")]
-    pub fn get_proof(&self) -> JsValue {
-        *self.0["proof"].clone()
+    pub fn js_get_proof(&self) -> Result<JsValue, FiError> {
+        to_js_value(&self.proof)
     }
 
-    #[wasm_bindgen]
-    pub fn sign(
+    #[wasm_bindgen(js_name = "sign")]
+    pub fn js_sign(
         &mut self,
         alg: Algorithm,
         purpose: String,
-        doc: &mut VerificationDocument,
+        doc: &SigningDocument,
         proof_type: ProofType,
     ) -> Result<(), FiError> {
-        let signable_values = match self.get_signable_content() {
+        let signer = match InMemorySigner::from_signing_document(doc, alg) {
             Err(error) => {
                 return Err(error);
             }
             Ok(val) => val,
         };
 
-        let proof = match proof_type.sign(alg, purpose, doc, signable_values.to_string()) {
-            Err(error) => {
-                return Err(error);
-            }
-            Ok(val) => val,
-        };
+        self.sign(&signer, proof_type.new_proof(alg, purpose))
+    }
 
-        self.0.insert(String::from("proof"), Box::new(proof));
-        return Ok(());
+    /// Adds a `fi-bbs-2023` base proof, see `bbs::add_base_proof`.
+    #[wasm_bindgen(js_name = "addBbsProof")]
+    pub fn js_add_bbs_proof(
+        &mut self,
+        doc: &VerificationDocument,
+        purpose: String,
+        mandatory_pointers: Vec<String>,
+    ) -> Result<(), FiError> {
+        bbs::add_base_proof(self, doc, purpose, &mandatory_pointers)
+    }
+
+    /// Derives a credential disclosing the selected claims, see `bbs::derive_proof`.
+    #[wasm_bindgen(js_name = "deriveBbsProof")]
+    pub fn js_derive_bbs_proof(
+        &self,
+        selective_pointers: Vec<String>,
+        presentation_header: Option<Vec<u8>>,
+    ) -> Result<VC, FiError> {
+        bbs::derive_proof(self, &selective_pointers, presentation_header.as_deref())
     }
 
-    #[wasm_bindgen]
-    pub fn verify(
+    /// Adds a `fi-ecdsa-sd-2023` base proof, see `ecdsa_sd::add_base_proof`.
+    #[wasm_bindgen(js_name = "addEcdsaSdProof")]
+    pub fn js_add_ecdsa_sd_proof(
         &mut self,
-        doc: &mut VerificationDocument,
-        proof_type: ProofType,
-    ) -> Result<bool, FiError> {
-        let signable_values = match self.get_signable_content() {
+        alg: Algorithm,
+        purpose: String,
+        doc: &SigningDocument,
+        mandatory_pointers: Vec<String>,
+    ) -> Result<(), FiError> {
+        let signer = match InMemorySigner::from_signing_document(doc, alg) {
             Err(error) => {
                 return Err(error);
             }
             Ok(val) => val,
         };
 
-        proof_type.verify(doc, signable_values.to_string(), *self.0["proof"].clone())
+        ecdsa_sd::add_base_proof(self, &signer, purpose, &mandatory_pointers)
     }
 
-    #[wasm_bindgen(js_name = "toObject")]
-    pub fn to_object(&mut self) -> Result<Object, FiError> {
-        let value = js_sys::Object::new();
-        self.0.iter().for_each(|(key, val)| {
-            _ = js_sys::Reflect::set(&value, &JsValue::from_str(key), val);
-        });
-
-        return Ok(value);
+    /// Derives a credential disclosing the selected claims, see `ecdsa_sd::derive_proof`.
+    #[wasm_bindgen(js_name = "deriveEcdsaSdProof")]
+    pub fn js_derive_ecdsa_sd_proof(&self, selective_pointers: Vec<String>) -> Result<VC, FiError> {
+        ecdsa_sd::derive_proof(self, &selective_pointers)
     }
 
-    #[wasm_bindgen(js_name = "getSignableContent")]
-    pub fn get_signable_content(&mut self) -> Result<String, FiError> {
-        let val = match self.to_object() {
-            Err(error) => {
-                return Err(error);
-            }
-            Ok(val) => val,
-        };
-
-        match js_sys::Reflect::delete_property(&val, &JsValue::from_str("proof")) {
-            Err(error) => return Err(FiError::new(error.as_string().unwrap().as_str())),
-            Ok(_val) => {}
-        };
+    #[wasm_bindgen(js_name = "verify")]
+    pub fn js_verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        self.verify(doc)
+    }
 
-        return Ok(val.to_string().into());
+    #[wasm_bindgen(js_name = "verifyAsync")]
+    pub async fn js_verify_async(&self, loader: &DocumentLoader) -> Result<bool, FiError> {
+        self.verify_async(loader).await
     }
 
-    #[wasm_bindgen(js_name = "addField")]
-    pub fn add_field(&mut self, key: &str, val: JsValue) {
-        self.0.insert(String::from(key), Box::new(val));
+    #[wasm_bindgen(js_name = "toObject")]
+    pub fn js_to_object(&self) -> Result<JsValue, FiError> {
+        match self.to_object() {
+            Err(error) => Err(error),
+            Ok(val) => to_js_value(&val),
+        }
     }
 
-    #[wasm_bindgen]
-    pub fn from(value: JsValue) -> Result<VC, FiError> {
-        let keys = match js_sys::Reflect::own_keys(&value) {
-            Ok(val) => val,
-            Err(error) => return Err(FiError::new(error.as_string().unwrap().as_str())),
-        };
+    #[wasm_bindgen(js_name = "toJSON")]
+    pub fn js_to_json(&self) -> Result<JsValue, FiError> {
+        self.js_to_object()
+    }
 
-        let mut new_value: HashMap<String, Box<JsValue>> = HashMap::new();
-        keys.iter().for_each(|key| {
-            let val = match js_sys::Reflect::get(&value, &key) {
-                Err(error) => {
-                    eprintln!("{}", error.as_string().unwrap());
-                    return;
-                }
-                Ok(_val) => _val,
-            };
+    /// Signable content as the JSON string the proof is computed over.
+    #[wasm_bindgen(js_name = "getSignableContent")]
+    pub fn js_get_signable_content(&self) -> Result<String, FiError> {
+        match self.get_signable_content() {
+            Err(error) => Err(error),
+            Ok(val) => Ok(val.to_string()),
+        }
+    }
 
-            new_value.insert(key.as_string().unwrap(), Box::new(val));
-        });
+    #[wasm_bindgen(js_name = "addField")]
+    pub fn js_add_field(&mut self, key: &str, val: JsValue) -> Result<(), FiError> {
+        match from_js_value(val) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.add_field(key, val);
+                Ok(())
+            }
+        }
+    }
 
-        Ok(VC(new_value))
+    #[wasm_bindgen(js_name = "from")]
+    pub fn js_from(value: JsValue) -> Result<VC, FiError> {
+        match from_js_value(value) {
+            Err(error) => Err(error),
+            Ok(val) => VC::from(val),
+        }
     }
 }
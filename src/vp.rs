@@ -1,30 +1,75 @@
-#[cfg(not(feature = "wasm"))]
-use std::borrow::{Borrow, BorrowMut};
-use std::collections::HashMap;
+use std::{
+    borrow::{Borrow, BorrowMut},
+    collections::HashMap,
+};
 
-#[cfg(feature = "wasm")]
-use fi_digital_signatures::algorithms::Algorithm;
-#[cfg(feature = "wasm")]
-use js_sys::{Array, Object};
-#[cfg(not(feature = "wasm"))]
 use serde::{Deserialize, Serialize};
-#[cfg(not(feature = "wasm"))]
 use serde_json::Value;
+use wasm_bindgen::prelude::wasm_bindgen;
 
 #[cfg(feature = "wasm")]
-use crate::proof::ProofType;
-#[cfg(feature = "wasm")]
-use wasm_bindgen::prelude::wasm_bindgen;
+use crate::{
+    document::SigningDocument,
+    proof::ProofType,
+    signer::InMemorySigner,
+    wasm::{from_js_value, to_js_value},
+};
+use fi_digital_signatures::algorithms::Algorithm;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::JsValue;
 
-#[cfg(not(feature = "wasm"))]
-use crate::proof::{FiProof, Proof};
+use crate::{
+    cose::{verified_json_payload, CoseSign1, VP_CONTENT_TYPE, VP_COSE_TYPE},
+    document::{DocumentLoader, PublicVerificationDocument},
+    enveloped::EnvelopedCredential,
+    error::FiError,
+    jose::{Jwt, VP_JWT_TYPE},
+    proof::{FiProof, Proof},
+    signer::Signer,
+    vc::VC,
+};
+
+/// Credential of a presentation, secured by an embedded proof or enveloped in a data URL.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PresentedCredential {
+    Embedded(Box<VC>),
+    Enveloped(EnvelopedCredential),
+}
 
-use crate::{document::VerificationDocument, error::FiError, vc::VC};
+impl PresentedCredential {
+    /// The credential itself, read from the envelope without verifying it.
+    pub fn get_credential(&self) -> Result<VC, FiError> {
+        match self {
+            PresentedCredential::Embedded(val) => Ok(val.as_ref().clone()),
+            PresentedCredential::Enveloped(val) => val.get_credential(),
+        }
+    }
 
-#[cfg(not(feature = "wasm"))]
-#[derive(Serialize, Deserialize)]
+    pub fn get_verification_method(&self) -> Result<String, FiError> {
+        match self {
+            PresentedCredential::Embedded(val) => match val
+                .get_proof()
+                .as_ref()
+                .and_then(|proof| proof.get_verification_method().clone())
+            {
+                None => Err(FiError::new("The proof has no verification method")),
+                Some(val) => Ok(val),
+            },
+            PresentedCredential::Enveloped(val) => val.get_verification_method(),
+        }
+    }
+
+    pub fn verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        match self {
+            PresentedCredential::Embedded(val) => val.verify(doc),
+            PresentedCredential::Enveloped(val) => val.verify(doc),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[wasm_bindgen]
 pub struct VP {
     #[serde(rename = "@context")]
     contexts: Vec<Value>,
@@ -35,16 +80,11 @@ pub struct VP {
     #[serde(rename = "proof", skip_serializing_if = "Option::is_none")]
     proof: Option<FiProof>,
     #[serde(rename = "verifiableCredential")]
-    verifiable_credential: Vec<VC>,
-    #[serde(skip_serializing, skip_deserializing)]
+    verifiable_credential: Vec<PresentedCredential>,
+    #[serde(flatten)]
     optional_fields: HashMap<String, Box<Value>>,
 }
 
-#[wasm_bindgen]
-#[cfg(feature = "wasm")]
-pub struct VP(HashMap<String, Box<JsValue>>);
-
-#[cfg(not(feature = "wasm"))]
 impl VP {
     pub fn new(id: String, holder: Option<String>) -> Self {
         let mut vp = VP {
@@ -66,6 +106,10 @@ impl VP {
         self.holder = holder;
     }
 
+    pub fn get_holder(&self) -> &Option<String> {
+        &self.holder
+    }
+
     pub fn set_context(&mut self, contexts: Vec<Value>) {
         self.contexts = contexts;
     }
@@ -75,17 +119,40 @@ impl VP {
     }
 
     pub fn add_verifiable_credentials(&mut self, verifiable_credential: VC) {
-        self.verifiable_credential.push(verifiable_credential);
+        self.verifiable_credential
+            .push(PresentedCredential::Embedded(Box::new(
+                verifiable_credential,
+            )));
+    }
+
+    pub fn add_enveloped_credential(&mut self, enveloped_credential: EnvelopedCredential) {
+        self.verifiable_credential
+            .push(PresentedCredential::Enveloped(enveloped_credential));
+    }
+
+    pub fn add_presented_credential(&mut self, presented_credential: PresentedCredential) {
+        self.verifiable_credential.push(presented_credential);
     }
 
     pub fn set_verifiable_credentials(&mut self, verifiable_credentials: Vec<VC>) {
-        self.verifiable_credential = verifiable_credentials;
+        self.verifiable_credential = verifiable_credentials
+            .into_iter()
+            .map(|val| PresentedCredential::Embedded(Box::new(val)))
+            .collect();
+    }
+
+    pub fn add_type(&mut self, _type: String) {
+        self.types.push(_type);
     }
 
     pub fn set_types(&mut self, types: Vec<String>) {
         self.types = types;
     }
 
+    pub fn get_verifiable_credentials(&self) -> &Vec<PresentedCredential> {
+        self.verifiable_credential.borrow()
+    }
+
     pub fn get_proof(&self) -> &Option<FiProof> {
         self.proof.borrow()
     }
@@ -94,11 +161,7 @@ impl VP {
         self.proof.borrow_mut()
     }
 
-    pub fn sign(
-        &mut self,
-        doc: &mut VerificationDocument,
-        mut proof: FiProof,
-    ) -> Result<(), FiError> {
+    pub fn sign(&mut self, signer: &dyn Signer, mut proof: FiProof) -> Result<(), FiError> {
         let signable_values = match self.get_signable_content() {
             Err(error) => {
                 return Err(error);
@@ -106,7 +169,7 @@ impl VP {
             Ok(val) => val,
         };
 
-        match proof.sign(doc, signable_values.to_string()) {
+        match proof.sign(signer, signable_values.to_string()) {
             Err(error) => {
                 return Err(error);
             }
@@ -117,7 +180,34 @@ impl VP {
         return Ok(());
     }
 
-    pub fn verify(&mut self, doc: &mut VerificationDocument) -> Result<bool, FiError> {
+    pub fn verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        self.verify_with_algorithms(doc, doc.get_algorithms().as_slice())
+    }
+
+    /// Like [`VP::verify`], but also fails unless the proof was signed over `nonce`, e.g. the
+    /// verifier's challenge.
+    pub fn verify_with_nonce(
+        &self,
+        doc: &PublicVerificationDocument,
+        nonce: &str,
+    ) -> Result<bool, FiError> {
+        match self.proof.as_ref().map(|val| val.check_nonce(nonce)) {
+            None => return Err(FiError::new("No proof was found")),
+            Some(Err(error)) => {
+                return Err(error);
+            }
+            Some(Ok(val)) => val,
+        };
+
+        self.verify(doc)
+    }
+
+    /// Like [`VP::verify`], but only accepts a proof signed with one of `allowed`.
+    pub fn verify_with_algorithms(
+        &self,
+        doc: &PublicVerificationDocument,
+        allowed: &[Algorithm],
+    ) -> Result<bool, FiError> {
         let signable_values = match self.get_signable_content() {
             Err(error) => {
                 return Err(error);
@@ -125,33 +215,150 @@ impl VP {
             Ok(val) => val,
         };
 
-        let proof = match self.proof.as_mut() {
-            None => return Err(FiError::new("Cannot get proof as a mutable reference")),
+        let proof = match self.proof.as_ref() {
+            None => return Err(FiError::new("No proof was found")),
             Some(val) => val,
         };
 
-        proof.verify(doc, signable_values.to_string())
+        match proof.verify_with_algorithms(doc, signable_values.to_string(), allowed) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(false) => return Ok(false),
+            Ok(true) => {}
+        };
+
+        match check_verification_method(proof.get_verification_method(), doc) {
+            Err(error) => {
+                return Err(error);
+            }
+            Ok(val) => val,
+        };
+
+        match self.check_holder_binding(doc) {
+            Err(error) => Err(error),
+            Ok(()) => Ok(true),
+        }
     }
 
-    pub fn to_object(&mut self) -> Result<Value, FiError> {
-        let mut value = match serde_json::to_value(&self) {
+    /// Fails when the presentation names a holder other than the controller of the signing
+    /// key, or a credential bound to a holder key was not presented with that key.
+    pub fn check_holder_binding(&self, doc: &PublicVerificationDocument) -> Result<(), FiError> {
+        match check_holder(self.holder.as_deref(), doc) {
             Err(error) => {
-                eprintln!("{}", error);
-                return Err(FiError::new("Cannot create value object from VP"));
+                return Err(error);
             }
             Ok(val) => val,
         };
 
-        let obj = value.as_object_mut().unwrap();
+        for credential in self.verifiable_credential.iter() {
+            match credential.get_credential() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => match val.check_holder_binding(doc) {
+                    Err(error) => {
+                        return Err(error);
+                    }
+                    Ok(val) => val,
+                },
+            };
+        }
 
-        self.optional_fields.iter_mut().for_each(move |(key, val)| {
-            obj.insert(String::from(key), val.take());
-        });
+        return Ok(());
+    }
+
+    /// Resolves the proof's verification method through the loader and verifies against it.
+    pub async fn verify_async(&self, loader: &DocumentLoader) -> Result<bool, FiError> {
+        let verification_method = match self.proof.as_ref() {
+            None => return Err(FiError::new("No proof was found")),
+            Some(val) => match val.get_verification_method() {
+                None => return Err(FiError::new("The proof has no verification method")),
+                Some(val) => val.clone(),
+            },
+        };
+
+        let doc = match loader
+            .get_verification_document_async(verification_method.as_str())
+            .await
+        {
+            None => return Err(FiError::new("Verification document could not be resolved")),
+            Some(val) => val,
+        };
+
+        self.verify(&doc)
+    }
+
+    /// Verifies every credential of the presentation with its issuer's key, resolved through
+    /// the loader. The presentation's own proof is verified by [`VP::verify`].
+    pub fn verify_credentials(&self, loader: &DocumentLoader) -> Result<bool, FiError> {
+        for credential in self.verifiable_credential.iter() {
+            let verification_method = match credential.get_verification_method() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+
+            let doc = match loader.get_verification_document(verification_method.as_str()) {
+                None => return Err(FiError::new("Verification document could not be resolved")),
+                Some(val) => val,
+            };
+
+            match credential.verify(&doc) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(false) => return Ok(false),
+                Ok(true) => {}
+            };
+        }
+
+        return Ok(true);
+    }
+
+    pub async fn verify_credentials_async(&self, loader: &DocumentLoader) -> Result<bool, FiError> {
+        for credential in self.verifiable_credential.iter() {
+            let verification_method = match credential.get_verification_method() {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(val) => val,
+            };
+
+            let doc = match loader
+                .get_verification_document_async(verification_method.as_str())
+                .await
+            {
+                None => return Err(FiError::new("Verification document could not be resolved")),
+                Some(val) => val,
+            };
+
+            match credential.verify(&doc) {
+                Err(error) => {
+                    return Err(error);
+                }
+                Ok(false) => return Ok(false),
+                Ok(true) => {}
+            };
+        }
+
+        return Ok(true);
+    }
+
+    pub fn to_object(&self) -> Result<Value, FiError> {
+        let value = match serde_json::to_value(self) {
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(FiError::new("Cannot create value object from VP"));
+            }
+            Ok(val) => val,
+        };
 
         return Ok(value);
     }
 
-    pub fn get_signable_content(&mut self) -> Result<Value, FiError> {
+    pub fn get_signable_content(&self) -> Result<Value, FiError> {
         let mut val = match self.to_object() {
             Err(error) => {
                 return Err(error);
@@ -164,14 +371,45 @@ impl VP {
         return Ok(val);
     }
 
-    pub fn from(value: Value) -> Result<Self, FiError> {
-        let mut map: HashMap<String, Box<Value>> = HashMap::new();
+    /// Secures the presentation as a `vp+jwt`, whose payload is the presentation without
+    /// its proof.
+    pub fn to_jwt(&self, signer: &dyn Signer) -> Result<String, FiError> {
+        match self.get_signable_content() {
+            Err(error) => Err(error),
+            Ok(val) => Jwt::sign(&val, VP_JWT_TYPE, signer),
+        }
+    }
+
+    /// Secures the presentation as a `vp+cose` COSE_Sign1 message, whose payload is the
+    /// presentation JSON without its proof.
+    pub fn to_cose(&self, signer: &dyn Signer) -> Result<Vec<u8>, FiError> {
+        match self.get_signable_content() {
+            Err(error) => Err(error),
+            Ok(val) => CoseSign1::sign(
+                val.to_string().as_bytes(),
+                VP_COSE_TYPE,
+                VP_CONTENT_TYPE,
+                signer,
+            ),
+        }
+    }
+
+    /// Reads a presentation secured with COSE, failing unless the signature verifies with the
+    /// document's key.
+    pub fn from_cose(bytes: &[u8], doc: &PublicVerificationDocument) -> Result<Self, FiError> {
+        match verified_json_payload(bytes, VP_CONTENT_TYPE, doc) {
+            Err(error) => Err(error),
+            Ok(val) => VP::from(val),
+        }
+    }
 
-        match serde_ignored::deserialize(&value, |path| {
-            let _path = path.to_string();
-            let value_to_save = value[&_path].clone();
-            map.insert(_path, Box::new(value_to_save));
-        }) {
+    pub fn add_field(&mut self, key: &str, val: Value) {
+        self.optional_fields
+            .insert(String::from(key), Box::new(val));
+    }
+
+    pub fn from(value: Value) -> Result<Self, FiError> {
+        match serde_json::from_value(value) {
             Ok(val) => return Ok(val),
             Err(error) => return Err(FiError::new(error.to_string().as_str())),
         };
@@ -182,198 +420,160 @@ impl VP {
 #[wasm_bindgen]
 impl VP {
     #[wasm_bindgen(constructor)]
-    pub fn new(id: String, holder: JsValue) -> Self {
-        let mut vp: HashMap<String, Box<JsValue>> = HashMap::new();
-
-        let mut types: Vec<JsValue> = Vec::new();
-        types.push(JsValue::from_str("VerifiablePresentation"));
-
-        vp.insert(String::from("type"), Box::new(JsValue::from(types)));
-        vp.insert(String::from("@context"), Box::new(Array::new().into()));
-        vp.insert(
-            String::from("verifiableCredential"),
-            Box::new(Array::new().into()),
-        );
-        vp.insert(String::from("holder"), Box::new(holder));
-        vp.insert(String::from("id"), Box::new(JsValue::from_str(id.as_str())));
-        vp.insert(String::from("proof"), Box::new(JsValue::null()));
-
-        VP(vp)
+    pub fn js_new(id: String, holder: Option<String>) -> VP {
+        VP::new(id, holder)
     }
 
     #[wasm_bindgen(js_name = "setHolder")]
-    pub fn set_holder(&mut self, holder: JsValue) {
-        self.0.insert(String::from("holder"), Box::new(holder));
+    pub fn js_set_holder(&mut self, holder: Option<String>) {
+        self.set_holder(holder);
     }
 
     #[wasm_bindgen(js_name = "setContext")]
-    pub fn set_context(&mut self, contexts: JsValue) {
-        self.0.insert(String::from("@context"), Box::new(contexts));
+    pub fn js_set_context(&mut self, contexts: JsValue) -> Result<(), FiError> {
+        match from_js_value(contexts) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.set_context(val);
+                Ok(())
+            }
+        }
     }
 
     #[wasm_bindgen(js_name = "addContext")]
-    pub fn add_context(&mut self, context: JsValue) -> Result<(), FiError> {
-        if self.0["@context"].is_array() {
-            let arr: Array = js_sys::Array::from(&*self.0["@context"]);
-
-            arr.push(&context.clone());
-
-            self.0
-                .insert(String::from("@context"), Box::new(JsValue::from(arr)));
-        } else {
-            let arr = js_sys::Array::new();
-            arr.push(&self.0["@context"].to_owned());
-            arr.push(&context.clone());
-
-            self.0
-                .insert(String::from("@context"), Box::new(JsValue::from(arr)));
+    pub fn js_add_context(&mut self, context: JsValue) -> Result<(), FiError> {
+        match from_js_value(context) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.add_context(val);
+                Ok(())
+            }
         }
-
-        return Ok(());
     }
 
     #[wasm_bindgen(js_name = "addType")]
-    pub fn add_type(&mut self, _type: JsValue) -> Result<(), FiError> {
-        if self.0["type"].is_array() {
-            let arr: Array = js_sys::Array::from(&*self.0["type"]);
-
-            arr.push(&_type.clone());
-
-            self.0
-                .insert(String::from("type"), Box::new(JsValue::from(arr)));
-        } else {
-            let arr = js_sys::Array::new();
-            arr.push(&self.0["type"].to_owned());
-            arr.push(&_type.clone());
-
-            self.0
-                .insert(String::from("type"), Box::new(JsValue::from(arr)));
-        }
-
-        return Ok(());
+    pub fn js_add_type(&mut self, _type: String) {
+        self.add_type(_type);
     }
 
     #[wasm_bindgen(js_name = "setType")]
-    pub fn set_type(&mut self, _type: JsValue) {
-        self.0.insert(String::from("type"), Box::new(_type));
+    pub fn js_set_type(&mut self, types: Vec<String>) {
+        self.set_types(types);
     }
 
     #[wasm_bindgen(js_name = "getProof")]
-    pub fn get_proof(&self) -> JsValue {
-        *self.0["proof"].clone()
+    pub fn js_get_proof(&self) -> Result<JsValue, FiError> {
+        to_js_value(&self.proof)
     }
 
     #[wasm_bindgen(js_name = "addVerifiableCredential")]
-    pub fn add_verifiable_credentials(&mut self, verifiable_credential: VC) {
-        let arr: Array = js_sys::Array::from(&*self.0["verifiableCredential"]);
-
-        arr.push(&JsValue::from(verifiable_credential));
-
-        self.0.insert(
-            String::from("verifiableCredential"),
-            Box::new(JsValue::from(arr)),
-        );
+    pub fn js_add_verifiable_credential(&mut self, verifiable_credential: &VC) {
+        self.add_verifiable_credentials(verifiable_credential.clone());
     }
 
     #[wasm_bindgen(js_name = "setVerifiableCredential")]
-    pub fn set_verifiable_credentials(&mut self, verifiable_credentials: Vec<VC>) {
-        self.0.insert(
-            String::from("verifiableCredential"),
-            Box::new(JsValue::from(verifiable_credentials)),
-        );
+    pub fn js_set_verifiable_credentials(&mut self, verifiable_credentials: Vec<VC>) {
+        self.set_verifiable_credentials(verifiable_credentials);
     }
 
-    #[wasm_bindgen]
-    pub fn sign(
+    #[wasm_bindgen(js_name = "sign")]
+    pub fn js_sign(
         &mut self,
         alg: Algorithm,
         purpose: String,
-        doc: &mut VerificationDocument,
+        doc: &SigningDocument,
         proof_type: ProofType,
     ) -> Result<(), FiError> {
-        let signable_values = match self.get_signable_content() {
+        let signer = match InMemorySigner::from_signing_document(doc, alg) {
             Err(error) => {
                 return Err(error);
             }
             Ok(val) => val,
         };
 
-        let proof = match proof_type.sign(alg, purpose, doc, signable_values.to_string()) {
-            Err(error) => {
-                return Err(error);
-            }
-            Ok(val) => val,
-        };
-
-        self.0.insert(String::from("proof"), Box::new(proof));
-        return Ok(());
+        self.sign(&signer, proof_type.new_proof(alg, purpose))
     }
 
-    #[wasm_bindgen]
-    pub fn verify(
-        &mut self,
-        doc: &mut VerificationDocument,
-        proof_type: ProofType,
-    ) -> Result<bool, FiError> {
-        let signable_values = match self.get_signable_content() {
-            Err(error) => {
-                return Err(error);
-            }
-            Ok(val) => val,
-        };
+    #[wasm_bindgen(js_name = "verify")]
+    pub fn js_verify(&self, doc: &PublicVerificationDocument) -> Result<bool, FiError> {
+        self.verify(doc)
+    }
 
-        proof_type.verify(doc, signable_values.to_string(), *self.0["proof"].clone())
+    #[wasm_bindgen(js_name = "verifyAsync")]
+    pub async fn js_verify_async(&self, loader: &DocumentLoader) -> Result<bool, FiError> {
+        self.verify_async(loader).await
     }
 
     #[wasm_bindgen(js_name = "toObject")]
-    pub fn to_object(&mut self) -> Result<Object, FiError> {
-        let value = js_sys::Object::new();
-        self.0.iter().for_each(|(key, val)| {
-            _ = js_sys::Reflect::set(&value, &JsValue::from_str(key), val);
-        });
+    pub fn js_to_object(&self) -> Result<JsValue, FiError> {
+        match self.to_object() {
+            Err(error) => Err(error),
+            Ok(val) => to_js_value(&val),
+        }
+    }
 
-        return Ok(value);
+    #[wasm_bindgen(js_name = "toJSON")]
+    pub fn js_to_json(&self) -> Result<JsValue, FiError> {
+        self.js_to_object()
     }
 
+    /// Signable content as the JSON string the proof is computed over.
     #[wasm_bindgen(js_name = "getSignableContent")]
-    pub fn get_signable_content(&mut self) -> Result<String, FiError> {
-        let val = match self.to_object() {
-            Err(error) => {
-                return Err(error);
-            }
-            Ok(val) => val,
-        };
-
-        _ = js_sys::Reflect::delete_property(&val, &JsValue::from_str("proof"));
-
-        return Ok(val.to_string().into());
+    pub fn js_get_signable_content(&self) -> Result<String, FiError> {
+        match self.get_signable_content() {
+            Err(error) => Err(error),
+            Ok(val) => Ok(val.to_string()),
+        }
     }
 
     #[wasm_bindgen(js_name = "addField")]
-    pub fn add_field(&mut self, key: &str, val: JsValue) {
-        self.0.insert(String::from(key), Box::new(val));
+    pub fn js_add_field(&mut self, key: &str, val: JsValue) -> Result<(), FiError> {
+        match from_js_value(val) {
+            Err(error) => Err(error),
+            Ok(val) => {
+                self.add_field(key, val);
+                Ok(())
+            }
+        }
     }
 
-    #[wasm_bindgen]
-    pub fn from(value: JsValue) -> Result<VP, FiError> {
-        let keys = match js_sys::Reflect::own_keys(&value) {
-            Ok(val) => val,
-            Err(error) => return Err(FiError::new(error.as_string().unwrap().as_str())),
-        };
+    #[wasm_bindgen(js_name = "from")]
+    pub fn js_from(value: JsValue) -> Result<VP, FiError> {
+        match from_js_value(value) {
+            Err(error) => Err(error),
+            Ok(val) => VP::from(val),
+        }
+    }
+}
 
-        let mut new_value: HashMap<String, Box<JsValue>> = HashMap::new();
-        keys.iter().for_each(|key| {
-            let val = match js_sys::Reflect::get(&value, &key) {
-                Err(error) => {
-                    eprintln!("{}", error.as_string().unwrap());
-                    return;
-                }
-                Ok(_val) => _val,
-            };
+/// Fails unless the proof names `doc` as its verification method, so the holder is checked
+/// against the key that actually signed.
+pub(crate) fn check_verification_method(
+    verification_method: &Option<String>,
+    doc: &PublicVerificationDocument,
+) -> Result<(), FiError> {
+    if verification_method.as_deref() != Some(doc.get_id().as_str()) {
+        return Err(FiError::new(
+            "The proof verification method is not the verification document",
+        ));
+    }
 
-            new_value.insert(key.as_string().unwrap(), Box::new(val));
-        });
+    return Ok(());
+}
 
-        Ok(VP(new_value))
+/// Fails when `holder` is set and is neither the signing key nor the DID controlling it.
+pub(crate) fn check_holder(
+    holder: Option<&str>,
+    doc: &PublicVerificationDocument,
+) -> Result<(), FiError> {
+    let holder = match holder {
+        None => return Ok(()),
+        Some(val) => val,
+    };
+
+    if doc.get_id() != holder && doc.get_id().split('#').next() != Some(holder) {
+        return Err(FiError::new("The presentation is not signed by its holder"));
     }
+
+    return Ok(());
 }
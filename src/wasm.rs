@@ -0,0 +1,30 @@
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::JsValue;
+
+use crate::error::FiError;
+
+pub(crate) fn from_js_value<T: DeserializeOwned>(value: JsValue) -> Result<T, FiError> {
+    match serde_wasm_bindgen::from_value(value) {
+        Ok(val) => Ok(val),
+        Err(error) => Err(FiError::new(error.to_string().as_str())),
+    }
+}
+
+/// Converts to plain JS objects and arrays rather than `Map`s, so values round trip as JSON.
+pub(crate) fn to_js_value<T: Serialize>(value: &T) -> Result<JsValue, FiError> {
+    match value.serialize(&serde_wasm_bindgen::Serializer::json_compatible()) {
+        Ok(val) => Ok(val),
+        Err(error) => Err(FiError::new(error.to_string().as_str())),
+    }
+}
+
+pub(crate) fn optional_js_value<T: DeserializeOwned>(value: JsValue) -> Result<Option<T>, FiError> {
+    if value.is_null() || value.is_undefined() {
+        return Ok(None);
+    }
+
+    match from_js_value(value) {
+        Err(error) => Err(error),
+        Ok(val) => Ok(Some(val)),
+    }
+}
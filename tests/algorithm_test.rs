@@ -0,0 +1,102 @@
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{PublicVerificationDocument, VerificationDocument},
+    keys::KeyType,
+    proof::FiProof,
+    signer::InMemorySigner,
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+fn signed_credential(alg: Algorithm) -> (VC, PublicVerificationDocument) {
+    let doc = VerificationDocument::generate(alg).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, alg).expect("Signer creation failed");
+
+    let mut vc = VC::new(
+        String::from("urn:uuid:algorithm"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({ "id": "did:example:holder" }));
+    vc.sign(&signer, FiProof::new(alg, String::from("assertionMethod")))
+        .expect("Signing failed");
+
+    (vc, doc.public_document().unwrap())
+}
+
+fn with_algorithm(vc: &VC, alg: &str) -> VC {
+    let mut value = vc.to_object().expect("Serialization failed");
+    value["proof"]["algorithm"] = Value::from(alg);
+    serde_json::from_value(value).expect("Deserialization failed")
+}
+
+#[test]
+pub fn test_proof_algorithm_must_match_key() {
+    let (vc, doc) = signed_credential(Algorithm::ES256);
+    assert!(vc.verify(&doc).expect("Verification failed"));
+
+    for alg in ["ES256K", "ES384", "EdDSA", "HS256"] {
+        let error = with_algorithm(&vc, alg)
+            .verify(&doc)
+            .expect_err("Proof with a foreign algorithm was accepted");
+        assert!(error
+            .to_string()
+            .contains("does not match the verification key"));
+    }
+}
+
+#[test]
+pub fn test_allow_list() {
+    let (vc, doc) = signed_credential(Algorithm::EdDSA);
+
+    assert!(vc
+        .verify_with_algorithms(&doc, &[Algorithm::EdDSA, Algorithm::ES256])
+        .expect("Verification failed"));
+    let error = vc
+        .verify_with_algorithms(&doc, &[Algorithm::ES256])
+        .expect_err("Algorithm outside of the allow-list was accepted");
+    assert!(error.to_string().contains("EdDSA is not allowed"));
+
+    let signer_doc = VerificationDocument::generate(Algorithm::EdDSA).unwrap();
+    let signer = InMemorySigner::new(&signer_doc, Algorithm::EdDSA).unwrap();
+    let mut vp = VP::new(String::from("urn:uuid:presentation"), None);
+    vp.add_verifiable_credentials(vc);
+    vp.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("authentication")),
+    )
+    .expect("Signing failed");
+    let holder_doc = signer_doc.public_document().unwrap();
+    assert!(vp
+        .verify_with_algorithms(&holder_doc, &[Algorithm::EdDSA])
+        .expect("Verification failed"));
+    assert!(vp
+        .verify_with_algorithms(&holder_doc, &[Algorithm::ES384])
+        .is_err());
+}
+
+#[test]
+pub fn test_untyped_keys_verify_nothing() {
+    let (vc, doc) = signed_credential(Algorithm::EdDSA);
+    assert!(doc.get_algorithms() == vec![Algorithm::EdDSA]);
+
+    // The same key without a key type could be guessed from its length, but is not
+    let untyped =
+        PublicVerificationDocument::new(doc.get_id().clone(), doc.get_public_key().clone());
+    assert!(untyped.get_algorithms().is_empty());
+    assert!(vc.verify(&untyped).is_err());
+    assert!(vc
+        .verify_with_algorithms(&untyped, &[Algorithm::EdDSA])
+        .is_err());
+
+    let typed = PublicVerificationDocument::with_key_type(
+        doc.get_id().clone(),
+        doc.get_public_key().clone(),
+        KeyType::Ed25519,
+    )
+    .expect("Invalid public key");
+    assert!(vc.verify(&typed).expect("Verification failed"));
+}
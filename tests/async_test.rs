@@ -0,0 +1,142 @@
+#![cfg(not(feature = "wasm"))]
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{
+        AsyncDocResolver, DocumentLoader, PublicVerificationDocument, VerificationDocument,
+    },
+    proof::FiProof,
+    signer::InMemorySigner,
+    vc::VC,
+    vp::VP,
+};
+use futures::executor::block_on;
+use serde_json::Value;
+
+const PRIVATE_KEY_HEX: &str = "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+/// Stand-in for a DID resolver that fetches documents over the network.
+struct RemoteResolver {
+    docs: HashMap<String, PublicVerificationDocument>,
+}
+
+#[async_trait]
+impl AsyncDocResolver for RemoteResolver {
+    async fn resolve(&self, url: &str) -> Option<PublicVerificationDocument> {
+        self.docs.get(url).cloned()
+    }
+}
+
+fn assert_send<T: Send>(_value: &T) {}
+
+fn eddsa_doc() -> VerificationDocument {
+    VerificationDocument::from_hex(
+        String::from("did:example:issuer#key-1"),
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed")
+}
+
+fn loader(eddsa_doc: &VerificationDocument) -> DocumentLoader {
+    let public_doc = eddsa_doc
+        .public_document()
+        .expect("Public document missing");
+
+    let mut docs = HashMap::new();
+    docs.insert(public_doc.get_id().clone(), public_doc);
+
+    let mut loader = DocumentLoader::new(None).expect("Loader creation failed");
+    loader.add_async_resolver(Box::new(RemoteResolver { docs }));
+    return loader;
+}
+
+#[test]
+pub fn test_vc_verify_async() {
+    let eddsa_doc = eddsa_doc();
+    let signer = InMemorySigner::new(&eddsa_doc, Algorithm::EdDSA).expect("Signer creation failed");
+    let loader = loader(&eddsa_doc);
+
+    let mut vc = VC::new(
+        String::from("id:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    if let Err(error) = vc.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("ESig")),
+    ) {
+        panic!("{}", error);
+    }
+
+    let future = vc.verify_async(&loader);
+    assert_send(&future);
+
+    let result = match block_on(future) {
+        Ok(val) => val,
+        Err(error) => panic!("{}", error),
+    };
+    assert!(result);
+    assert!(loader
+        .get_verification_document("did:example:issuer#key-1")
+        .is_some());
+}
+
+#[test]
+pub fn test_vp_verify_async() {
+    let eddsa_doc = eddsa_doc();
+    let signer = InMemorySigner::new(&eddsa_doc, Algorithm::EdDSA).expect("Signer creation failed");
+    let loader = loader(&eddsa_doc);
+
+    let mut vp = VP::new(
+        String::from("id:1"),
+        Some(String::from("did:example:issuer")),
+    );
+    if let Err(error) = vp.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("ESig")),
+    ) {
+        panic!("{}", error);
+    }
+
+    let result = match block_on(vp.verify_async(&loader)) {
+        Ok(val) => val,
+        Err(error) => panic!("{}", error),
+    };
+    assert!(result);
+}
+
+#[test]
+pub fn test_verify_async_fails_for_unknown_verification_method() {
+    let eddsa_doc = eddsa_doc();
+    let other_doc = VerificationDocument::new(
+        String::from("did:example:other#key-1"),
+        Some(hex::decode(PRIVATE_KEY_HEX).expect("Private key hex decode failed")),
+        None,
+    );
+    let signer = InMemorySigner::new(&other_doc, Algorithm::EdDSA).expect("Signer creation failed");
+    let loader = loader(&eddsa_doc);
+
+    let mut vc = VC::new(
+        String::from("id:1"),
+        Value::from("did:example:other"),
+        None,
+        None,
+        None,
+    );
+    if let Err(error) = vc.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("ESig")),
+    ) {
+        panic!("{}", error);
+    }
+
+    assert!(block_on(vc.verify_async(&loader)).is_err());
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    batch::{sign_credentials, verify_credentials, verify_credentials_with_algorithms},
+    document::VerificationDocument,
+    error::FiError,
+    proof::FiProof,
+    signer::{InMemorySigner, Signer},
+    vc::VC,
+};
+use serde_json::{json, Value};
+
+fn credentials(count: usize) -> Vec<VC> {
+    (0..count)
+        .map(|index| {
+            let mut vc = VC::new(
+                format!("urn:uuid:student-{}", index),
+                Value::from("did:example:registrar"),
+                None,
+                None,
+                None,
+            );
+            vc.set_credential_subject(json!({ "id": format!("did:example:{}", index) }));
+            vc
+        })
+        .collect()
+}
+
+#[test]
+pub fn test_sign_and_verify_batch() {
+    let doc = VerificationDocument::generate(Algorithm::ES256).expect("Key generation failed");
+    let proof = FiProof::new(Algorithm::ES256, String::from("assertionMethod"));
+
+    let signer = InMemorySigner::new(&doc, Algorithm::ES256).expect("Signer creation failed");
+
+    let signed: Vec<VC> = sign_credentials(&signer, credentials(20), &proof)
+        .into_iter()
+        .map(|result| result.expect("Signing failed"))
+        .collect();
+    assert_eq!(signed.len(), 20);
+    assert_eq!(signed[7].to_object().unwrap()["id"], "urn:uuid:student-7");
+
+    let public_doc = doc.public_document().unwrap();
+    let results = verify_credentials(&public_doc, signed.as_slice());
+    assert!(results.iter().all(|result| matches!(result, Ok(true))));
+
+    let results =
+        verify_credentials_with_algorithms(&public_doc, signed.as_slice(), &[Algorithm::EdDSA]);
+    assert!(results.iter().all(|result| result.is_err()));
+}
+
+#[test]
+pub fn test_failures_are_reported_per_credential() {
+    let doc = VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let proof = FiProof::new(Algorithm::EdDSA, String::from("assertionMethod"));
+    let signer = InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed");
+    let mut batch: Vec<VC> = sign_credentials(&signer, credentials(4), &proof)
+        .into_iter()
+        .map(|result| result.expect("Signing failed"))
+        .collect();
+
+    let mut tampered = batch[1].to_object().unwrap();
+    tampered["credentialSubject"]["id"] = Value::from("did:example:mallory");
+    batch[1] = VC::from(tampered).expect("VC parsing failed");
+    batch[2] = credentials(1).remove(0);
+
+    let results = verify_credentials(&doc.public_document().unwrap(), batch.as_slice());
+    assert_eq!(results.len(), 4);
+    assert!(matches!(results[0], Ok(true)));
+    assert!(!matches!(results[1], Ok(true)));
+    assert!(results[2].is_err());
+    assert!(matches!(results[3], Ok(true)));
+}
+
+/// Stands in for a key held in an HSM or KMS, which the library never sees.
+struct RemoteSigner {
+    key: InMemorySigner,
+    requests: AtomicUsize,
+}
+
+impl Signer for RemoteSigner {
+    fn sign(&self, content: &[u8]) -> Result<Vec<u8>, FiError> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.key.sign(content)
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        self.key.algorithm()
+    }
+
+    fn verification_method(&self) -> &str {
+        self.key.verification_method()
+    }
+}
+
+#[test]
+pub fn test_batch_signs_through_any_signer() {
+    let doc = VerificationDocument::generate(Algorithm::ES256).expect("Key generation failed");
+    let signer = RemoteSigner {
+        key: InMemorySigner::new(&doc, Algorithm::ES256).expect("Signer creation failed"),
+        requests: AtomicUsize::new(0),
+    };
+    let proof = FiProof::new(Algorithm::ES256, String::from("assertionMethod"));
+
+    let signed: Vec<VC> = sign_credentials(&signer, credentials(8), &proof)
+        .into_iter()
+        .map(|result| result.expect("Signing failed"))
+        .collect();
+    assert_eq!(signer.requests.load(Ordering::SeqCst), 8);
+
+    let results = verify_credentials(&doc.public_document().unwrap(), signed.as_slice());
+    assert!(results.iter().all(|result| matches!(result, Ok(true))));
+}
+
+#[test]
+pub fn test_signing_with_another_algorithm_fails_every_credential() {
+    let doc = VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed");
+    let proof = FiProof::new(Algorithm::ES256, String::from("assertionMethod"));
+
+    let results = sign_credentials(&signer, credentials(3), &proof);
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| result.is_err()));
+}
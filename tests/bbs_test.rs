@@ -0,0 +1,133 @@
+use fi_verifiable_data::{
+    bbs,
+    document::{PublicVerificationDocument, VerificationDocument},
+    keys::KeyType,
+    proof::CredentialProof,
+    vc::VC,
+};
+use serde_json::{json, Value};
+
+fn issue() -> (VC, PublicVerificationDocument) {
+    let mut vc = VC::new(
+        String::from("urn:uuid:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({
+        "id": "did:example:holder",
+        "givenName": "Alice",
+        "birthDate": "1990-01-01",
+        "address": {
+            "country": "LK",
+            "city": "Colombo"
+        }
+    }));
+
+    let doc =
+        VerificationDocument::generate_key(KeyType::Bls12381G2).expect("Key generation failed");
+    bbs::add_base_proof(
+        &mut vc,
+        &doc,
+        String::from("assertionMethod"),
+        &[String::from("/credentialSubject/address/country")],
+    )
+    .expect("Signing failed");
+
+    (vc, doc.public_document().expect("Public document missing"))
+}
+
+fn pointers(pointers: &[&str]) -> Vec<String> {
+    pointers.iter().map(|val| val.to_string()).collect()
+}
+
+#[test]
+pub fn bbs_base_proof_verifies() {
+    let (vc, public_doc) = issue();
+
+    match vc.get_proof() {
+        Some(CredentialProof::DataIntegrity(proof)) => {
+            assert_eq!(proof.get_cryptosuite(), bbs::FI_BBS_2023)
+        }
+        _ => panic!("Expected a Data Integrity proof"),
+    };
+    assert!(vc.verify(&public_doc).expect("Verification failed"));
+
+    let other_doc = VerificationDocument::generate_key(KeyType::Bls12381G2)
+        .expect("Key generation failed")
+        .public_document()
+        .expect("Public document missing");
+    assert!(!vc.verify(&other_doc).expect("Verification failed"));
+
+    // The proofs are not standard `bbs-2023` proofs and are never verified as such
+    let mut value = vc.to_object().expect("Object creation failed");
+    value["proof"]["cryptosuite"] = Value::from("bbs-2023");
+    let relabelled: VC = serde_json::from_value(value).expect("Deserialization failed");
+    assert!(relabelled.verify(&public_doc).is_err());
+}
+
+#[test]
+pub fn bbs_derived_proof_discloses_selected_claims() {
+    let (vc, public_doc) = issue();
+
+    let derived = bbs::derive_proof(
+        &vc,
+        &pointers(&["/credentialSubject/birthDate"]),
+        Some(b"nonce"),
+    )
+    .expect("Derivation failed");
+
+    let subject = &derived.to_object().expect("Object creation failed")["credentialSubject"];
+    assert_eq!(subject["birthDate"], "1990-01-01");
+    assert_eq!(subject["address"]["country"], "LK");
+    assert!(subject.get("givenName").is_none());
+    assert!(subject["address"].get("city").is_none());
+
+    assert!(derived.verify(&public_doc).expect("Verification failed"));
+
+    // Survives a JSON round trip to the verifier
+    let received =
+        VC::from(derived.to_object().expect("Object creation failed")).expect("Parsing failed");
+    assert!(received.verify(&public_doc).expect("Verification failed"));
+}
+
+#[test]
+pub fn bbs_tampered_derived_proof_fails() {
+    let (vc, public_doc) = issue();
+
+    let derived = bbs::derive_proof(&vc, &pointers(&["/credentialSubject/birthDate"]), None)
+        .expect("Derivation failed");
+
+    let mut object = derived.to_object().expect("Object creation failed");
+    object["credentialSubject"]["birthDate"] = Value::from("2010-01-01");
+    let tampered = VC::from(object).expect("Parsing failed");
+    assert!(!tampered.verify(&public_doc).expect("Verification failed"));
+
+    let mut object = derived.to_object().expect("Object creation failed");
+    object["credentialSubject"]["givenName"] = Value::from("Mallory");
+    let tampered = VC::from(object).expect("Parsing failed");
+    assert!(!tampered.verify(&public_doc).expect("Verification failed"));
+}
+
+#[test]
+pub fn bbs_derived_proofs_are_unlinkable() {
+    let (vc, public_doc) = issue();
+    let selected = pointers(&["/credentialSubject/birthDate"]);
+
+    let first = bbs::derive_proof(&vc, &selected, None).expect("Derivation failed");
+    let second = bbs::derive_proof(&vc, &selected, None).expect("Derivation failed");
+
+    let proof_value = |vc: &VC| match vc.get_proof() {
+        Some(CredentialProof::DataIntegrity(proof)) => proof.get_proof_value().clone(),
+        _ => None,
+    };
+    assert_ne!(proof_value(&first), proof_value(&second));
+    assert_ne!(proof_value(&first), proof_value(&vc));
+
+    assert!(first.verify(&public_doc).expect("Verification failed"));
+    assert!(second.verify(&public_doc).expect("Verification failed"));
+
+    assert!(bbs::derive_proof(&first, &selected, None).is_err());
+    assert!(bbs::derive_proof(&vc, &pointers(&["/credentialSubject/unknown"]), None).is_err());
+}
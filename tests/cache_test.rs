@@ -0,0 +1,169 @@
+#![cfg(not(feature = "wasm"))]
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use chrono::Duration;
+use fi_verifiable_data::{
+    cache::CacheOptions,
+    document::{DocResolver, DocumentLoader, PublicVerificationDocument},
+    keys::KeyType,
+};
+
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+/// Resolves every `did:example:` url and counts how often it was asked.
+struct CountingResolver {
+    calls: Arc<AtomicUsize>,
+}
+
+impl DocResolver for CountingResolver {
+    fn resolve(&self, url: &str) -> Option<PublicVerificationDocument> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+
+        if !url.starts_with("did:example:") {
+            return None;
+        }
+
+        Some(public_doc(url))
+    }
+}
+
+fn public_doc(id: &str) -> PublicVerificationDocument {
+    PublicVerificationDocument::with_key_type(
+        String::from(id),
+        hex::decode(PUBLIC_KEY_HEX).expect("Public key hex decode failed"),
+        KeyType::Ed25519,
+    )
+    .expect("Invalid public key")
+}
+
+fn loader(options: CacheOptions) -> (DocumentLoader, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let mut loader =
+        DocumentLoader::with_cache_options(None, options).expect("Loader creation failed");
+    loader.add_resolver(Box::new(CountingResolver {
+        calls: calls.clone(),
+    }));
+
+    return (loader, calls);
+}
+
+#[test]
+pub fn test_loader_is_shared_between_threads() {
+    let (loader, calls) = loader(CacheOptions::default());
+    let loader = Arc::new(loader);
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let loader = loader.clone();
+            thread::spawn(move || loader.get_verification_document("did:example:issuer#key-1"))
+        })
+        .collect();
+
+    for handle in handles {
+        assert!(handle.join().expect("Thread panicked").is_some());
+    }
+
+    assert!(loader
+        .get_verification_document("did:example:issuer#key-1")
+        .is_some());
+    assert!(calls.load(Ordering::SeqCst) <= 4);
+    assert_eq!(loader.get_cache_size(), 1);
+}
+
+#[test]
+pub fn test_expired_documents_are_resolved_again() {
+    let (loader, calls) = loader(CacheOptions::new(
+        Some(Duration::milliseconds(50)),
+        None,
+        None,
+    ));
+
+    assert!(loader
+        .get_verification_document("did:example:issuer")
+        .is_some());
+    assert!(loader
+        .get_verification_document("did:example:issuer")
+        .is_some());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    thread::sleep(std::time::Duration::from_millis(100));
+
+    assert!(loader
+        .get_verification_document("did:example:issuer")
+        .is_some());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+pub fn test_failed_lookups_are_cached_until_invalidated() {
+    let (loader, calls) = loader(CacheOptions::default());
+
+    assert!(loader
+        .get_verification_document("did:other:issuer")
+        .is_none());
+    assert!(loader
+        .get_verification_document("did:other:issuer")
+        .is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    loader.invalidate("did:other:issuer");
+
+    assert!(loader
+        .get_verification_document("did:other:issuer")
+        .is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+pub fn test_least_recently_used_documents_are_evicted() {
+    let (loader, calls) = loader(CacheOptions::new(None, None, Some(2)));
+
+    loader.get_verification_document("did:example:a");
+    loader.get_verification_document("did:example:b");
+    loader.get_verification_document("did:example:a");
+    loader.get_verification_document("did:example:c");
+    assert_eq!(loader.get_cache_size(), 2);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // "b" was used least recently, so "a" is still cached
+    loader.get_verification_document("did:example:a");
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    loader.get_verification_document("did:example:b");
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+pub fn test_seeded_documents_are_pinned() {
+    let mut docs = HashMap::new();
+    docs.insert(
+        String::from("did:example:seeded"),
+        public_doc("did:example:seeded"),
+    );
+
+    let loader = DocumentLoader::with_cache_options(
+        Some(docs),
+        CacheOptions::new(Some(Duration::zero()), None, Some(1)),
+    )
+    .expect("Loader creation failed");
+
+    loader.clear();
+    assert_eq!(
+        loader.get_verification_document("did:example:seeded"),
+        Some(public_doc("did:example:seeded"))
+    );
+
+    loader.invalidate("did:example:seeded");
+    assert!(loader
+        .get_verification_document("did:example:seeded")
+        .is_none());
+}
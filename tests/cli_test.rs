@@ -0,0 +1,169 @@
+//! Runs with `cargo test --features cli --test cli_test`.
+#![cfg(feature = "cli")]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use serde_json::Value;
+
+fn fi_vc(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fi-vc"))
+        .args(args)
+        .output()
+        .expect("fi-vc failed to run")
+}
+
+fn json_output(output: &Output) -> Value {
+    serde_json::from_slice(output.stdout.as_slice()).expect("Output is not JSON")
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fi-vc-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).expect("Temp dir creation failed");
+    return dir;
+}
+
+fn write(dir: &Path, name: &str, content: &[u8]) -> String {
+    let path = dir.join(name);
+    fs::write(&path, content).expect("Write failed");
+    return path.to_string_lossy().into_owned();
+}
+
+const TEMPLATE: &str = r#"{
+    "@context": ["https://www.w3.org/ns/credentials/v2"],
+    "id": "urn:vc:1",
+    "type": ["VerifiableCredential"],
+    "credentialSubject": { "id": "did:example:holder", "degree": "BSc" }
+}"#;
+
+#[test]
+pub fn test_issue_present_and_verify() {
+    let dir = temp_dir("issue");
+
+    let issuer_key = fi_vc(&["keygen"]);
+    assert!(issuer_key.status.success());
+    let issuer_key = write(&dir, "issuer.json", &issuer_key.stdout);
+    let holder_key = fi_vc(&["keygen", "--alg", "ES256"]);
+    let holder_key = write(&dir, "holder.json", &holder_key.stdout);
+    let template = write(&dir, "template.json", TEMPLATE.as_bytes());
+
+    let vc = fi_vc(&["issue", template.as_str(), "--key", issuer_key.as_str()]);
+    assert!(vc.status.success());
+    assert!(json_output(&vc)["issuer"]
+        .as_str()
+        .unwrap()
+        .starts_with("did:key:"));
+    let vc = write(&dir, "vc.json", &vc.stdout);
+
+    let vp = fi_vc(&["present", vc.as_str(), "--key", holder_key.as_str()]);
+    assert!(vp.status.success());
+    let vp = write(&dir, "vp.json", &vp.stdout);
+
+    // did:key methods are only resolved from the DID when asked to
+    let result = fi_vc(&["verify", vp.as_str()]);
+    assert_eq!(result.status.code(), Some(1));
+    let result = fi_vc(&["verify", vp.as_str(), "--resolve-did-key"]);
+    assert!(result.status.success());
+    let result = json_output(&result);
+    assert_eq!(result["verified"], true);
+    assert_eq!(result["type"], "VerifiablePresentation");
+    assert_eq!(result["credentials"][0]["verified"], true);
+
+    let jwt = fi_vc(&[
+        "issue",
+        template.as_str(),
+        "--key",
+        issuer_key.as_str(),
+        "--format",
+        "jwt",
+    ]);
+    let jwt = write(&dir, "vc.jwt", &jwt.stdout);
+    let result = fi_vc(&["verify", jwt.as_str(), "--key", issuer_key.as_str()]);
+    assert!(result.status.success());
+    assert_eq!(json_output(&result)["format"], "jwt");
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+pub fn test_exit_codes() {
+    let dir = temp_dir("exit");
+
+    let key = fi_vc(&["keygen", "--id", "did:example:issuer#key-1"]);
+    let key = write(&dir, "key.json", &key.stdout);
+    let template = write(&dir, "template.json", TEMPLATE.as_bytes());
+    let vc = fi_vc(&["issue", template.as_str(), "--key", key.as_str()]);
+    let vc = write(&dir, "vc.json", &vc.stdout);
+
+    // did:example can only be resolved with the key passed in
+    let result = fi_vc(&["verify", vc.as_str()]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(json_output(&result)["error"].is_string());
+    assert!(fi_vc(&["verify", vc.as_str(), "--key", key.as_str()])
+        .status
+        .success());
+
+    let tampered = fs::read_to_string(&vc).unwrap().replace("BSc", "PhD");
+    let tampered = write(&dir, "tampered.json", tampered.as_bytes());
+    let result = fi_vc(&["verify", tampered.as_str(), "--key", key.as_str()]);
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(json_output(&result)["verified"], false);
+
+    // A key of the verifier's choosing does not make it the issuer's
+    let other_key = fi_vc(&["keygen"]);
+    let other_key = write(&dir, "other.json", &other_key.stdout);
+    let impostor = TEMPLATE.replace(
+        r#""id": "urn:vc:1","#,
+        r#""id": "urn:vc:1", "issuer": "did:example:issuer","#,
+    );
+    let impostor = write(&dir, "impostor.json", impostor.as_bytes());
+    let vc = fi_vc(&["issue", impostor.as_str(), "--key", other_key.as_str()]);
+    let vc = write(&dir, "impostor-vc.json", &vc.stdout);
+    let result = fi_vc(&[
+        "verify",
+        vc.as_str(),
+        "--key",
+        other_key.as_str(),
+        "--resolve-did-key",
+    ]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(json_output(&result)["error"]
+        .as_str()
+        .unwrap()
+        .contains("does not belong to the issuer"));
+
+    let result = fi_vc(&["verify", "missing.json"]);
+    assert_eq!(result.status.code(), Some(2));
+    assert!(json_output(&result)["error"].is_string());
+
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+pub fn test_inspect_decodes_jwts() {
+    let dir = temp_dir("inspect");
+
+    let key = fi_vc(&["keygen"]);
+    let key = write(&dir, "key.json", &key.stdout);
+    let template = write(&dir, "template.json", TEMPLATE.as_bytes());
+    let jwt = fi_vc(&[
+        "issue",
+        template.as_str(),
+        "--key",
+        key.as_str(),
+        "--format",
+        "jwt",
+    ]);
+    let jwt = write(&dir, "vc.jwt", &jwt.stdout);
+
+    let result = fi_vc(&["inspect", jwt.as_str()]);
+    assert!(result.status.success());
+    let result = json_output(&result);
+    assert_eq!(result["header"]["typ"], "vc+jwt");
+    assert_eq!(result["payload"]["credentialSubject"]["degree"], "BSc");
+
+    fs::remove_dir_all(dir).ok();
+}
@@ -0,0 +1,166 @@
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    cose::{CoseSign1, VC_CONTENT_TYPE, VC_COSE_TYPE},
+    document::{PublicVerificationDocument, VerificationDocument},
+    signer::{InMemorySigner, Signer},
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+const PRIVATE_KEY_HEX: &str = "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+fn signer() -> InMemorySigner {
+    let doc = VerificationDocument::new(
+        String::from("did:example:issuer#key-1"),
+        Some(hex::decode(PRIVATE_KEY_HEX).expect("Private key hex decode failed")),
+        None,
+    );
+    InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed")
+}
+
+fn public_doc() -> PublicVerificationDocument {
+    VerificationDocument::from_hex(
+        String::from("did:example:issuer#key-1"),
+        Algorithm::EdDSA,
+        None,
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed")
+    .public_document()
+    .expect("Public document missing")
+}
+
+fn credential() -> VC {
+    let mut vc = VC::new(
+        String::from("id:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({ "id": "did:example:subject" }));
+    vc
+}
+
+#[test]
+pub fn test_vc_cose_round_trip() {
+    let vc = credential();
+
+    let bytes = vc.to_cose(&signer()).expect("Signing failed");
+    // Tagged COSE_Sign1 (tag 18) holding an array of four
+    assert_eq!(&bytes[..2], &[0xd2, 0x84]);
+
+    let message = CoseSign1::decode(bytes.as_slice()).expect("Decoding failed");
+    assert!(matches!(message.get_algorithm(), Ok(Algorithm::EdDSA)));
+    assert_eq!(message.get_type(), Some(VC_COSE_TYPE));
+    assert_eq!(message.get_content_type(), Some(VC_CONTENT_TYPE));
+    assert_eq!(
+        message.get_kid(),
+        Some("did:example:issuer#key-1".as_bytes())
+    );
+
+    let received = VC::from_cose(bytes.as_slice(), &public_doc()).expect("Verification failed");
+    assert_eq!(
+        received.get_signable_content().unwrap(),
+        vc.get_signable_content().unwrap()
+    );
+
+    // Ed25519 signatures and the header encoding are deterministic
+    assert_eq!(bytes, vc.to_cose(&signer()).expect("Signing failed"));
+}
+
+#[test]
+pub fn test_cose_tampered_or_wrong_key_fails() {
+    let bytes = credential().to_cose(&signer()).expect("Signing failed");
+
+    let position = bytes
+        .windows(4)
+        .position(|val| val == b"id:1")
+        .expect("Payload not found");
+    let mut tampered = bytes.clone();
+    tampered[position + 3] = b'2';
+    assert!(VC::from_cose(tampered.as_slice(), &public_doc()).is_err());
+
+    let other = VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let other = other.public_document().expect("Public document missing");
+    assert!(VC::from_cose(bytes.as_slice(), &other).is_err());
+
+    // A credential is not accepted as a presentation
+    assert!(VP::from_cose(bytes.as_slice(), &public_doc()).is_err());
+}
+
+#[test]
+pub fn test_vp_cose_round_trip_with_es256() {
+    let doc = VerificationDocument::generate(Algorithm::ES256).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::ES256).expect("Signer creation failed");
+
+    let mut vp = VP::new(
+        String::from("id:vp"),
+        Some(String::from("did:example:holder")),
+    );
+    vp.add_verifiable_credentials(credential());
+
+    let bytes = vp.to_cose(&signer).expect("Signing failed");
+    let received = VP::from_cose(
+        bytes.as_slice(),
+        &doc.public_document().expect("Public document missing"),
+    )
+    .expect("Verification failed");
+
+    assert_eq!(received.get_verifiable_credentials().len(), 1);
+    assert_eq!(
+        received.get_signable_content().unwrap(),
+        vp.get_signable_content().unwrap()
+    );
+}
+
+fn encode(value: &ciborium::Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).expect("CBOR encoding failed");
+    bytes
+}
+
+#[test]
+pub fn test_cose_unprotected_header_is_not_trusted() {
+    use ciborium::Value as Cbor;
+
+    // Algorithm, content type and certificate chain only in the unsigned header
+    let payload = credential().to_object().unwrap().to_string().into_bytes();
+    let to_be_signed = encode(&Cbor::Array(vec![
+        Cbor::Text(String::from("Signature1")),
+        Cbor::Bytes(Vec::new()),
+        Cbor::Bytes(Vec::new()),
+        Cbor::Bytes(payload.clone()),
+    ]));
+    let signature = signer().sign(to_be_signed.as_slice()).unwrap();
+    let bytes = encode(&Cbor::Array(vec![
+        Cbor::Bytes(Vec::new()),
+        Cbor::Map(vec![
+            (Cbor::from(1), Cbor::from(-8)),
+            (Cbor::from(3), Cbor::Text(String::from(VC_CONTENT_TYPE))),
+            (Cbor::from(33), Cbor::Bytes(vec![0x30])),
+        ]),
+        Cbor::Bytes(payload),
+        Cbor::Bytes(signature),
+    ]));
+
+    let message = CoseSign1::decode(bytes.as_slice()).expect("Decoding failed");
+    assert!(message.get_algorithm().is_err());
+    assert_eq!(message.get_content_type(), None);
+    assert!(message.get_x5chain().is_empty());
+    assert!(VC::from_cose(bytes.as_slice(), &public_doc()).is_err());
+
+    // A document without a key type still has to support the protected algorithm
+    let doc = VerificationDocument::generate(Algorithm::ES256).expect("Key generation failed");
+    let es256 = InMemorySigner::new(&doc, Algorithm::ES256).expect("Signer creation failed");
+    let bytes = credential().to_cose(&es256).expect("Signing failed");
+    let error = CoseSign1::decode(bytes.as_slice())
+        .expect("Decoding failed")
+        .verify(&public_doc(), None)
+        .expect_err("Key of another type was accepted");
+    assert!(error
+        .to_string()
+        .contains("does not match the COSE algorithm"));
+}
@@ -0,0 +1,44 @@
+use fi_verifiable_data::{document::PublicVerificationDocument, keys::KeyType, vc::VC, vp::VP};
+use serde_json::Value;
+
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+/// Signed through the JS bindings by `tests/js/sign_vc.mjs`.
+const JS_SIGNED: &str = include_str!("js/signed.json");
+
+fn public_doc() -> PublicVerificationDocument {
+    PublicVerificationDocument::with_key_type(
+        String::from("did:example:issuer#key-1"),
+        hex::decode(PUBLIC_KEY_HEX).expect("Public key hex decode failed"),
+        KeyType::Ed25519,
+    )
+    .expect("Invalid public key")
+}
+
+fn js_signed() -> Value {
+    serde_json::from_str(JS_SIGNED).expect("Invalid fixture")
+}
+
+#[test]
+pub fn test_js_signed_vc_verifies_natively() {
+    let object = js_signed()["vc"].clone();
+
+    let vc = VC::from(object.clone()).expect("VC parsing failed");
+    assert!(vc.verify(&public_doc()).expect("Verification failed"));
+    assert_eq!(vc.to_object().expect("to_object failed"), object);
+}
+
+#[test]
+pub fn test_js_signed_vp_verifies_natively() {
+    let vp = VP::from(js_signed()["vp"].clone()).expect("VP parsing failed");
+    assert!(vp.verify(&public_doc()).expect("Verification failed"));
+}
+
+#[test]
+pub fn test_tampered_js_signed_vc_fails() {
+    let mut object = js_signed()["vc"].clone();
+    object["nickname"] = Value::from("Someone else");
+
+    let vc = VC::from(object).expect("VC parsing failed");
+    assert!(!vc.verify(&public_doc()).unwrap_or(false));
+}
@@ -0,0 +1,192 @@
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    dcql::DcqlQuery,
+    document::VerificationDocument,
+    enveloped::EnvelopedCredential,
+    oid4vp::AuthorizationRequest,
+    signer::InMemorySigner,
+    vc::VC,
+    vp::{PresentedCredential, VP},
+};
+use serde_json::{json, Value};
+
+fn credential(id: &str, _type: &str, subject: Value) -> VC {
+    let mut vc = VC::new(
+        String::from(id),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.add_type(String::from(_type));
+    vc.set_credential_subject(subject);
+    vc
+}
+
+fn wallet() -> Vec<PresentedCredential> {
+    let doc = VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed");
+
+    let licence = credential(
+        "urn:uuid:licence",
+        "DrivingLicenceCredential",
+        json!({ "id": "did:example:holder", "categories": [{ "code": "B" }, { "code": "C1" }] }),
+    );
+    let token = licence.to_jwt(&signer).expect("Signing failed");
+
+    return vec![
+        PresentedCredential::Embedded(Box::new(credential(
+            "urn:uuid:id-card",
+            "IdentityCredential",
+            json!({ "id": "did:example:holder", "nationality": "NL", "given_name": "Ada" }),
+        ))),
+        PresentedCredential::Enveloped(EnvelopedCredential::from_jwt(token.as_str())),
+        PresentedCredential::Embedded(Box::new(credential(
+            "urn:uuid:passport",
+            "PassportCredential",
+            json!({ "id": "did:example:holder", "nationality": "BE" }),
+        ))),
+    ];
+}
+
+fn ids(credentials: &[PresentedCredential]) -> Vec<String> {
+    credentials
+        .iter()
+        .map(|val| {
+            val.get_credential().unwrap().to_object().unwrap()["id"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+pub fn test_claims_and_types_select_credentials() {
+    let wallet = wallet();
+    let query = DcqlQuery::from(json!({
+        "credentials": [
+            {
+                "id": "eu_identity",
+                "format": "ldp_vc",
+                "multiple": true,
+                "meta": { "type_values": [["IdentityCredential"], ["PassportCredential"]] },
+                "claims": [{ "path": ["credentialSubject", "nationality"], "values": ["NL", "BE"] }]
+            },
+            {
+                "id": "truck_licence",
+                "format": "jwt_vc_json",
+                "claims": [{ "path": ["credentialSubject", "categories", null, "code"], "values": ["C1"] }]
+            }
+        ]
+    }))
+    .expect("Query parsing failed");
+
+    let selected = query.select(&wallet).expect("Selection failed");
+    assert_eq!(
+        ids(&selected["eu_identity"]),
+        vec!["urn:uuid:id-card", "urn:uuid:passport"]
+    );
+    assert_eq!(ids(&selected["truck_licence"]), vec!["urn:uuid:licence"]);
+
+    // The licence is a JWT credential, so an ldp_vc query for it finds nothing
+    let query = DcqlQuery::from(json!({
+        "credentials": [{
+            "id": "licence",
+            "format": "ldp_vc",
+            "meta": { "type_values": [["DrivingLicenceCredential"]] }
+        }]
+    }))
+    .expect("Query parsing failed");
+    assert!(query.select(&wallet).is_err());
+}
+
+#[test]
+pub fn test_credential_sets_and_claim_sets() {
+    let wallet = wallet();
+    let query = DcqlQuery::from(json!({
+        "credentials": [
+            {
+                "id": "mdl",
+                "format": "mso_mdoc",
+                "meta": { "doctype_value": "org.iso.18013.5.1.mDL" }
+            },
+            {
+                "id": "id_card",
+                "format": "ldp_vc",
+                "claims": [
+                    { "id": "name", "path": ["credentialSubject", "given_name"] },
+                    { "id": "birth", "path": ["credentialSubject", "birth_date"] },
+                    { "id": "country", "path": ["credentialSubject", "nationality"] }
+                ],
+                "claim_sets": [["name", "birth"], ["name", "country"]]
+            },
+            {
+                "id": "licence",
+                "format": "jwt_vc_json",
+                "meta": { "type_values": [["DrivingLicenceCredential"]] }
+            }
+        ],
+        "credential_sets": [
+            { "options": [["mdl"], ["id_card"]] },
+            { "options": [["licence"]], "required": false }
+        ]
+    }))
+    .expect("Query parsing failed");
+
+    // No mdoc, so the identity card answers the first set and the optional licence is added
+    let selected = query.select(&wallet).expect("Selection failed");
+    let answered: Vec<&String> = selected.keys().collect();
+    assert_eq!(answered, vec!["id_card", "licence"]);
+
+    // Without the identity card the required set cannot be met
+    assert!(query.select(&wallet[1..]).is_err());
+
+    let invalid = DcqlQuery::from(json!({
+        "credentials": [{ "id": "id_card", "format": "ldp_vc" }],
+        "credential_sets": [{ "options": [["passport"]] }]
+    }));
+    assert!(invalid.is_err());
+}
+
+#[test]
+pub fn test_presentation_satisfies_query() {
+    let wallet = wallet();
+    let query = DcqlQuery::from(json!({
+        "credentials": [{
+            "id": "licence",
+            "format": "jwt_vc_json",
+            "claims": [{ "path": ["credentialSubject", "categories", 0, "code"], "values": ["B"] }]
+        }]
+    }))
+    .expect("Query parsing failed");
+
+    // The query travels in the authorization request
+    let mut request = AuthorizationRequest::new(
+        String::from("https://verifier.example.com"),
+        String::from("https://verifier.example.com/response"),
+    );
+    request.set_dcql_query(Some(query));
+    let uri = request.to_uri().expect("URI creation failed");
+    let received = AuthorizationRequest::from_uri(uri.as_str()).expect("Request parsing failed");
+    let query = received.get_dcql_query().clone().expect("Query missing");
+    assert_eq!(request.get_dcql_query(), &Some(query.clone()));
+
+    let mut vp = VP::new(
+        String::from("urn:uuid:presentation"),
+        Some(String::from("did:example:holder")),
+    );
+    for credential in query.select(&wallet).expect("Selection failed")["licence"].clone() {
+        vp.add_presented_credential(credential);
+    }
+    assert!(query.evaluate_presentation(&vp).expect("Evaluation failed"));
+
+    let mut other = VP::new(
+        String::from("urn:uuid:presentation"),
+        Some(String::from("did:example:holder")),
+    );
+    other.add_presented_credential(wallet[0].clone());
+    assert!(!query
+        .evaluate_presentation(&other)
+        .expect("Evaluation failed"));
+}
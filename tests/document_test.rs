@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, PublicVerificationDocument, VerificationDocument},
+    keys::KeyType,
+    proof::FiProof,
+    signer::InMemorySigner,
+    vc::VC,
+};
+use serde_json::Value;
+
+const PRIVATE_KEY_HEX: &str = "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+fn eddsa_doc() -> VerificationDocument {
+    VerificationDocument::from_hex(
+        String::from("did:example:issuer#key-1"),
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed")
+}
+
+#[test]
+pub fn test_private_key_is_not_serialized() {
+    let value = serde_json::to_value(eddsa_doc()).expect("Serialization failed");
+
+    assert!(value.get("private_key").is_none());
+    assert!(value.get("public_key").is_some());
+}
+
+#[test]
+pub fn test_private_key_is_redacted_from_debug() {
+    let eddsa_doc = eddsa_doc();
+    let private_key = hex::decode(PRIVATE_KEY_HEX).expect("Private key hex decode failed");
+    let debug = format!("{:?}", eddsa_doc);
+
+    assert!(debug.contains("REDACTED"));
+    assert!(!debug.contains(&format!("{:?}", private_key)));
+
+    let signing_doc = eddsa_doc
+        .into_signing_document()
+        .expect("Signing document missing");
+    assert!(format!("{:?}", signing_doc).contains("REDACTED"));
+}
+
+#[test]
+pub fn test_sign_with_signing_document() {
+    let eddsa_doc = eddsa_doc();
+    let public_doc = eddsa_doc
+        .public_document()
+        .expect("Public document missing");
+    let signing_doc = eddsa_doc
+        .into_signing_document()
+        .expect("Signing document missing");
+
+    let signer = InMemorySigner::from_signing_document(&signing_doc, Algorithm::EdDSA)
+        .expect("Signer creation failed");
+
+    let mut vc = VC::new(
+        String::from("id:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    if let Err(error) = vc.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("ESig")),
+    ) {
+        panic!("{}", error);
+    }
+
+    let result = match vc.verify(&public_doc) {
+        Ok(val) => val,
+        Err(error) => panic!("{}", error),
+    };
+    assert!(result);
+}
+
+#[test]
+pub fn test_loader_returns_public_documents() {
+    let public_doc = eddsa_doc()
+        .public_document()
+        .expect("Public document missing");
+
+    let mut docs: HashMap<String, PublicVerificationDocument> = HashMap::new();
+    docs.insert(String::from(public_doc.get_id()), public_doc.clone());
+
+    let loader = DocumentLoader::new(Some(docs)).expect("Loader creation failed");
+
+    assert_eq!(
+        loader.get_verification_document("did:example:issuer#key-1"),
+        Some(public_doc)
+    );
+    assert!(loader
+        .get_verification_document("did:example:other#key-1")
+        .is_none());
+}
+
+#[test]
+pub fn test_loader_reads_did_documents() {
+    let public_doc = VerificationDocument::from_hex(
+        String::from("did:example:issuer#key-1"),
+        Algorithm::EdDSA,
+        None,
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .and_then(|doc| doc.public_document())
+    .expect("Public document missing");
+    let did_document = serde_json::json!({
+        "id": "did:example:issuer",
+        "verificationMethod": [{
+            "id": "#key-1",
+            "type": "Multikey",
+            "controller": "did:example:issuer",
+            "publicKeyMultibase": public_doc.to_multikey().expect("Multikey encoding failed"),
+        }],
+        "assertionMethod": [
+            "did:example:issuer#key-1",
+            {
+                "id": "did:example:issuer#key-2",
+                "type": "JsonWebKey2020",
+                "publicKeyJwk": public_doc.to_jwk().expect("JWK encoding failed"),
+            },
+            {
+                "id": "did:example:issuer#key-3",
+                "type": "Ed25519VerificationKey2018",
+                "publicKeyHex": PUBLIC_KEY_HEX,
+            },
+            {
+                "id": "did:example:issuer#key-4",
+                "publicKeyHex": PUBLIC_KEY_HEX,
+            }
+        ],
+    });
+
+    let loader = DocumentLoader::new(None).expect("Loader creation failed");
+    assert_eq!(
+        loader
+            .add_did_document(&did_document)
+            .expect("DID document rejected"),
+        4
+    );
+
+    for id in [
+        "did:example:issuer#key-1",
+        "did:example:issuer#key-2",
+        "did:example:issuer#key-3",
+    ] {
+        let doc = loader
+            .get_verification_document(id)
+            .expect("Verification method missing");
+        assert_eq!(doc.get_id(), id);
+        assert_eq!(doc.get_public_key(), public_doc.get_public_key());
+        assert_eq!(doc.key_type(), Some(KeyType::Ed25519));
+    }
+
+    // A raw key without a method type stays untyped and verifies nothing
+    let untyped = loader
+        .get_verification_document("did:example:issuer#key-4")
+        .expect("Verification method missing");
+    assert!(untyped.get_algorithms().is_empty());
+
+    assert!(loader
+        .add_did_document(&serde_json::json!({ "verificationMethod": [] }))
+        .is_err());
+}
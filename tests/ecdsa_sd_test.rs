@@ -0,0 +1,119 @@
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{PublicVerificationDocument, VerificationDocument},
+    ecdsa_sd,
+    proof::CredentialProof,
+    signer::InMemorySigner,
+    vc::VC,
+};
+use serde_json::{json, Value};
+
+fn issue(alg: Algorithm) -> (VC, PublicVerificationDocument) {
+    let mut vc = VC::new(
+        String::from("urn:uuid:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({
+        "id": "did:example:holder",
+        "givenName": "Alice",
+        "birthDate": "1990-01-01",
+        "address": {
+            "country": "LK",
+            "city": "Colombo"
+        }
+    }));
+
+    let doc = VerificationDocument::generate(alg).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, alg).expect("Signer creation failed");
+    ecdsa_sd::add_base_proof(
+        &mut vc,
+        &signer,
+        String::from("assertionMethod"),
+        &[String::from("/credentialSubject/address/country")],
+    )
+    .expect("Signing failed");
+
+    (vc, doc.public_document().expect("Public document missing"))
+}
+
+fn pointers(pointers: &[&str]) -> Vec<String> {
+    pointers.iter().map(|val| val.to_string()).collect()
+}
+
+#[test]
+pub fn ecdsa_sd_base_proof_verifies() {
+    for alg in [Algorithm::ES256, Algorithm::ES384] {
+        let (vc, public_doc) = issue(alg);
+
+        match vc.get_proof() {
+            Some(CredentialProof::DataIntegrity(proof)) => {
+                assert_eq!(proof.get_cryptosuite(), ecdsa_sd::FI_ECDSA_SD_2023)
+            }
+            _ => panic!("Expected a Data Integrity proof"),
+        };
+        assert!(vc.verify(&public_doc).expect("Verification failed"));
+
+        // The proofs are not standard `ecdsa-sd-2023` proofs and are never verified as such
+        let mut value = vc.to_object().expect("Object creation failed");
+        value["proof"]["cryptosuite"] = Value::from("ecdsa-sd-2023");
+        let relabelled: VC = serde_json::from_value(value).expect("Deserialization failed");
+        assert!(relabelled.verify(&public_doc).is_err());
+    }
+
+    let mut vc = VC::new(
+        String::from("urn:uuid:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    let doc = VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed");
+    assert!(
+        ecdsa_sd::add_base_proof(&mut vc, &signer, String::from("assertionMethod"), &[]).is_err()
+    );
+}
+
+#[test]
+pub fn ecdsa_sd_derived_proof_discloses_selected_claims() {
+    let (vc, public_doc) = issue(Algorithm::ES256);
+
+    let derived = ecdsa_sd::derive_proof(&vc, &pointers(&["/credentialSubject/birthDate"]))
+        .expect("Derivation failed");
+
+    let subject = &derived.to_object().expect("Object creation failed")["credentialSubject"];
+    assert_eq!(subject["birthDate"], "1990-01-01");
+    assert_eq!(subject["address"]["country"], "LK");
+    assert!(subject.get("givenName").is_none());
+    assert!(subject["address"].get("city").is_none());
+
+    let received =
+        VC::from(derived.to_object().expect("Object creation failed")).expect("Parsing failed");
+    assert!(received.verify(&public_doc).expect("Verification failed"));
+}
+
+#[test]
+pub fn ecdsa_sd_tampered_derived_proof_fails() {
+    let (vc, public_doc) = issue(Algorithm::ES256);
+
+    let derived = ecdsa_sd::derive_proof(&vc, &pointers(&["/credentialSubject/birthDate"]))
+        .expect("Derivation failed");
+
+    let mut object = derived.to_object().expect("Object creation failed");
+    object["credentialSubject"]["birthDate"] = Value::from("2010-01-01");
+    let tampered = VC::from(object).expect("Parsing failed");
+    assert!(!tampered.verify(&public_doc).expect("Verification failed"));
+
+    let mut object = derived.to_object().expect("Object creation failed");
+    object["credentialSubject"]["address"]["country"] = Value::from("US");
+    let tampered = VC::from(object).expect("Parsing failed");
+    assert!(!tampered.verify(&public_doc).expect("Verification failed"));
+
+    let mut object = derived.to_object().expect("Object creation failed");
+    object["credentialSubject"]["givenName"] = Value::from("Mallory");
+    let tampered = VC::from(object).expect("Parsing failed");
+    assert!(!tampered.verify(&public_doc).expect("Verification failed"));
+}
@@ -0,0 +1,174 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, VerificationDocument},
+    enveloped::{EnvelopedCredential, ENVELOPED_VC_TYPE},
+    jose::{Jwt, VC_JWT_TYPE},
+    proof::FiProof,
+    signer::InMemorySigner,
+    vc::VC,
+    vp::{PresentedCredential, VP},
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+const PRIVATE_KEY_HEX: &str = "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+const ISSUER_KEY: &str = "did:example:issuer#key-1";
+
+fn signer() -> InMemorySigner {
+    let doc = VerificationDocument::new(
+        String::from(ISSUER_KEY),
+        Some(hex::decode(PRIVATE_KEY_HEX).expect("Private key hex decode failed")),
+        None,
+    );
+    InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed")
+}
+
+fn loader() -> DocumentLoader {
+    let loader = DocumentLoader::new(None).expect("Loader creation failed");
+    loader.add_document(
+        VerificationDocument::from_hex(
+            String::from(ISSUER_KEY),
+            Algorithm::EdDSA,
+            None,
+            Some(String::from(PUBLIC_KEY_HEX)),
+        )
+        .expect("Document creation failed")
+        .public_document()
+        .expect("Public document missing"),
+    );
+    loader
+}
+
+fn credential(id: &str) -> VC {
+    let mut vc = VC::new(
+        String::from(id),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({ "id": "did:example:subject" }));
+    vc
+}
+
+fn presentation() -> VP {
+    let mut embedded = credential("id:embedded");
+    embedded
+        .sign(
+            &signer(),
+            FiProof::new(Algorithm::EdDSA, String::from("assertionMethod")),
+        )
+        .expect("Signing failed");
+
+    let jwt = credential("id:jwt")
+        .to_jwt(&signer())
+        .expect("Signing failed");
+    let cose = credential("id:cose")
+        .to_cose(&signer())
+        .expect("Signing failed");
+
+    let mut vp = VP::new(
+        String::from("id:vp"),
+        Some(String::from("did:example:holder")),
+    );
+    vp.add_verifiable_credentials(embedded);
+    vp.add_enveloped_credential(EnvelopedCredential::from_jwt(jwt.as_str()));
+    vp.add_enveloped_credential(EnvelopedCredential::from_cose(cose.as_slice()));
+    vp
+}
+
+#[test]
+pub fn test_vp_with_embedded_and_enveloped_credentials() {
+    let object = presentation().to_object().expect("Object creation failed");
+
+    let credentials = object["verifiableCredential"].as_array().unwrap();
+    assert_eq!(credentials[0]["id"], "id:embedded");
+    assert_eq!(credentials[1]["type"], ENVELOPED_VC_TYPE);
+    assert!(credentials[1]["id"]
+        .as_str()
+        .unwrap()
+        .starts_with("data:application/vc+jwt,ey"));
+    assert!(credentials[2]["id"]
+        .as_str()
+        .unwrap()
+        .starts_with("data:application/vc+cose;base64,"));
+
+    let received = VP::from(object).expect("Parsing failed");
+    let credentials = received.get_verifiable_credentials();
+    assert!(matches!(credentials[0], PresentedCredential::Embedded(_)));
+    assert!(matches!(credentials[1], PresentedCredential::Enveloped(_)));
+    assert_eq!(
+        credentials[2]
+            .get_credential()
+            .expect("Decoding failed")
+            .to_object()
+            .unwrap()["id"],
+        "id:cose"
+    );
+    assert_eq!(
+        credentials[1].get_verification_method().expect("No kid"),
+        ISSUER_KEY
+    );
+
+    assert!(received
+        .verify_credentials(&loader())
+        .expect("Verification failed"));
+}
+
+#[test]
+pub fn test_enveloped_sd_jwt_credential() {
+    let encoded = URL_SAFE_NO_PAD.encode(json!(["salt-1", "name", "Alice"]).to_string());
+    let digest = URL_SAFE_NO_PAD.encode(Sha256::digest(encoded.as_bytes()));
+
+    let mut payload = credential("id:sd-jwt")
+        .get_signable_content()
+        .expect("Object creation failed");
+    payload["credentialSubject"]["_sd"] = json!([digest]);
+    payload["_sd_alg"] = Value::from("sha-256");
+    let jwt = Jwt::sign(&payload, VC_JWT_TYPE, &signer()).expect("Signing failed");
+
+    let enveloped = EnvelopedCredential::from_sd_jwt(format!("{}~{}~", jwt, encoded).as_str());
+    assert_eq!(enveloped.get_media_type(), Some("application/vc+sd-jwt"));
+
+    let vc = enveloped.get_credential().expect("Decoding failed");
+    assert_eq!(
+        vc.to_object().unwrap()["credentialSubject"]["name"],
+        "Alice"
+    );
+
+    let mut vp = VP::new(String::from("id:vp"), None);
+    vp.add_enveloped_credential(enveloped);
+    assert!(vp
+        .verify_credentials(&loader())
+        .expect("Verification failed"));
+}
+
+#[test]
+pub fn test_tampered_or_unsupported_enveloped_credential() {
+    let mut object = presentation().to_object().expect("Object creation failed");
+
+    let token = object["verifiableCredential"][1]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let parts: Vec<&str> = token.split('.').collect();
+    let payload = URL_SAFE_NO_PAD.encode(
+        credential("id:forged")
+            .get_signable_content()
+            .unwrap()
+            .to_string(),
+    );
+    object["verifiableCredential"][1]["id"] =
+        Value::from(format!("{}.{}.{}", parts[0], payload, parts[2]));
+
+    let tampered = VP::from(object.clone()).expect("Parsing failed");
+    assert!(!tampered
+        .verify_credentials(&loader())
+        .expect("Verification failed"));
+
+    object["verifiableCredential"][1]["id"] = Value::from("data:application/vc+ld+json,{}");
+    let unsupported = VP::from(object).expect("Parsing failed");
+    assert!(unsupported.verify_credentials(&loader()).is_err());
+}
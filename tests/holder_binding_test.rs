@@ -0,0 +1,234 @@
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, PublicVerificationDocument, VerificationDocument},
+    enveloped::EnvelopedCredential,
+    keys::KeyType,
+    oid4vp::{create_response, verify_response, AuthorizationRequest},
+    proof::FiProof,
+    signer::InMemorySigner,
+    streaming::BorrowedVP,
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+struct Party {
+    doc: VerificationDocument,
+    signer: InMemorySigner,
+}
+
+fn new_party(alg: Algorithm) -> Party {
+    let doc = VerificationDocument::generate(alg).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, alg).expect("Signer creation failed");
+    Party { doc, signer }
+}
+
+fn did(party: &Party) -> String {
+    party.doc.get_id().split('#').next().unwrap().to_string()
+}
+
+fn credential(issuer: &Party, holder: &Party) -> VC {
+    let mut vc = VC::new(
+        String::from("urn:uuid:licence"),
+        Value::from(did(issuer)),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({ "id": did(holder), "licence": "B" }));
+    vc
+}
+
+fn signed_presentation(vc: VC, presenter: &Party) -> VP {
+    let mut vp = VP::new(String::from("urn:uuid:presentation"), Some(did(presenter)));
+    vp.add_verifiable_credentials(vc);
+    vp.sign(
+        &presenter.signer,
+        FiProof::new(Algorithm::ES256, String::from("authentication")),
+    )
+    .expect("Signing failed");
+    vp
+}
+
+#[test]
+pub fn test_credential_bound_to_key() {
+    let (issuer, holder, thief) = (
+        new_party(Algorithm::ES256),
+        new_party(Algorithm::ES256),
+        new_party(Algorithm::ES256),
+    );
+
+    let mut vc = credential(&issuer, &holder);
+    vc.bind_to_key(&holder.doc.public_document().unwrap())
+        .expect("Binding failed");
+    assert_eq!(vc.get_confirmation().as_ref().unwrap()["jwk"]["kty"], "EC");
+    vc.sign(
+        &issuer.signer,
+        FiProof::new(Algorithm::ES256, String::from("assertionMethod")),
+    )
+    .expect("Signing failed");
+
+    let vp = signed_presentation(vc.clone(), &holder);
+    assert!(vp
+        .verify(&holder.doc.public_document().unwrap())
+        .expect("Verification failed"));
+
+    // The thief's own signature is valid, but the credential names another key
+    let vp = signed_presentation(vc, &thief);
+    let error = vp
+        .verify(&thief.doc.public_document().unwrap())
+        .expect_err("Presentation by another holder was accepted");
+    assert!(error.to_string().contains("bound to another holder"));
+}
+
+#[test]
+pub fn test_enveloped_credential_bound_to_subject() {
+    let (issuer, holder, thief) = (
+        new_party(Algorithm::ES256),
+        new_party(Algorithm::ES256),
+        new_party(Algorithm::ES256),
+    );
+
+    let mut vc = credential(&issuer, &holder);
+    vc.bind_to_subject().expect("Binding failed");
+    assert_eq!(vc.get_confirmation(), &Some(json!({ "kid": did(&holder) })));
+    let token = vc.to_jwt(&issuer.signer).expect("Signing failed");
+
+    let mut vp = VP::new(String::from("urn:uuid:presentation"), Some(did(&holder)));
+    vp.add_enveloped_credential(EnvelopedCredential::from_jwt(token.as_str()));
+    assert!(vp
+        .check_holder_binding(&holder.doc.public_document().unwrap())
+        .is_ok());
+    assert!(vp
+        .check_holder_binding(&thief.doc.public_document().unwrap())
+        .is_err());
+
+    // Unbound credentials can be presented by anyone
+    let mut unbound = VP::new(String::from("urn:uuid:presentation"), None);
+    unbound.add_verifiable_credentials(credential(&issuer, &holder));
+    assert!(unbound
+        .check_holder_binding(&thief.doc.public_document().unwrap())
+        .is_ok());
+}
+
+#[test]
+pub fn test_vp_token_with_credential_of_another_holder_is_rejected() {
+    let (issuer, holder, thief) = (
+        new_party(Algorithm::EdDSA),
+        new_party(Algorithm::EdDSA),
+        new_party(Algorithm::EdDSA),
+    );
+    let loader = DocumentLoader::new(None).expect("Loader creation failed");
+    for party in [&issuer, &holder, &thief] {
+        loader.add_document(party.doc.public_document().unwrap());
+    }
+
+    let mut vc = credential(&issuer, &holder);
+    vc.bind_to_subject().expect("Binding failed");
+    let token = vc.to_jwt(&issuer.signer).expect("Signing failed");
+
+    let request = AuthorizationRequest::new(
+        String::from("https://verifier.example.com"),
+        String::from("https://verifier.example.com/response"),
+    );
+
+    let mut vp = VP::new(String::from("urn:uuid:presentation"), Some(did(&holder)));
+    vp.add_enveloped_credential(EnvelopedCredential::from_jwt(token.as_str()));
+    let response =
+        create_response(&request, &vp, &holder.signer, None).expect("Response creation failed");
+    assert!(verify_response(&request, &response, &loader).is_ok());
+
+    // The thief presents the credential as its own holder
+    vp.set_holder(Some(did(&thief)));
+    let response =
+        create_response(&request, &vp, &thief.signer, None).expect("Response creation failed");
+    let error = match verify_response(&request, &response, &loader) {
+        Ok(_) => panic!("Presentation by another holder was accepted"),
+        Err(error) => error,
+    };
+    assert!(error.to_string().contains("bound to another holder"));
+}
+
+#[test]
+pub fn test_presentation_holder_must_sign() {
+    let (issuer, holder, thief) = (
+        new_party(Algorithm::ES256),
+        new_party(Algorithm::ES256),
+        new_party(Algorithm::ES256),
+    );
+
+    // The credential carries no `cnf`, only the presentation's holder ties it to a key
+    let mut vc = credential(&issuer, &holder);
+    vc.sign(
+        &issuer.signer,
+        FiProof::new(Algorithm::ES256, String::from("assertionMethod")),
+    )
+    .expect("Signing failed");
+
+    let mut vp = VP::new(String::from("urn:uuid:presentation"), Some(did(&holder)));
+    vp.add_verifiable_credentials(vc);
+    vp.sign(
+        &thief.signer,
+        FiProof::new(Algorithm::ES256, String::from("authentication")),
+    )
+    .expect("Signing failed");
+
+    let error = vp
+        .verify(&thief.doc.public_document().unwrap())
+        .expect_err("Presentation signed by someone other than its holder was accepted");
+    assert!(error.to_string().contains("not signed by its holder"));
+
+    let json = vp.to_object().unwrap().to_string();
+    assert!(BorrowedVP::parse(json.as_str())
+        .unwrap()
+        .verify(&thief.doc.public_document().unwrap())
+        .is_err());
+}
+
+#[test]
+pub fn test_presentation_key_must_be_the_verification_method() {
+    let (issuer, holder, thief) = (
+        new_party(Algorithm::ES256),
+        new_party(Algorithm::ES256),
+        new_party(Algorithm::ES256),
+    );
+
+    let mut vc = credential(&issuer, &holder);
+    vc.sign(
+        &issuer.signer,
+        FiProof::new(Algorithm::ES256, String::from("assertionMethod")),
+    )
+    .expect("Signing failed");
+
+    let mut vp = VP::new(String::from("urn:uuid:presentation"), Some(did(&holder)));
+    vp.add_verifiable_credentials(vc);
+    vp.sign(
+        &thief.signer,
+        FiProof::new(Algorithm::ES256, String::from("authentication")),
+    )
+    .expect("Signing failed");
+
+    // The thief's key filed under the holder's id passes the signature and holder checks
+    let relabelled = PublicVerificationDocument::with_key_type(
+        holder.doc.get_id().clone(),
+        thief
+            .doc
+            .public_document()
+            .unwrap()
+            .get_public_key()
+            .clone(),
+        KeyType::P256,
+    )
+    .expect("Invalid public key");
+
+    let error = vp
+        .verify(&relabelled)
+        .expect_err("Proof of another verification method was accepted");
+    assert!(error.to_string().contains("not the verification document"));
+
+    let json = vp.to_object().unwrap().to_string();
+    assert!(BorrowedVP::parse(json.as_str())
+        .unwrap()
+        .verify(&relabelled)
+        .is_err());
+}
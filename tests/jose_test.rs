@@ -0,0 +1,280 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{PublicVerificationDocument, VerificationDocument},
+    jose::{Jwt, SdJwt, KB_JWT_TYPE, VC_JWT_TYPE},
+    keys::KeyType,
+    signer::InMemorySigner,
+    vc::VC,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+const PRIVATE_KEY_HEX: &str = "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+fn signer() -> InMemorySigner {
+    let doc = VerificationDocument::new(
+        String::from("did:example:issuer#key-1"),
+        Some(hex::decode(PRIVATE_KEY_HEX).expect("Private key hex decode failed")),
+        None,
+    );
+    InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed")
+}
+
+fn public_doc() -> PublicVerificationDocument {
+    PublicVerificationDocument::with_key_type(
+        String::from("did:example:issuer#key-1"),
+        hex::decode(PUBLIC_KEY_HEX).expect("Public key hex decode failed"),
+        KeyType::Ed25519,
+    )
+    .expect("Invalid public key")
+}
+
+fn disclosure(value: Value) -> (String, String) {
+    let encoded = URL_SAFE_NO_PAD.encode(value.to_string());
+    let digest = URL_SAFE_NO_PAD.encode(Sha256::digest(encoded.as_bytes()));
+    return (encoded, digest);
+}
+
+#[test]
+pub fn test_vc_jwt_round_trip() {
+    let mut vc = VC::new(
+        String::from("id:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({ "id": "did:example:subject" }));
+
+    let token = vc.to_jwt(&signer()).expect("Signing failed");
+    let jwt = Jwt::decode(token.as_str()).expect("Decoding failed");
+
+    assert_eq!(jwt.get_type(), Some(VC_JWT_TYPE));
+    assert_eq!(jwt.get_kid(), Some("did:example:issuer#key-1"));
+    assert_eq!(jwt.get_payload(), &vc.get_signable_content().unwrap());
+    assert!(jwt.verify(&public_doc()).expect("Verification failed"));
+
+    // A key of unknown type is never trusted
+    let untyped = PublicVerificationDocument::new(
+        String::from("did:example:issuer#key-1"),
+        hex::decode(PUBLIC_KEY_HEX).expect("Public key hex decode failed"),
+    );
+    assert!(jwt.verify(&untyped).is_err());
+
+    let payload = token.split('.').nth(1).unwrap();
+    let tampered = token.replace(
+        payload,
+        URL_SAFE_NO_PAD
+            .encode(json!({ "id": "id:2" }).to_string())
+            .as_str(),
+    );
+    assert!(!Jwt::decode(tampered.as_str())
+        .expect("Decoding failed")
+        .verify(&public_doc())
+        .unwrap_or(false));
+}
+
+#[test]
+pub fn test_symmetric_jwt_algorithms_are_rejected() {
+    let header = URL_SAFE_NO_PAD.encode(json!({ "alg": "HS256" }).to_string());
+    let payload = URL_SAFE_NO_PAD.encode("{}");
+    let jwt =
+        Jwt::decode(format!("{}.{}.c2ln", header, payload).as_str()).expect("Decoding failed");
+
+    assert!(jwt.verify(&public_doc()).is_err());
+    assert!(Jwt::decode("a.b").is_err());
+}
+
+#[test]
+pub fn test_sd_jwt_disclosures_are_put_in_place() {
+    let (name, name_digest) = disclosure(json!(["salt-1", "name", "Alice"]));
+    let (_hidden, hidden_digest) = disclosure(json!(["salt-2", "age", 30]));
+    let (nationality, nationality_digest) = disclosure(json!(["salt-3", "DE"]));
+
+    let payload = json!({
+        "iss": "did:example:issuer",
+        "_sd_alg": "sha-256",
+        "_sd": [name_digest, hidden_digest],
+        "nationalities": ["LK", { "...": nationality_digest }],
+    });
+    let jwt = Jwt::sign(&payload, "vc+sd-jwt", &signer()).expect("Signing failed");
+
+    let sd_jwt = SdJwt::decode(format!("{}~{}~{}~", jwt, name, nationality).as_str())
+        .expect("Decoding failed");
+    assert_eq!(sd_jwt.get_disclosures().len(), 2);
+    assert!(sd_jwt.get_key_binding_jwt().is_none());
+    assert!(sd_jwt
+        .get_jwt()
+        .verify(&public_doc())
+        .expect("Verification failed"));
+
+    assert_eq!(
+        sd_jwt.get_claims().expect("Disclosure failed"),
+        json!({
+            "iss": "did:example:issuer",
+            "name": "Alice",
+            "nationalities": ["LK", "DE"],
+        })
+    );
+
+    let with_key_binding =
+        SdJwt::decode(format!("{}~{}~{}", jwt, name, jwt).as_str()).expect("Decoding failed");
+    assert!(with_key_binding.get_key_binding_jwt().is_some());
+    assert!(SdJwt::decode(jwt.as_str()).is_err());
+}
+
+#[test]
+pub fn test_jwt_algorithm_must_match_key_and_allow_list() {
+    let token = Jwt::sign(
+        &json!({ "iss": "did:example:issuer" }),
+        VC_JWT_TYPE,
+        &signer(),
+    )
+    .expect("Signing failed");
+    let jwt = Jwt::decode(token.as_str()).expect("Decoding failed");
+
+    assert!(jwt
+        .verify_with_algorithms(&public_doc(), &[Algorithm::EdDSA])
+        .expect("Verification failed"));
+    let error = jwt
+        .verify_with_algorithms(&public_doc(), &[Algorithm::ES256])
+        .expect_err("Algorithm outside of the allow-list was accepted");
+    assert!(error.to_string().contains("EdDSA is not allowed"));
+
+    let (_, rest) = token.split_once('.').unwrap();
+    let header = URL_SAFE_NO_PAD.encode(json!({ "alg": "ES256", "typ": VC_JWT_TYPE }).to_string());
+    let downgraded = Jwt::decode(format!("{}.{}", header, rest).as_str()).expect("Decoding failed");
+    let typed_doc = VerificationDocument::from_hex(
+        String::from("did:example:issuer#key-1"),
+        Algorithm::EdDSA,
+        None,
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed")
+    .public_document()
+    .expect("Public document missing");
+    let error = downgraded
+        .verify(&typed_doc)
+        .expect_err("JWT with a foreign algorithm was accepted");
+    assert!(error
+        .to_string()
+        .contains("does not match the verification key"));
+}
+
+#[test]
+pub fn test_sd_jwt_disclosures_must_be_referenced_once() {
+    let (name, name_digest) = disclosure(json!(["salt-1", "name", "Alice"]));
+    let (age, _) = disclosure(json!(["salt-2", "age", 30]));
+
+    let payload = json!({ "iss": "did:example:issuer", "_sd": [name_digest] });
+    let jwt = Jwt::sign(&payload, "vc+sd-jwt", &signer()).expect("Signing failed");
+
+    let decode = |token: String| SdJwt::decode(token.as_str()).expect("Decoding failed");
+    assert!(decode(format!("{}~{}~", jwt, name)).get_claims().is_ok());
+
+    let error = decode(format!("{}~{}~{}~", jwt, name, name))
+        .get_claims()
+        .expect_err("Repeated disclosure accepted");
+    assert!(error.to_string().contains("repeated"));
+
+    let error = decode(format!("{}~{}~{}~", jwt, name, age))
+        .get_claims()
+        .expect_err("Unreferenced disclosure accepted");
+    assert!(error.to_string().contains("not referenced"));
+
+    // A digest may only be disclosed in one place
+    let payload = json!({
+        "iss": "did:example:issuer",
+        "_sd": [name_digest],
+        "nested": { "_sd": [name_digest] },
+    });
+    let jwt = Jwt::sign(&payload, "vc+sd-jwt", &signer()).expect("Signing failed");
+    assert!(decode(format!("{}~{}~", jwt, name)).get_claims().is_err());
+}
+
+#[test]
+pub fn test_sd_jwt_key_binding() {
+    let holder = VerificationDocument::generate(Algorithm::ES256).expect("Key generation failed");
+    let holder_signer =
+        InMemorySigner::new(&holder, Algorithm::ES256).expect("Signer creation failed");
+
+    let (name, name_digest) = disclosure(json!(["salt-1", "name", "Alice"]));
+    let (age, age_digest) = disclosure(json!(["salt-2", "age", 30]));
+    let payload = json!({
+        "iss": "did:example:issuer",
+        "_sd": [name_digest, age_digest],
+        "cnf": { "jwk": holder.to_public_jwk().expect("JWK encoding failed") },
+    });
+    let jwt = Jwt::sign(&payload, "vc+sd-jwt", &signer()).expect("Signing failed");
+
+    let key_binding = |presented: &str, signer: &InMemorySigner| {
+        let claims = json!({
+            "aud": "https://verifier.example.com",
+            "nonce": "n-1",
+            "iat": 1700000000,
+            "sd_hash": URL_SAFE_NO_PAD.encode(Sha256::digest(presented.as_bytes())),
+        });
+        Jwt::sign(&claims, KB_JWT_TYPE, signer).expect("Signing failed")
+    };
+    let decode = |token: String| SdJwt::decode(token.as_str()).expect("Decoding failed");
+
+    let presented = format!("{}~{}~", jwt, name);
+    let kb_jwt = key_binding(presented.as_str(), &holder_signer);
+    let sd_jwt = decode(format!("{}{}", presented, kb_jwt));
+    assert_eq!(
+        sd_jwt.get_claims().expect("Disclosure failed")["name"],
+        "Alice"
+    );
+    assert!(sd_jwt
+        .verify_key_binding(Some("https://verifier.example.com"), Some("n-1"))
+        .is_ok());
+    assert!(sd_jwt
+        .verify_key_binding(Some("https://verifier.example.com"), Some("n-2"))
+        .is_err());
+    assert!(sd_jwt
+        .verify_key_binding(Some("https://other.example.com"), Some("n-1"))
+        .is_err());
+
+    // A bound SD-JWT without its key binding JWT
+    let error = decode(presented.clone())
+        .get_claims()
+        .expect_err("Missing key binding JWT accepted");
+    assert!(error.to_string().contains("requires a key binding JWT"));
+
+    // Signed by a key other than the one in cnf
+    let thief = VerificationDocument::generate(Algorithm::ES256).expect("Key generation failed");
+    let thief_signer =
+        InMemorySigner::new(&thief, Algorithm::ES256).expect("Signer creation failed");
+    let stolen = decode(format!(
+        "{}{}",
+        presented,
+        key_binding(presented.as_str(), &thief_signer)
+    ));
+    assert!(stolen.get_claims().is_err());
+
+    // A disclosure added after the key binding JWT was signed
+    let extended = decode(format!("{}~{}~{}~{}", jwt, name, age, kb_jwt));
+    let error = extended
+        .get_claims()
+        .expect_err("Key binding JWT over other disclosures accepted");
+    assert!(error.to_string().contains("sd_hash"));
+
+    // Without a cnf claim there is no key to check a key binding JWT against
+    let unbound = Jwt::sign(
+        &json!({ "iss": "did:example:issuer", "_sd": [name_digest] }),
+        "vc+sd-jwt",
+        &signer(),
+    )
+    .expect("Signing failed");
+    let presented = format!("{}~{}~", unbound, name);
+    let error = decode(format!(
+        "{}{}",
+        presented,
+        key_binding(presented.as_str(), &holder_signer)
+    ))
+    .get_claims()
+    .expect_err("Key binding JWT without cnf accepted");
+    assert!(error.to_string().contains("without a cnf claim"));
+}
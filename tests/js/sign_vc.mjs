@@ -0,0 +1,34 @@
+// Signs a VC and a VP through the JS bindings and prints them as JSON. The output is the
+// fixture `tests/cross_target_test.rs` verifies with the Rust API.
+//
+// cargo build --target wasm32-unknown-unknown --features wasm --release
+// wasm-bindgen --target nodejs --out-dir pkg target/wasm32-unknown-unknown/release/fi_verifiable_data.wasm
+// node tests/js/sign_vc.mjs > tests/js/signed.json
+import { createRequire } from "node:module";
+
+const require = createRequire(import.meta.url);
+const fiVerifiableData = require("../../pkg/fi_verifiable_data.js");
+
+const privateKeyBytes = Uint8Array.from(
+  Buffer.from("aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8", "hex")
+);
+const signingDocument = () =>
+  new fiVerifiableData.SigningDocument("did:example:issuer#key-1", privateKeyBytes);
+
+const vc = new fiVerifiableData.VC(
+  "id:1",
+  "did:example:issuer",
+  "Test Issuer",
+  undefined,
+  "2030-01-01T00:00:00+00:00",
+  ["https://www.w3.org/ns/credentials/v2"]
+);
+vc.setCredentialSubject({ id: "did:example:subject", degree: { type: "BachelorDegree" } });
+vc.addField("nickname", "Tester");
+vc.sign(fiVerifiableData.Algorithm.EdDSA, "assertionMethod", signingDocument(), fiVerifiableData.ProofType.FiProof);
+
+const vp = new fiVerifiableData.VP("id:2", "did:example:issuer");
+vp.addVerifiableCredential(vc);
+vp.sign(fiVerifiableData.Algorithm.EdDSA, "authentication", signingDocument(), fiVerifiableData.ProofType.FiProof);
+
+console.log(JSON.stringify({ vc: vc.toObject(), vp: vp.toObject() }, null, 2));
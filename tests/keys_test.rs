@@ -0,0 +1,174 @@
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{PublicVerificationDocument, VerificationDocument},
+    keys::KeyType,
+    proof::FiProof,
+    signer::InMemorySigner,
+    vc::VC,
+};
+use serde_json::{json, Value};
+
+const PRIVATE_KEY_HEX: &str = "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+fn sign_and_verify(
+    doc: &VerificationDocument,
+    public_doc: &PublicVerificationDocument,
+    alg: Algorithm,
+) {
+    let signer = InMemorySigner::new(doc, alg).expect("Signer creation failed");
+
+    let mut vc = VC::new(
+        String::from("id:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    if let Err(error) = vc.sign(&signer, FiProof::new(alg, String::from("ESig"))) {
+        panic!("{}", error);
+    }
+
+    let result = match vc.verify(public_doc) {
+        Ok(val) => val,
+        Err(error) => panic!("{}", error),
+    };
+    assert!(result, "{} signature did not verify", alg.to_str());
+}
+
+fn assert_round_trips(doc: &VerificationDocument, alg: Algorithm) {
+    let public_doc = doc.public_document().expect("Public document missing");
+    let id = doc.get_id().clone();
+
+    let from_pem = VerificationDocument::from_pem(
+        id.clone(),
+        doc.to_private_pem()
+            .expect("Private PEM export failed")
+            .as_str(),
+    )
+    .expect("Private PEM import failed");
+    assert_eq!(from_pem.get_public_key(), doc.get_public_key());
+    sign_and_verify(&from_pem, &public_doc, alg);
+
+    let from_jwk = VerificationDocument::from_jwk(
+        id.clone(),
+        &doc.to_private_jwk().expect("Private JWK export failed"),
+    )
+    .expect("Private JWK import failed");
+    assert_eq!(from_jwk.get_public_key(), doc.get_public_key());
+    sign_and_verify(&from_jwk, &public_doc, alg);
+
+    let from_multikey = VerificationDocument::from_multikey(
+        id.clone(),
+        doc.to_private_multikey()
+            .expect("Private multikey export failed")
+            .as_str(),
+    )
+    .expect("Private multikey import failed");
+    assert_eq!(from_multikey.get_public_key(), doc.get_public_key());
+    sign_and_verify(&from_multikey, &public_doc, alg);
+
+    let from_hex = VerificationDocument::from_hex(
+        id.clone(),
+        alg,
+        Some(doc.to_private_hex().expect("Private hex export failed")),
+        Some(doc.to_public_hex().expect("Public hex export failed")),
+    )
+    .expect("Hex import failed");
+    sign_and_verify(&from_hex, &public_doc, alg);
+
+    let public_pem = doc.to_public_pem().expect("Public PEM export failed");
+    let public_jwk = doc.to_public_jwk().expect("Public JWK export failed");
+    let public_multikey = doc
+        .to_public_multikey()
+        .expect("Public multikey export failed");
+    assert!(public_jwk.get("d").is_none());
+
+    for imported in [
+        PublicVerificationDocument::from_pem(id.clone(), public_pem.as_str()),
+        PublicVerificationDocument::from_jwk(id.clone(), &public_jwk),
+        PublicVerificationDocument::from_multikey(id.clone(), public_multikey.as_str()),
+    ] {
+        assert_eq!(imported.expect("Public key import failed"), public_doc);
+    }
+}
+
+#[test]
+pub fn test_generate_and_round_trip_every_key_type() {
+    for alg in [
+        Algorithm::EdDSA,
+        Algorithm::ES256,
+        Algorithm::ES384,
+        Algorithm::ES512,
+        Algorithm::ES256K,
+        Algorithm::PS256,
+    ] {
+        let doc = VerificationDocument::generate(alg).expect("Key generation failed");
+
+        assert_eq!(doc.key_type(), Some(KeyType::from_algorithm(alg).unwrap()));
+        assert!(doc.get_id().starts_with("did:key:z"));
+
+        assert_round_trips(&doc, alg);
+    }
+}
+
+#[test]
+pub fn test_bls12381_keys_round_trip_without_pem() {
+    let doc =
+        VerificationDocument::generate_key(KeyType::Bls12381G2).expect("Key generation failed");
+    assert_eq!(doc.key_type(), Some(KeyType::Bls12381G2));
+    assert!(doc.get_id().starts_with("did:key:zUC7"));
+
+    let multikey = doc.to_public_multikey().expect("Multikey export failed");
+    let public_doc = PublicVerificationDocument::from_multikey(String::from("doc_id"), &multikey)
+        .expect("Multikey import failed");
+    assert_eq!(public_doc.key_type(), Some(KeyType::Bls12381G2));
+
+    let jwk = doc.to_private_jwk().expect("JWK export failed");
+    assert_eq!(jwk["crv"], "Bls12381G2");
+    let imported =
+        VerificationDocument::from_jwk(String::from("doc_id"), &jwk).expect("JWK import failed");
+    assert_eq!(imported.get_public_key(), doc.get_public_key());
+
+    assert!(doc.to_private_pem().is_err());
+}
+
+#[test]
+pub fn test_generate_rejects_symmetric_algorithms() {
+    assert!(VerificationDocument::generate(Algorithm::HS256).is_err());
+}
+
+#[test]
+pub fn test_import_known_ed25519_key() {
+    let doc = VerificationDocument::from_hex(
+        String::from("did:example:issuer#key-1"),
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        None,
+    )
+    .expect("Hex import failed");
+
+    assert_eq!(doc.to_public_hex().unwrap(), PUBLIC_KEY_HEX);
+    assert_eq!(
+        doc.to_public_jwk().unwrap(),
+        json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": "e233GXWVDV6hWsCQxX1GL3PTpIZE-88sbV24OK3xNrU",
+            "kid": "did:example:issuer#key-1",
+        })
+    );
+    assert!(doc.to_public_multikey().unwrap().starts_with("z6Mk"));
+}
+
+#[test]
+pub fn test_import_rejects_mismatched_keys() {
+    let result = VerificationDocument::from_hex(
+        String::from("did:example:issuer#key-1"),
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        Some(String::from(PRIVATE_KEY_HEX)),
+    );
+
+    assert!(result.is_err());
+}
@@ -0,0 +1,260 @@
+use chrono::Duration;
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, VerificationDocument},
+    jose::Jwt,
+    oid4vci::{
+        create_key_proof, CredentialConfiguration, CredentialIssuer, CredentialIssuerMetadata,
+        CredentialOffer, CredentialRequest, TokenRequest, JWT_VC_JSON_FORMAT, LDP_VC_FORMAT,
+    },
+    signer::InMemorySigner,
+    vc::VC,
+};
+use serde_json::{json, Value};
+
+const ISSUER_URL: &str = "https://issuer.example.com";
+
+struct Holder {
+    doc: VerificationDocument,
+    signer: InMemorySigner,
+}
+
+fn new_holder() -> Holder {
+    let doc = VerificationDocument::generate(Algorithm::ES256).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::ES256).expect("Signer creation failed");
+    Holder { doc, signer }
+}
+
+fn mock_issuer(holder: &Holder) -> (CredentialIssuer, VerificationDocument) {
+    let mut metadata = CredentialIssuerMetadata::new(
+        String::from(ISSUER_URL),
+        format!("{}/credential", ISSUER_URL),
+    );
+    for (id, format) in [
+        ("UniversityDegree_jwt", JWT_VC_JSON_FORMAT),
+        ("UniversityDegree_ldp", LDP_VC_FORMAT),
+    ] {
+        metadata.add_configuration(
+            id,
+            CredentialConfiguration::new(
+                format,
+                vec![
+                    String::from("VerifiableCredential"),
+                    String::from("UniversityDegreeCredential"),
+                ],
+                vec![String::from("EdDSA")],
+                vec![String::from("ES256")],
+            ),
+        );
+    }
+
+    let issuer_doc =
+        VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let signer =
+        InMemorySigner::new(&issuer_doc, Algorithm::EdDSA).expect("Signer creation failed");
+
+    // Holder keys are did:key methods, resolved here from the holder's public document
+    let loader = DocumentLoader::new(None).expect("Loader creation failed");
+    loader.add_document(
+        holder
+            .doc
+            .public_document()
+            .expect("Public document missing"),
+    );
+
+    (
+        CredentialIssuer::new(metadata, Box::new(signer), loader),
+        issuer_doc,
+    )
+}
+
+#[test]
+pub fn test_pre_authorized_code_flow() {
+    let holder = new_holder();
+    let (mut issuer, issuer_doc) = mock_issuer(&holder);
+
+    let offer = issuer
+        .create_offer(
+            vec![
+                String::from("UniversityDegree_jwt"),
+                String::from("UniversityDegree_ldp"),
+            ],
+            json!({ "degree": { "type": "BachelorDegree" } }),
+        )
+        .expect("Offer creation failed");
+
+    // The wallet receives the offer as a deep link
+    let uri = offer.to_uri().expect("URI creation failed");
+    assert!(uri.starts_with("openid-credential-offer://?credential_offer=%7B"));
+    let offer = CredentialOffer::from_uri(uri.as_str()).expect("Offer parsing failed");
+    assert_eq!(offer.get_credential_issuer(), ISSUER_URL);
+
+    let token = issuer
+        .token(&TokenRequest::new(
+            offer.get_pre_authorized_code().expect("No code").clone(),
+        ))
+        .expect("Token request failed");
+
+    let proof = create_key_proof(&holder.signer, ISSUER_URL, token.get_c_nonce().as_deref())
+        .expect("Proof creation failed");
+    let response = issuer
+        .credential(
+            token.get_access_token(),
+            &CredentialRequest::new(String::from("UniversityDegree_jwt"), Some(proof)),
+        )
+        .expect("Credential request failed");
+
+    let public_issuer = issuer_doc
+        .public_document()
+        .expect("Public document missing");
+    let jwt = Jwt::decode(response.get_credentials()[0].as_str().expect("Not a JWT"))
+        .expect("Decoding failed");
+    assert!(jwt.verify(&public_issuer).expect("Verification failed"));
+
+    let holder_did = holder.doc.get_id().split('#').next().unwrap().to_string();
+    let subject = &jwt.get_payload()["credentialSubject"];
+    assert_eq!(subject["id"], Value::from(holder_did.as_str()));
+    assert_eq!(subject["degree"]["type"], "BachelorDegree");
+    assert_eq!(jwt.get_payload()["type"][1], "UniversityDegreeCredential");
+
+    // The next request proves possession with the nonce of the previous response
+    let proof = create_key_proof(
+        &holder.signer,
+        ISSUER_URL,
+        response.get_c_nonce().as_deref(),
+    )
+    .expect("Proof creation failed");
+    let response = issuer
+        .credential(
+            token.get_access_token(),
+            &CredentialRequest::new(String::from("UniversityDegree_ldp"), Some(proof)),
+        )
+        .expect("Credential request failed");
+
+    let vc = VC::from(response.get_credentials()[0].clone()).expect("Parsing failed");
+    assert!(vc.verify(&public_issuer).expect("Verification failed"));
+}
+
+#[test]
+pub fn test_invalid_key_proofs_are_rejected() {
+    let holder = new_holder();
+    let (mut issuer, _) = mock_issuer(&holder);
+
+    let offer = issuer
+        .create_offer(vec![String::from("UniversityDegree_jwt")], json!({}))
+        .expect("Offer creation failed");
+    let code = offer.get_pre_authorized_code().expect("No code").clone();
+    let token = issuer
+        .token(&TokenRequest::new(code.clone()))
+        .expect("Token request failed");
+
+    // The pre-authorized code is single use
+    assert!(issuer.token(&TokenRequest::new(code)).is_err());
+
+    let request =
+        |proof: String| CredentialRequest::new(String::from("UniversityDegree_jwt"), Some(proof));
+
+    let stale = create_key_proof(&holder.signer, ISSUER_URL, Some("stale")).unwrap();
+    let error = issuer
+        .credential(token.get_access_token(), &request(stale))
+        .expect_err("Stale nonce accepted");
+    assert!(error.to_string().starts_with("invalid_nonce"));
+
+    let unknown = new_holder();
+    let proof =
+        create_key_proof(&unknown.signer, ISSUER_URL, token.get_c_nonce().as_deref()).unwrap();
+    let error = issuer
+        .credential(token.get_access_token(), &request(proof))
+        .expect_err("Unknown key accepted");
+    assert!(error.to_string().starts_with("invalid_proof"));
+
+    let proof = create_key_proof(
+        &holder.signer,
+        "https://other.example.com",
+        token.get_c_nonce().as_deref(),
+    )
+    .unwrap();
+    assert!(issuer
+        .credential(token.get_access_token(), &request(proof))
+        .is_err());
+
+    // A nonce is consumed once a credential is issued with it
+    let proof =
+        create_key_proof(&holder.signer, ISSUER_URL, token.get_c_nonce().as_deref()).unwrap();
+    assert!(issuer
+        .credential(token.get_access_token(), &request(proof.clone()))
+        .is_ok());
+    assert!(issuer
+        .credential(token.get_access_token(), &request(proof))
+        .is_err());
+
+    assert!(issuer
+        .credential(
+            "unknown-token",
+            &CredentialRequest::new(String::from("UniversityDegree_jwt"), None)
+        )
+        .is_err());
+    assert!(issuer
+        .create_offer(vec![String::from("Unknown")], json!({}))
+        .is_err());
+}
+
+#[test]
+pub fn test_redeemed_and_expired_sessions_are_purged() {
+    let holder = new_holder();
+    let (mut issuer, _) = mock_issuer(&holder);
+
+    let token = |issuer: &mut CredentialIssuer| {
+        let offer = issuer
+            .create_offer(vec![String::from("UniversityDegree_jwt")], json!({}))
+            .expect("Offer creation failed");
+        issuer
+            .token(&TokenRequest::new(
+                offer.get_pre_authorized_code().expect("No code").clone(),
+            ))
+            .expect("Token request failed")
+    };
+
+    // The access token is spent once every offered credential was issued
+    let redeemed = token(&mut issuer);
+    assert_eq!(issuer.get_session_count(), 1);
+    let proof = create_key_proof(
+        &holder.signer,
+        ISSUER_URL,
+        redeemed.get_c_nonce().as_deref(),
+    )
+    .unwrap();
+    let response = issuer
+        .credential(
+            redeemed.get_access_token(),
+            &CredentialRequest::new(String::from("UniversityDegree_jwt"), Some(proof)),
+        )
+        .expect("Credential request failed");
+    assert!(response.get_c_nonce().is_none());
+    assert_eq!(issuer.get_session_count(), 0);
+
+    let proof = create_key_proof(&holder.signer, ISSUER_URL, Some("any")).unwrap();
+    let error = issuer
+        .credential(
+            redeemed.get_access_token(),
+            &CredentialRequest::new(String::from("UniversityDegree_jwt"), Some(proof)),
+        )
+        .expect_err("Redeemed access token accepted");
+    assert!(error.to_string().starts_with("invalid_token"));
+
+    // Expired sessions are dropped on the next request
+    issuer.set_access_token_lifetime(Duration::zero());
+    let expired = token(&mut issuer);
+    assert_eq!(issuer.get_session_count(), 1);
+
+    let proof =
+        create_key_proof(&holder.signer, ISSUER_URL, expired.get_c_nonce().as_deref()).unwrap();
+    let error = issuer
+        .credential(
+            expired.get_access_token(),
+            &CredentialRequest::new(String::from("UniversityDegree_jwt"), Some(proof)),
+        )
+        .expect_err("Expired access token accepted");
+    assert!(error.to_string().starts_with("invalid_token"));
+    assert_eq!(issuer.get_session_count(), 0);
+}
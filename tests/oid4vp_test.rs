@@ -0,0 +1,206 @@
+use chrono::Utc;
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, VerificationDocument},
+    enveloped::EnvelopedCredential,
+    jose::{Jwt, VP_JWT_TYPE},
+    oid4vci::JWT_VC_JSON_FORMAT,
+    oid4vp::{
+        create_response, verify_response, AuthorizationRequest, AuthorizationResponse,
+        PresentationSubmission,
+    },
+    presentation_exchange::PresentationDefinition,
+    signer::InMemorySigner,
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+const CLIENT_ID: &str = "https://verifier.example.com";
+
+struct Party {
+    doc: VerificationDocument,
+    signer: InMemorySigner,
+}
+
+fn new_party() -> Party {
+    let doc = VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed");
+    Party { doc, signer }
+}
+
+fn did(party: &Party) -> String {
+    party.doc.get_id().split('#').next().unwrap().to_string()
+}
+
+fn loader(parties: &[&Party]) -> DocumentLoader {
+    let loader = DocumentLoader::new(None).expect("Loader creation failed");
+    for party in parties {
+        loader.add_document(
+            party
+                .doc
+                .public_document()
+                .expect("Public document missing"),
+        );
+    }
+    loader
+}
+
+fn request() -> AuthorizationRequest {
+    let mut request =
+        AuthorizationRequest::new(String::from(CLIENT_ID), format!("{}/response", CLIENT_ID));
+    let definition = PresentationDefinition::from(json!({
+        "id": "degree-check",
+        "input_descriptors": [{
+            "id": "degree",
+            "constraints": {
+                "fields": [{ "path": ["$.credentialSubject.degree"] }]
+            }
+        }]
+    }))
+    .expect("Definition parsing failed");
+    request.set_presentation_definition(Some(definition));
+    request
+}
+
+fn presentation(issuer: &Party, holder: &Party) -> VP {
+    let mut vc = VC::new(
+        String::from("urn:uuid:1"),
+        Value::from(did(issuer)),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({ "id": did(holder), "degree": "BSc" }));
+    let token = vc.to_jwt(&issuer.signer).expect("Signing failed");
+
+    let mut vp = VP::new(String::from("urn:uuid:2"), Some(did(holder)));
+    vp.add_enveloped_credential(EnvelopedCredential::from_jwt(token.as_str()));
+    vp
+}
+
+fn submission() -> Option<PresentationSubmission> {
+    let mut submission = PresentationSubmission::new(String::from("degree-check"));
+    submission.add_credential("degree", JWT_VC_JSON_FORMAT, 0);
+    Some(submission)
+}
+
+#[test]
+pub fn test_vp_token_response_round_trip() {
+    let (issuer, holder) = (new_party(), new_party());
+    let request = request();
+
+    // The wallet reads the request from the verifier's QR code
+    let uri = request.to_uri().expect("URI creation failed");
+    assert!(uri.starts_with("openid4vp://?"));
+    let received = AuthorizationRequest::from_uri(uri.as_str()).expect("Request parsing failed");
+    assert_eq!(received.get_nonce(), request.get_nonce());
+    assert_eq!(
+        received.get_presentation_definition(),
+        request.get_presentation_definition()
+    );
+
+    let response = create_response(
+        &received,
+        &presentation(&issuer, &holder),
+        &holder.signer,
+        submission(),
+    )
+    .expect("Response creation failed");
+
+    let form = response.to_form().expect("Form encoding failed");
+    let posted = AuthorizationResponse::from_form(form.as_str()).expect("Form decoding failed");
+    assert_eq!(posted.get_vp_token(), response.get_vp_token());
+
+    let vp = verify_response(&request, &posted, &loader(&[&issuer, &holder]))
+        .expect("Verification failed");
+    assert_eq!(vp.get_holder(), &Some(did(&holder)));
+    assert_eq!(
+        vp.get_verifiable_credentials()[0]
+            .get_credential()
+            .expect("Decoding failed")
+            .to_object()
+            .unwrap()["credentialSubject"]["degree"],
+        "BSc"
+    );
+}
+
+#[test]
+pub fn test_response_is_bound_to_request() {
+    let (issuer, holder) = (new_party(), new_party());
+    let loader = loader(&[&issuer, &holder]);
+    let request = request();
+    let vp = presentation(&issuer, &holder);
+
+    let response = create_response(&request, &vp, &holder.signer, submission())
+        .expect("Response creation failed");
+
+    // Replayed to another request, which has its own nonce and state
+    assert!(verify_response(&self::request(), &response, &loader).is_err());
+
+    // Same nonce and state, presented to another verifier
+    let mut other_verifier = serde_json::to_value(&request).unwrap();
+    other_verifier["client_id"] = Value::from("https://other.example.com");
+    let other_verifier: AuthorizationRequest = serde_json::from_value(other_verifier).unwrap();
+    assert!(verify_response(&other_verifier, &response, &loader).is_err());
+}
+
+#[test]
+pub fn test_response_requires_holder_and_submission() {
+    let (issuer, holder) = (new_party(), new_party());
+    let loader = loader(&[&issuer, &holder]);
+    let request = request();
+    let vp = presentation(&issuer, &holder);
+
+    // Signed by the issuer rather than the holder
+    let response = create_response(&request, &vp, &issuer.signer, submission())
+        .expect("Response creation failed");
+    assert!(verify_response(&request, &response, &loader).is_err());
+
+    let response =
+        create_response(&request, &vp, &holder.signer, None).expect("Response creation failed");
+    assert!(verify_response(&request, &response, &loader).is_err());
+
+    let mut wrong_index = PresentationSubmission::new(String::from("degree-check"));
+    wrong_index.add_credential("degree", JWT_VC_JSON_FORMAT, 1);
+    let response = create_response(&request, &vp, &holder.signer, Some(wrong_index))
+        .expect("Response creation failed");
+    assert!(verify_response(&request, &response, &loader).is_err());
+}
+
+#[test]
+pub fn test_stale_response_is_rejected() {
+    let (issuer, holder) = (new_party(), new_party());
+    let loader = loader(&[&issuer, &holder]);
+    let request = request();
+
+    let response = create_response(
+        &request,
+        &presentation(&issuer, &holder),
+        &holder.signer,
+        submission(),
+    )
+    .expect("Response creation failed");
+
+    // The same response, signed ten minutes ago or ten minutes from now
+    for offset in [-600, 600] {
+        let mut payload = Jwt::decode(response.get_vp_token().as_str())
+            .expect("Decoding failed")
+            .get_payload()
+            .clone();
+        payload["iat"] = Value::from(Utc::now().timestamp() + offset);
+
+        let mut stale = serde_json::to_value(&response).unwrap();
+        stale["vp_token"] =
+            Value::from(Jwt::sign(&payload, VP_JWT_TYPE, &holder.signer).expect("Signing failed"));
+        let stale: AuthorizationResponse = serde_json::from_value(stale).unwrap();
+
+        let error = match verify_response(&request, &stale, &loader) {
+            Err(error) => error,
+            Ok(_) => panic!("Stale response accepted"),
+        };
+        assert!(error.to_string().contains("iat"));
+    }
+
+    assert!(verify_response(&request, &response, &loader).is_ok());
+}
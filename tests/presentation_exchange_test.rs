@@ -0,0 +1,226 @@
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::VerificationDocument,
+    enveloped::EnvelopedCredential,
+    oid4vci::{JWT_VC_JSON_FORMAT, LDP_VC_FORMAT},
+    oid4vp::PresentationSubmission,
+    presentation_exchange::{schema_matches, PresentationDefinition},
+    signer::InMemorySigner,
+    vc::VC,
+    vp::{PresentedCredential, VP},
+};
+use serde_json::{json, Value};
+
+const UNIVERSITY: &str = "did:example:university";
+const CITY: &str = "did:example:city";
+
+fn credential(id: &str, issuer: &str, _type: &str, subject: Value) -> VC {
+    let mut vc = VC::new(String::from(id), Value::from(issuer), None, None, None);
+    vc.add_type(String::from(_type));
+    vc.set_credential_subject(subject);
+    vc
+}
+
+fn wallet() -> Vec<PresentedCredential> {
+    let doc = VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed");
+
+    let residence = credential(
+        "urn:uuid:residence",
+        CITY,
+        "ResidenceCredential",
+        json!({ "id": "did:example:holder", "birthDate": "1990-05-01", "city": "Utrecht" }),
+    );
+    let token = residence.to_jwt(&signer).expect("Signing failed");
+
+    return vec![
+        PresentedCredential::Embedded(Box::new(credential(
+            "urn:uuid:degree",
+            UNIVERSITY,
+            "UniversityDegreeCredential",
+            json!({ "id": "did:example:holder", "degree": { "type": "BachelorDegree" } }),
+        ))),
+        PresentedCredential::Enveloped(EnvelopedCredential::from_jwt(token.as_str())),
+        PresentedCredential::Embedded(Box::new(credential(
+            "urn:uuid:library",
+            CITY,
+            "LibraryCardCredential",
+            json!({ "id": "did:example:holder", "card": "L-1234" }),
+        ))),
+    ];
+}
+
+fn presentation(credentials: Vec<PresentedCredential>) -> VP {
+    let mut vp = VP::new(
+        String::from("urn:uuid:presentation"),
+        Some(String::from("did:example:holder")),
+    );
+    for credential in credentials {
+        vp.add_presented_credential(credential);
+    }
+    vp
+}
+
+fn adult_resident() -> PresentationDefinition {
+    PresentationDefinition::from(json!({
+        "id": "adult-resident",
+        "input_descriptors": [{
+            "id": "residence",
+            "format": { "jwt_vc_json": { "alg": ["EdDSA"] } },
+            "constraints": {
+                "fields": [
+                    { "path": ["$.issuer", "$.issuer.id"], "filter": { "type": "string", "const": CITY } },
+                    {
+                        "path": ["$.credentialSubject.birthDate"],
+                        "filter": { "type": "string", "format": "date", "formatMaximum": "2007-10-19" }
+                    },
+                    { "path": ["$.credentialSubject.email"], "optional": true }
+                ]
+            }
+        }]
+    }))
+    .expect("Definition parsing failed")
+}
+
+#[test]
+pub fn test_fields_and_filters_select_credentials() {
+    let wallet = wallet();
+    let definition = adult_resident();
+
+    // The library card has the issuer but no birth date, the degree neither
+    let matches = definition
+        .match_credentials(&wallet)
+        .expect("Matching failed");
+    assert_eq!(matches, vec![(String::from("residence"), vec![1])]);
+
+    let (credentials, submission) = definition
+        .create_submission(&wallet)
+        .expect("Submission creation failed");
+    assert_eq!(credentials.len(), 1);
+    assert_eq!(submission.get_definition_id(), "adult-resident");
+    let entry = &submission.get_descriptor_map()[0];
+    assert_eq!(entry.get_id(), "residence");
+    assert_eq!(
+        entry.get_path_nested().unwrap().get_format(),
+        JWT_VC_JSON_FORMAT
+    );
+
+    let vp = presentation(credentials);
+    assert!(definition
+        .evaluate_presentation(&vp, &submission)
+        .expect("Evaluation failed"));
+
+    assert!(schema_matches(
+        &json!({ "type": "array", "contains": { "const": "BachelorDegree" } }),
+        &json!(["MasterDegree", "BachelorDegree"])
+    ));
+    assert!(!schema_matches(
+        &json!({ "type": "integer", "minimum": 18 }),
+        &json!(17)
+    ));
+    assert!(schema_matches(
+        &json!({ "type": "string", "pattern": "^L-[0-9]+$" }),
+        &json!("L-1234")
+    ));
+}
+
+#[test]
+pub fn test_submission_requirements_pick() {
+    let wallet = wallet();
+    let definition = PresentationDefinition::from(json!({
+        "id": "enrolment",
+        "submission_requirements": [
+            { "rule": "all", "from": "A" },
+            { "rule": "pick", "count": 1, "from": "B" }
+        ],
+        "input_descriptors": [
+            {
+                "id": "degree",
+                "group": ["A"],
+                "constraints": {
+                    "fields": [{
+                        "path": ["$.type"],
+                        "filter": { "type": "array", "contains": { "const": "UniversityDegreeCredential" } }
+                    }]
+                }
+            },
+            {
+                "id": "passport",
+                "group": ["B"],
+                "constraints": {
+                    "fields": [{
+                        "path": ["$.type"],
+                        "filter": { "type": "array", "contains": { "const": "PassportCredential" } }
+                    }]
+                }
+            },
+            {
+                "id": "library",
+                "group": ["B"],
+                "constraints": {
+                    "fields": [{ "path": ["$.credentialSubject.card"], "filter": { "pattern": "^L-" } }]
+                }
+            }
+        ]
+    }))
+    .expect("Definition parsing failed");
+
+    // No passport in the wallet, so the library card answers group B
+    let (credentials, submission) = definition
+        .create_submission(&wallet)
+        .expect("Submission creation failed");
+    let ids: Vec<&String> = submission
+        .get_descriptor_map()
+        .iter()
+        .map(|entry| entry.get_id())
+        .collect();
+    assert_eq!(ids, vec!["degree", "library"]);
+    assert_eq!(credentials.len(), 2);
+    assert_eq!(
+        submission.get_descriptor_map()[1]
+            .get_path_nested()
+            .unwrap()
+            .get_format(),
+        LDP_VC_FORMAT
+    );
+
+    let vp = presentation(credentials);
+    assert!(definition
+        .evaluate_presentation(&vp, &submission)
+        .expect("Evaluation failed"));
+
+    // Group A is required in full
+    assert!(definition.create_submission(&wallet[1..]).is_err());
+}
+
+#[test]
+pub fn test_presentation_not_satisfying_definition_is_rejected() {
+    let wallet = wallet();
+    let definition = adult_resident();
+
+    // The mapped credential is the degree, which does not match the descriptor
+    let vp = presentation(wallet.clone());
+    let mut submission = PresentationSubmission::new(String::from("adult-resident"));
+    submission.add_credential("residence", LDP_VC_FORMAT, 0);
+    assert!(!definition
+        .evaluate_presentation(&vp, &submission)
+        .expect("Evaluation failed"));
+
+    // Answering another definition
+    let mut submission = PresentationSubmission::new(String::from("other"));
+    submission.add_credential("residence", JWT_VC_JSON_FORMAT, 1);
+    assert!(!definition
+        .evaluate_presentation(&vp, &submission)
+        .expect("Evaluation failed"));
+
+    // Nothing mapped to the required descriptor
+    let submission = PresentationSubmission::new(String::from("adult-resident"));
+    assert!(!definition
+        .evaluate_presentation(&vp, &submission)
+        .expect("Evaluation failed"));
+
+    // Only embedded credentials, which the descriptor's format excludes
+    assert!(definition
+        .create_submission(&[wallet[0].clone(), wallet[2].clone()])
+        .is_err());
+}
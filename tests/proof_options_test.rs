@@ -0,0 +1,145 @@
+use chrono::{Duration, Utc};
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{PublicVerificationDocument, VerificationDocument},
+    proof::FiProof,
+    signer::InMemorySigner,
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+fn setup() -> (InMemorySigner, PublicVerificationDocument) {
+    let doc = VerificationDocument::generate(Algorithm::EdDSA).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::EdDSA).expect("Signer creation failed");
+    (signer, doc.public_document().unwrap())
+}
+
+fn credential() -> VC {
+    let mut vc = VC::new(
+        String::from("urn:uuid:options"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.set_credential_subject(json!({ "id": "did:example:holder" }));
+    vc
+}
+
+#[test]
+pub fn test_proof_options_are_signed() {
+    let (signer, doc) = setup();
+
+    let mut proof = FiProof::new(Algorithm::EdDSA, String::from("assertionMethod"));
+    proof.set_expires(Some(Utc::now() + Duration::hours(1)));
+    proof.set_nonce(Some(String::from("n-0S6_WzA2Mj")));
+    let mut vc = credential();
+    vc.sign(&signer, proof).expect("Signing failed");
+    assert!(vc.verify(&doc).expect("Verification failed"));
+
+    let original = vc.to_object().expect("Serialization failed");
+    assert_eq!(original["proof"]["nonce"], "n-0S6_WzA2Mj");
+
+    let tampered = [
+        ("nonce", Value::from("replayed")),
+        ("created", Value::from("2020-01-01T00:00:00+00:00")),
+        (
+            "expires",
+            Value::from((Utc::now() + Duration::days(365)).to_rfc3339()),
+        ),
+        ("proofPurpose", Value::from("authentication")),
+    ];
+    for (name, value) in tampered {
+        let mut object = original.clone();
+        object["proof"][name] = value;
+        let vc: VC = serde_json::from_value(object).expect("Deserialization failed");
+        assert!(
+            !matches!(vc.verify(&doc), Ok(true)),
+            "Changing {} kept the proof valid",
+            name
+        );
+    }
+}
+
+#[test]
+pub fn test_expired_proof_is_rejected() {
+    let (signer, doc) = setup();
+
+    let mut proof = FiProof::new(Algorithm::EdDSA, String::from("assertionMethod"));
+    proof.set_expires(Some(Utc::now() - Duration::minutes(5)));
+    let mut vc = credential();
+    vc.sign(&signer, proof).expect("Signing failed");
+
+    let error = vc.verify(&doc).expect_err("Expired proof was accepted");
+    assert!(error.to_string().contains("expired"));
+}
+
+#[test]
+pub fn test_proof_created_in_the_future_is_rejected() {
+    let (signer, doc) = setup();
+
+    let proof: FiProof = serde_json::from_value(json!({
+        "type": "FiProof",
+        "created": (Utc::now() + Duration::days(1)).to_rfc3339(),
+        "algorithm": "EdDSA",
+        "proofPurpose": "assertionMethod",
+        "jws": null
+    }))
+    .expect("Proof deserialization failed");
+    let mut vc = credential();
+    vc.sign(&signer, proof).expect("Signing failed");
+
+    let error = vc
+        .verify(&doc)
+        .expect_err("Proof from the future was accepted");
+    assert!(error.to_string().contains("created in the future"));
+}
+
+#[test]
+pub fn test_clock_skew_is_tolerated() {
+    let (signer, doc) = setup();
+
+    // The signer's clock runs a few seconds ahead of the verifier's
+    let mut proof: FiProof = serde_json::from_value(json!({
+        "type": "FiProof",
+        "created": (Utc::now() + Duration::seconds(5)).to_rfc3339(),
+        "algorithm": "EdDSA",
+        "proofPurpose": "assertionMethod",
+        "jws": null
+    }))
+    .expect("Proof deserialization failed");
+    proof.set_expires(Some(Utc::now() - Duration::seconds(5)));
+    let mut vc = credential();
+    vc.sign(&signer, proof).expect("Signing failed");
+
+    assert!(vc.verify(&doc).expect("Verification failed"));
+}
+
+#[test]
+pub fn test_expected_nonce_is_required() {
+    let (signer, doc) = setup();
+
+    let mut proof = FiProof::new(Algorithm::EdDSA, String::from("authentication"));
+    proof.set_nonce(Some(String::from("challenge-1")));
+    let mut vp = VP::new(String::from("urn:uuid:vp"), None);
+    vp.add_verifiable_credentials(credential());
+    vp.sign(&signer, proof).expect("Signing failed");
+
+    assert!(vp
+        .verify_with_nonce(&doc, "challenge-1")
+        .expect("Verification failed"));
+    let error = vp
+        .verify_with_nonce(&doc, "challenge-2")
+        .expect_err("Proof over another nonce was accepted");
+    assert!(error.to_string().contains("nonce does not match"));
+
+    let mut vc = credential();
+    vc.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("assertionMethod")),
+    )
+    .expect("Signing failed");
+    assert!(vc.verify(&doc).expect("Verification failed"));
+    assert!(vc.verify_with_nonce(&doc, "challenge-1").is_err());
+}
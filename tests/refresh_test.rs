@@ -0,0 +1,202 @@
+use chrono::{DateTime, Duration, Utc};
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, VerificationDocument},
+    error::FiError,
+    policy::StatusChecker,
+    proof::FiProof,
+    refresh::{create_refresh_request, expiring_credentials, RefreshIssuer, RefreshService},
+    signer::InMemorySigner,
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+const REFRESH_URL: &str = "https://registrar.example.com/refresh";
+
+struct Party {
+    doc: VerificationDocument,
+    signer: InMemorySigner,
+}
+
+fn new_party() -> Party {
+    let doc = VerificationDocument::generate(Algorithm::ES256).expect("Key generation failed");
+    let signer = InMemorySigner::new(&doc, Algorithm::ES256).expect("Signer creation failed");
+    Party { doc, signer }
+}
+
+fn did(party: &Party) -> String {
+    party.doc.get_id().split('#').next().unwrap().to_string()
+}
+
+fn enrolment(issuer: &Party, holder: &Party, valid_for: Duration, refreshable: bool) -> VC {
+    let mut vc = VC::new(
+        String::from("urn:uuid:enrolment"),
+        Value::from(did(issuer)),
+        None,
+        None,
+        Some(Utc::now() + valid_for),
+    );
+    vc.set_credential_subject(json!({ "id": did(holder), "programme": "Physics" }));
+    vc.set_credential_status(Some(
+        json!({ "id": "https://registrar.example.com/status#7" }),
+    ));
+    if refreshable {
+        vc.set_refresh_service(Some(
+            RefreshService::Refresh2021 {
+                url: String::from(REFRESH_URL),
+            }
+            .into(),
+        ));
+    }
+    vc.sign(
+        &issuer.signer,
+        FiProof::new(Algorithm::ES256, String::from("assertionMethod")),
+    )
+    .expect("Signing failed");
+    vc
+}
+
+fn refresh_issuer(issuer: &Party, parties: &[&Party]) -> RefreshIssuer {
+    let loader = DocumentLoader::new(None).expect("Loader creation failed");
+    for party in parties {
+        loader.add_document(party.doc.public_document().unwrap());
+    }
+    let signer = InMemorySigner::new(&issuer.doc, Algorithm::ES256).unwrap();
+    RefreshIssuer::new(Box::new(signer), loader, Duration::days(365))
+}
+
+struct Revoked;
+
+impl StatusChecker for Revoked {
+    fn check(&self, _credential_status: &Value) -> Result<bool, FiError> {
+        Ok(false)
+    }
+}
+
+#[test]
+pub fn test_wallet_refreshes_expiring_credential() {
+    let (issuer, holder) = (new_party(), new_party());
+    let wallet = vec![
+        enrolment(&issuer, &holder, Duration::days(3), true),
+        enrolment(&issuer, &holder, Duration::days(300), true),
+        enrolment(&issuer, &holder, Duration::days(3), false),
+    ];
+
+    let expiring = expiring_credentials(wallet.as_slice(), Duration::days(7));
+    assert_eq!(expiring.len(), 1);
+    let expiring = expiring[0];
+
+    // The registrar's refresh endpoint, run in process
+    let mut service = refresh_issuer(&issuer, &[&issuer, &holder]);
+    let challenge = service.create_challenge();
+    let request = create_refresh_request(expiring, &holder.signer, challenge.as_str())
+        .expect("Request failed");
+    assert_eq!(
+        request.get_proof().as_ref().unwrap().get_nonce(),
+        &Some(challenge)
+    );
+
+    let refreshed = service.refresh(&request).expect("Refresh failed");
+    assert_eq!(refreshed.get_id(), expiring.get_id());
+    assert_eq!(
+        refreshed.get_credential_subject(),
+        expiring.get_credential_subject()
+    );
+    let valid_until = refreshed.get_valid_until().as_ref().unwrap();
+    let valid_until = DateTime::parse_from_rfc3339(valid_until).unwrap();
+    assert!(valid_until > Utc::now() + Duration::days(364));
+    assert!(refreshed
+        .verify(&issuer.doc.public_document().unwrap())
+        .expect("Verification failed"));
+    assert!(expiring_credentials(&[refreshed], Duration::days(7)).is_empty());
+
+    // Challenges are single use
+    let error = service
+        .refresh(&request)
+        .err()
+        .expect("Replayed request was accepted");
+    assert!(error.to_string().contains("challenge"));
+}
+
+#[test]
+pub fn test_refresh_request_is_rejected() {
+    let (issuer, holder, thief, other_issuer) =
+        (new_party(), new_party(), new_party(), new_party());
+    let credential = enrolment(&issuer, &holder, Duration::days(1), true);
+    let mut service = refresh_issuer(&issuer, &[&issuer, &holder, &thief, &other_issuer]);
+
+    let request = create_refresh_request(&credential, &holder.signer, "made-up").unwrap();
+    assert!(service.refresh(&request).is_err());
+
+    let challenge = service.create_challenge();
+    let request = create_refresh_request(&credential, &thief.signer, challenge.as_str()).unwrap();
+    let error = service
+        .refresh(&request)
+        .err()
+        .expect("Credential of another holder was refreshed");
+    assert!(error.to_string().contains("another holder"));
+
+    let foreign = enrolment(&other_issuer, &holder, Duration::days(1), true);
+    let challenge = service.create_challenge();
+    let request = create_refresh_request(&foreign, &holder.signer, challenge.as_str()).unwrap();
+    let error = service
+        .refresh(&request)
+        .err()
+        .expect("Credential of another issuer was refreshed");
+    assert!(error.to_string().contains("not issued by this issuer"));
+
+    service.set_status_checker(Some(Box::new(Revoked)));
+    let challenge = service.create_challenge();
+    let request = create_refresh_request(&credential, &holder.signer, challenge.as_str()).unwrap();
+    assert!(service.refresh(&request).is_err());
+}
+
+#[test]
+pub fn test_failed_requests_do_not_consume_the_challenge() {
+    let (issuer, holder, thief) = (new_party(), new_party(), new_party());
+    let credential = enrolment(&issuer, &holder, Duration::days(1), true);
+    let mut service = refresh_issuer(&issuer, &[&issuer, &holder, &thief]);
+    let challenge = service.create_challenge();
+
+    let request = create_refresh_request(&credential, &holder.signer, challenge.as_str()).unwrap();
+    let mut forged = request.to_object().unwrap();
+    forged["proof"]["jws"] = Value::from("c2lnbmF0dXJl");
+    assert!(service.refresh(&VP::from(forged).unwrap()).is_err());
+
+    let stolen = create_refresh_request(&credential, &thief.signer, challenge.as_str()).unwrap();
+    assert!(service.refresh(&stolen).is_err());
+
+    // The holder's own request still goes through, once
+    assert!(service.refresh(&request).is_ok());
+    assert!(service.refresh(&request).is_err());
+}
+
+#[test]
+pub fn test_typed_refresh_services() {
+    let (issuer, holder) = (new_party(), new_party());
+    let mut vc = enrolment(&issuer, &holder, Duration::days(1), false);
+    vc.set_refresh_service(Some(json!([
+        { "type": "ManualRefreshService2018", "id": "https://registrar.example.com/renew" },
+        { "type": "VerifiableCredentialRefreshService2021", "url": REFRESH_URL },
+        { "type": "SomeOtherRefreshService", "endpoint": "https://example.com" }
+    ])));
+
+    let services = vc.get_refresh_services();
+    assert_eq!(services.len(), 2);
+    assert_eq!(services[0].get_url(), "https://registrar.example.com/renew");
+    assert_eq!(
+        services[1],
+        RefreshService::Refresh2021 {
+            url: String::from(REFRESH_URL)
+        }
+    );
+
+    vc.set_refresh_service(Some(
+        RefreshService::Manual {
+            id: String::from("https://registrar.example.com/renew"),
+        }
+        .into(),
+    ));
+    assert!(create_refresh_request(&vc, &holder.signer, "challenge").is_err());
+}
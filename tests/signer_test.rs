@@ -0,0 +1,134 @@
+use std::cell::Cell;
+
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::VerificationDocument,
+    error::FiError,
+    proof::FiProof,
+    signer::{InMemoryKeyStore, InMemorySigner, KeyStore, Signer},
+    vc::VC,
+};
+use serde_json::Value;
+
+const PRIVATE_KEY_HEX: &str = "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+/// Stand-in for an HSM/KMS adapter: the caller never sees the key, only signatures.
+struct RemoteSigner {
+    backend: InMemorySigner,
+    calls: Cell<u32>,
+}
+
+impl Signer for RemoteSigner {
+    fn sign(&self, content: &[u8]) -> Result<Vec<u8>, FiError> {
+        self.calls.set(self.calls.get() + 1);
+        self.backend.sign(content)
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        self.backend.algorithm()
+    }
+
+    fn verification_method(&self) -> &str {
+        "did:example:issuer#key-1"
+    }
+}
+
+fn eddsa_doc() -> VerificationDocument {
+    VerificationDocument::from_hex(
+        String::from("did:example:issuer#key-1"),
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed")
+}
+
+fn new_vc() -> VC {
+    VC::new(
+        String::from("id:1"),
+        Value::from("did:example:issuer"),
+        Some(Value::from("Test Issuer")),
+        None,
+        None,
+    )
+}
+
+#[test]
+pub fn test_sign_with_custom_signer() {
+    let eddsa_doc = eddsa_doc();
+    let signer = RemoteSigner {
+        backend: InMemorySigner::new(&eddsa_doc, Algorithm::EdDSA).expect("Signer creation failed"),
+        calls: Cell::new(0),
+    };
+
+    let mut vc = new_vc();
+    if let Err(error) = vc.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("ESig")),
+    ) {
+        panic!("{}", error);
+    }
+
+    assert_eq!(signer.calls.get(), 1);
+    assert_eq!(
+        vc.get_proof()
+            .as_ref()
+            .unwrap()
+            .get_verification_method()
+            .as_deref(),
+        Some("did:example:issuer#key-1")
+    );
+
+    let public_doc = eddsa_doc
+        .public_document()
+        .expect("Public document missing");
+    let result = match vc.verify(&public_doc) {
+        Ok(val) => val,
+        Err(error) => panic!("{}", error),
+    };
+    assert!(result);
+}
+
+#[test]
+pub fn test_sign_with_key_store() {
+    let eddsa_doc = eddsa_doc();
+    let mut key_store = InMemoryKeyStore::new();
+    key_store.add_signer(Box::new(
+        InMemorySigner::new(&eddsa_doc, Algorithm::EdDSA).expect("Signer creation failed"),
+    ));
+
+    assert!(key_store.get_signer("did:example:other#key-1").is_none());
+    let signer = key_store
+        .get_signer("did:example:issuer#key-1")
+        .expect("Signer not found");
+
+    let mut vc = new_vc();
+    if let Err(error) = vc.sign(signer, FiProof::new(Algorithm::EdDSA, String::from("ESig"))) {
+        panic!("{}", error);
+    }
+
+    let public_doc = eddsa_doc
+        .public_document()
+        .expect("Public document missing");
+    let result = match vc.verify(&public_doc) {
+        Ok(val) => val,
+        Err(error) => panic!("{}", error),
+    };
+    assert!(result);
+}
+
+#[test]
+pub fn test_sign_rejects_algorithm_mismatch() {
+    let signer =
+        InMemorySigner::new(&eddsa_doc(), Algorithm::EdDSA).expect("Signer creation failed");
+
+    let mut vc = new_vc();
+    let result = vc.sign(
+        &signer,
+        FiProof::new(Algorithm::ES256, String::from("ESig")),
+    );
+
+    assert!(result.is_err());
+    assert!(vc.get_proof().is_none());
+}
@@ -0,0 +1,160 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, PublicVerificationDocument, VerificationDocument},
+    proof::FiProof,
+    signer::InMemorySigner,
+    streaming::BorrowedVP,
+    vc::VC,
+    vp::VP,
+};
+use serde_json::{json, Value};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(run: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = run();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+struct Setup {
+    json: String,
+    holder: PublicVerificationDocument,
+    loader: DocumentLoader,
+}
+
+fn presentation(count: usize, alg: Algorithm) -> Setup {
+    let issuer = VerificationDocument::generate(alg).expect("Key generation failed");
+    let issuer_signer = InMemorySigner::new(&issuer, alg).expect("Signer creation failed");
+    let holder = VerificationDocument::generate(alg).expect("Key generation failed");
+    let holder_signer = InMemorySigner::new(&holder, alg).expect("Signer creation failed");
+
+    let holder_did = holder.get_id().split('#').next().unwrap();
+    let mut vp = VP::new(
+        String::from("urn:uuid:upload"),
+        Some(String::from(holder_did)),
+    );
+    for index in 0..count {
+        let mut vc = VC::new(
+            format!("urn:uuid:grade-{}", index),
+            Value::from("did:example:registrar"),
+            Some(Value::from("Caf\u{e9} \"Registrar\"\n")),
+            None,
+            None,
+        );
+        vc.set_credential_subject(json!({
+            "id": "did:example:holder",
+            "grades": [1.5, -2, 18446744073709551615u64, null, true],
+            "note": "line\tbreak \\ \u{1F393}"
+        }));
+        if index == 0 {
+            vc.bind_to_key(&holder.public_document().unwrap())
+                .expect("Binding failed");
+        }
+        vc.sign(
+            &issuer_signer,
+            FiProof::new(alg, String::from("assertionMethod")),
+        )
+        .expect("Signing failed");
+        vp.add_verifiable_credentials(vc);
+    }
+    vp.sign(
+        &holder_signer,
+        FiProof::new(alg, String::from("authentication")),
+    )
+    .expect("Signing failed");
+
+    let loader = DocumentLoader::new(None).expect("Loader creation failed");
+    loader.add_document(issuer.public_document().unwrap());
+    Setup {
+        json: vp.to_object().expect("Serialization failed").to_string(),
+        holder: holder.public_document().unwrap(),
+        loader,
+    }
+}
+
+#[test]
+pub fn test_streaming_matches_vp_verification() {
+    for alg in [Algorithm::ES256, Algorithm::EdDSA] {
+        let setup = presentation(5, alg);
+
+        let vp = VP::from(serde_json::from_str(setup.json.as_str()).unwrap()).unwrap();
+        assert!(vp.verify(&setup.holder).expect("Verification failed"));
+        assert!(vp
+            .verify_credentials(&setup.loader)
+            .expect("Verification failed"));
+
+        let borrowed = BorrowedVP::parse(setup.json.as_str()).expect("Parsing failed");
+        assert!(borrowed.verify(&setup.holder).expect("Verification failed"));
+        assert!(borrowed
+            .verify_credentials(&setup.loader)
+            .expect("Verification failed"));
+
+        // The credential bound to the holder key can not be presented by anyone else
+        let other = VerificationDocument::generate(alg).unwrap();
+        assert!(borrowed
+            .check_holder_binding(&other.public_document().unwrap())
+            .is_err());
+    }
+}
+
+#[test]
+pub fn test_tampered_presentation_fails() {
+    let setup = presentation(3, Algorithm::ES256);
+    let tampered = setup
+        .json
+        .replacen("urn:uuid:grade-2", "urn:uuid:grade-9", 1);
+
+    let borrowed = BorrowedVP::parse(tampered.as_str()).expect("Parsing failed");
+    assert!(!borrowed.verify(&setup.holder).unwrap_or(false));
+    assert!(!borrowed.verify_credentials(&setup.loader).unwrap_or(false));
+
+    let duplicated = setup.json.replacen("{", "{\"id\":\"urn:uuid:other\",", 1);
+    assert!(BorrowedVP::parse(duplicated.as_str()).is_err());
+}
+
+#[test]
+pub fn test_streaming_allocates_less() {
+    let setup = presentation(40, Algorithm::ES256);
+
+    let (verified, classic) = allocations(|| {
+        let vp = VP::from(serde_json::from_str(setup.json.as_str()).unwrap()).unwrap();
+        vp.verify(&setup.holder).unwrap() && vp.verify_credentials(&setup.loader).unwrap()
+    });
+    assert!(verified);
+
+    let (verified, streaming) = allocations(|| {
+        let vp = BorrowedVP::parse(setup.json.as_str()).unwrap();
+        vp.verify(&setup.holder).unwrap() && vp.verify_credentials(&setup.loader).unwrap()
+    });
+    assert!(verified);
+    assert!(
+        streaming * 2 < classic,
+        "{} allocations streaming, {} classic",
+        streaming,
+        classic
+    );
+}
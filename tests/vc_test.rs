@@ -1,4 +1,7 @@
-use fi_verifiable_data::{document::VerificationDocument, proof::FiProof, vc::VC};
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::VerificationDocument, proof::FiProof, signer::InMemorySigner, vc::VC,
+};
 use serde_json::Value;
 
 const PRIVATE_KEY_HEX: &'static str =
@@ -14,21 +17,21 @@ pub fn vc_basic_test() {
 
     let mut vc = VC::new(id, Value::from(issuer), Some(Value::from(name)), None, None);
 
-    let private_key_bytes = hex::decode(PRIVATE_KEY_HEX).expect("rivate key hex decode failed");
-    let public_key_bytes = hex::decode(PUBLIC_KEY_HEX).expect("Public key hex decode failed");
-
-    let mut eddsa_doc = VerificationDocument::new(
+    let eddsa_doc = VerificationDocument::from_hex(
         String::from("doc_id"),
-        Some(private_key_bytes),
-        Some(public_key_bytes),
-    );
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed");
+    let signer = InMemorySigner::new(&eddsa_doc, Algorithm::EdDSA).expect("Signer creation failed");
 
     let proof = FiProof::new(
         fi_digital_signatures::algorithms::Algorithm::EdDSA,
         String::from("ESig"),
     );
 
-    match vc.sign(&mut eddsa_doc, proof) {
+    match vc.sign(&signer, proof) {
         Err(error) => {
             eprintln!("{}", error);
             assert!(false);
@@ -36,7 +39,10 @@ pub fn vc_basic_test() {
         Ok(_) => {}
     };
 
-    let result = match vc.verify(&mut eddsa_doc) {
+    let public_doc = eddsa_doc
+        .public_document()
+        .expect("Public document missing");
+    let result = match vc.verify(&public_doc) {
         Ok(val) => val,
         Err(error) => {
             eprintln!("{}", error);
@@ -46,3 +52,50 @@ pub fn vc_basic_test() {
     };
     assert!(result);
 }
+
+#[test]
+pub fn vc_custom_fields_survive_sign_and_verify() {
+    let mut vc = VC::new(
+        String::from("id:1"),
+        Value::from("id:1#issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.add_field("nickname", Value::from("Tester"));
+
+    let eddsa_doc = VerificationDocument::from_hex(
+        String::from("doc_id"),
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed");
+    let signer = InMemorySigner::new(&eddsa_doc, Algorithm::EdDSA).expect("Signer creation failed");
+    let public_doc = eddsa_doc
+        .public_document()
+        .expect("Public document missing");
+
+    if let Err(error) = vc.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("ESig")),
+    ) {
+        panic!("{}", error);
+    }
+
+    let object = vc.to_object().expect("to_object failed");
+    assert_eq!(object["nickname"], Value::from("Tester"));
+    assert_eq!(vc.to_object().expect("to_object failed"), object);
+    assert_eq!(
+        serde_json::to_value(&vc).expect("Serialization failed"),
+        object
+    );
+
+    for _ in 0..2 {
+        assert!(vc.verify(&public_doc).expect("Verification failed"));
+    }
+
+    let parsed = VC::from(object.clone()).expect("VC parsing failed");
+    assert_eq!(parsed.to_object().expect("to_object failed"), object);
+    assert!(parsed.verify(&public_doc).expect("Verification failed"));
+}
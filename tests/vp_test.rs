@@ -1,5 +1,8 @@
-use fi_verifiable_data::{document::VerificationDocument, proof::FiProof, vc::VC, vp::VP};
-use serde_json::{error, json, Value};
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::VerificationDocument, proof::FiProof, signer::InMemorySigner, vc::VC, vp::VP,
+};
+use serde_json::{json, Value};
 
 const PRIVATE_KEY_HEX: &'static str =
     "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
@@ -29,14 +32,14 @@ pub fn vp_basic_test() {
         None,
     );
 
-    let private_key_bytes = hex::decode(PRIVATE_KEY_HEX).expect("rivate key hex decode failed");
-    let public_key_bytes = hex::decode(PUBLIC_KEY_HEX).expect("Public key hex decode failed");
-
-    let mut eddsa_doc = VerificationDocument::new(
-        String::from("doc_id"),
-        Some(private_key_bytes),
-        Some(public_key_bytes),
-    );
+    let eddsa_doc = VerificationDocument::from_hex(
+        String::from("id:#issuer"),
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed");
+    let signer = InMemorySigner::new(&eddsa_doc, Algorithm::EdDSA).expect("Signer creation failed");
 
     let proof1 = FiProof::new(
         fi_digital_signatures::algorithms::Algorithm::EdDSA,
@@ -47,14 +50,14 @@ pub fn vp_basic_test() {
         String::from("ESig"),
     );
 
-    match vc1.sign(&mut eddsa_doc, proof1) {
+    match vc1.sign(&signer, proof1) {
         Err(error) => {
             eprintln!("{}", error);
             assert!(false);
         }
         Ok(_) => {}
     };
-    match vc2.sign(&mut eddsa_doc, proof2) {
+    match vc2.sign(&signer, proof2) {
         Err(error) => {
             eprintln!("{}", error);
             assert!(false);
@@ -65,7 +68,7 @@ pub fn vp_basic_test() {
     let vp_id = String::from("id");
     let vp_issuer = String::from("id:#issuer");
 
-    let mut vp = VP::new(vp_id, Some(String::from(vp_issuer)));
+    let mut vp = VP::new(vp_id, Some(vp_issuer));
     vp.add_verifiable_credentials(vc1);
     vp.add_verifiable_credentials(vc2);
 
@@ -74,7 +77,7 @@ pub fn vp_basic_test() {
         String::from("ESig"),
     );
 
-    match vp.sign(&mut eddsa_doc, proof) {
+    match vp.sign(&signer, proof) {
         Err(error) => {
             eprintln!("{}", error);
             assert!(false);
@@ -82,7 +85,10 @@ pub fn vp_basic_test() {
         Ok(_) => {}
     };
 
-    let result = match vp.verify(&mut eddsa_doc) {
+    let public_doc = eddsa_doc
+        .public_document()
+        .expect("Public document missing");
+    let result = match vp.verify(&public_doc) {
         Ok(val) => val,
         Err(error) => {
             eprintln!("{}", error);
@@ -103,9 +109,10 @@ pub fn test_vp_from_string() {
       "proof": {
         "algorithm": "EdDSA",
         "created": "2024-08-22T08:33:13.736780200+00:00",
-        "jws": "GO0OdIKNlRqVYkbwq7C0v5KgG1woMvkwsMlXE3pkCNM1qEC-pGyt7rtViZffq8aigekUHq2or3Be5pOrDsLUCQ",
+        "jws": "HIoYUy3hXPz_qOo5vooUsJof6wvOLk0qmp20OsXOiXkRtEKeWX7i8LHvf5_CWmFp50_OJxEDgoTq5CZSflFtCQ",
         "proofPurpose": "ESig",
-        "type": "FiProof"
+        "type": "FiProof",
+        "verificationMethod": "id:#issuer"
       },
       "type": ["VerifiablePresentation"],
       "verifiableCredential": [
@@ -119,9 +126,10 @@ pub fn test_vp_from_string() {
           "proof": {
             "algorithm": "EdDSA",
             "created": "2024-08-22T08:33:13.735970700+00:00",
-            "jws": "FDdaNuqLlggEQainuOQhkiIv4hWKsVVOqLgtATa0S1zgALlPSR4_V9uU9fClw8f_oJ_vVZ1Yuf_-r4sJC7iXAg",
+            "jws": "fyOwfeQnQHCd7RhoGy217EyYVZKz9trH4KyNZWrvPE58_br8mej6xw_3hA9QL_YgIsCgUuVH8_X-iEb2P4ALDg",
             "proofPurpose": "ESig",
-            "type": "FiProof"
+            "type": "FiProof",
+            "verificationMethod": "id:#issuer"
           },
           "type": ["VerifiableCredential"],
           "validFrom": "2024-08-22T08:33:13.735475600+00:00"
@@ -136,9 +144,10 @@ pub fn test_vp_from_string() {
           "proof": {
             "algorithm": "EdDSA",
             "created": "2024-08-22T08:33:13.735980200+00:00",
-            "jws": "q-9rfghN4qu6PHh_-53fxX8N8KnMn-zz3xxSJmzMdvOfoP2ksIXvOkeElovoVfa5KW47qL7T2YAAVnZXUba3BQ",
+            "jws": "XI6tQGVsFWiJtcNUNlxxijspljAFqu8fXktBeAgQLPhqSquU6GhBsltxvC781yLBitc3V3J9x17TLr2dZzLGAw",
             "proofPurpose": "ESig",
-            "type": "FiProof"
+            "type": "FiProof",
+            "verificationMethod": "id:#issuer"
           },
           "type": ["VerifiableCredential"],
           "validFrom": "2024-08-22T08:33:13.735924500+00:00"
@@ -147,7 +156,7 @@ pub fn test_vp_from_string() {
     }
     );
 
-    let mut vp = match VP::from(json_value) {
+    let vp = match VP::from(json_value) {
         Ok(val) => val,
         Err(error) => {
             eprintln!("{}", error);
@@ -156,16 +165,18 @@ pub fn test_vp_from_string() {
         }
     };
 
-    let private_key_bytes = hex::decode(PRIVATE_KEY_HEX).expect("rivate key hex decode failed");
-    let public_key_bytes = hex::decode(PUBLIC_KEY_HEX).expect("Public key hex decode failed");
-
-    let mut eddsa_doc = VerificationDocument::new(
-        String::from("doc_id"),
-        Some(private_key_bytes),
-        Some(public_key_bytes),
-    );
-
-    let result = match vp.verify(&mut eddsa_doc) {
+    let eddsa_doc = VerificationDocument::from_hex(
+        String::from("id:#issuer"),
+        Algorithm::EdDSA,
+        Some(String::from(PRIVATE_KEY_HEX)),
+        Some(String::from(PUBLIC_KEY_HEX)),
+    )
+    .expect("Document creation failed");
+
+    let public_doc = eddsa_doc
+        .public_document()
+        .expect("Public document missing");
+    let result = match vp.verify(&public_doc) {
         Ok(val) => val,
         Err(error) => {
             eprintln!("{}", error);
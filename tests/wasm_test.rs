@@ -0,0 +1,262 @@
+//! Runs under `wasm-bindgen-test-runner`:
+//! `cargo test --target wasm32-unknown-unknown --features wasm --test wasm_test`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use fi_digital_signatures::algorithms::Algorithm;
+use fi_verifiable_data::{
+    document::{DocumentLoader, PublicVerificationDocument, SigningDocument, VerificationDocument},
+    keys::KeyType,
+    proof::{FiProof, ProofType},
+    signer::InMemorySigner,
+    vc::VC,
+    vp::VP,
+};
+use js_sys::{Function, Object, Reflect, JSON};
+use serde_json::Value;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const PRIVATE_KEY_HEX: &str = "aa7f263d0a1a671a4c06ea22800c1391dd8974174f01d0e5a848fe51bdd1bcf8";
+const PUBLIC_KEY_HEX: &str = "7b6df71975950d5ea15ac090c57d462f73d3a48644fbcf2c6d5db838adf136b5";
+
+fn signing_doc() -> SigningDocument {
+    SigningDocument::new(
+        String::from("did:example:issuer#key-1"),
+        hex::decode(PRIVATE_KEY_HEX).expect("Private key hex decode failed"),
+    )
+}
+
+fn public_doc() -> PublicVerificationDocument {
+    PublicVerificationDocument::with_key_type(
+        String::from("did:example:issuer#key-1"),
+        hex::decode(PUBLIC_KEY_HEX).expect("Public key hex decode failed"),
+        KeyType::Ed25519,
+    )
+    .expect("Invalid public key")
+}
+
+fn js_vc() -> VC {
+    let mut vc = VC::js_new(
+        String::from("id:1"),
+        JsValue::from_str("did:example:issuer"),
+        JsValue::from_str("Test Issuer"),
+        JsValue::UNDEFINED,
+        JsValue::from_str("2030-01-01T00:00:00+00:00"),
+        vec![String::from("https://www.w3.org/ns/credentials/v2")],
+    )
+    .expect("VC creation failed");
+
+    vc.js_set_credential_subject(JSON::parse(r#"{"id": "did:example:subject"}"#).unwrap())
+        .expect("Setting the subject failed");
+    vc.js_add_field("nickname", JsValue::from_str("Tester"))
+        .expect("Adding a field failed");
+    return vc;
+}
+
+/// JS value as the JSON text a JS caller would send to a Rust service.
+fn to_json(value: JsValue) -> Value {
+    let json = JSON::stringify(&value).expect("Stringify failed");
+    serde_json::from_str(json.as_string().unwrap().as_str()).expect("Invalid JSON")
+}
+
+fn from_json(value: &Value) -> JsValue {
+    JSON::parse(value.to_string().as_str()).expect("Parse failed")
+}
+
+#[wasm_bindgen_test]
+pub fn test_js_signed_vc_verifies_in_rust() {
+    let mut vc = js_vc();
+    vc.js_sign(
+        Algorithm::EdDSA,
+        String::from("assertionMethod"),
+        &signing_doc(),
+        ProofType::FiProof,
+    )
+    .expect("Signing failed");
+
+    let object = to_json(vc.js_to_object().expect("toObject failed"));
+    assert_eq!(object["validUntil"], "2030-01-01T00:00:00+00:00");
+    assert_eq!(object["nickname"], "Tester");
+
+    let rust_vc = VC::from(object).expect("VC parsing failed");
+    assert!(rust_vc.verify(&public_doc()).expect("Verification failed"));
+}
+
+#[wasm_bindgen_test]
+pub fn test_rust_signed_vc_verifies_in_js() {
+    let signer = InMemorySigner::from_signing_document(&signing_doc(), Algorithm::EdDSA)
+        .expect("Signer creation failed");
+
+    let mut vc = VC::new(
+        String::from("id:1"),
+        Value::from("did:example:issuer"),
+        None,
+        None,
+        None,
+    );
+    vc.add_field("nickname", Value::from("Tester"));
+    vc.sign(
+        &signer,
+        FiProof::new(Algorithm::EdDSA, String::from("assertionMethod")),
+    )
+    .expect("Signing failed");
+
+    let js_vc = VC::js_from(from_json(&vc.to_object().unwrap())).expect("VC parsing failed");
+    assert!(js_vc.js_verify(&public_doc()).expect("Verification failed"));
+    assert_eq!(
+        js_vc.js_get_signable_content().unwrap(),
+        vc.get_signable_content().unwrap().to_string()
+    );
+}
+
+#[wasm_bindgen_test]
+pub fn test_js_signed_vp_verifies_in_rust() {
+    let mut vp = VP::js_new(
+        String::from("id:2"),
+        Some(String::from("did:example:issuer")),
+    );
+    vp.js_add_verifiable_credential(&js_vc());
+    vp.js_sign(
+        Algorithm::EdDSA,
+        String::from("authentication"),
+        &signing_doc(),
+        ProofType::FiProof,
+    )
+    .expect("Signing failed");
+
+    let object = to_json(vp.js_to_object().expect("toObject failed"));
+    assert_eq!(object["verifiableCredential"][0]["nickname"], "Tester");
+
+    let rust_vp = VP::from(object).expect("VP parsing failed");
+    assert!(rust_vp.verify(&public_doc()).expect("Verification failed"));
+}
+
+#[wasm_bindgen_test]
+pub fn test_js_setters_write_the_credential_fields() {
+    let mut vc = js_vc();
+    vc.js_add_issuer(JsValue::from_str("did:example:co-issuer"))
+        .expect("Adding an issuer failed");
+    vc.js_set_evidence(JSON::parse(r#"[{"type": "DocumentVerification"}]"#).unwrap())
+        .expect("Setting the evidence failed");
+    vc.js_set_valid_until(Some(String::from("2031-01-01T00:00:00+00:00")));
+
+    let object = to_json(vc.js_to_object().expect("toObject failed"));
+    assert_eq!(
+        object["issuer"],
+        serde_json::json!(["did:example:issuer", "did:example:co-issuer"])
+    );
+    assert_eq!(object["evidence"][0]["type"], "DocumentVerification");
+    assert_eq!(object["validUntil"], "2031-01-01T00:00:00+00:00");
+    assert!(object.get("expire").is_none());
+}
+
+#[wasm_bindgen_test]
+pub fn test_js_sign_reports_errors() {
+    let doc = VerificationDocument::new(
+        String::from("did:example:issuer#key-1"),
+        Some(vec![1, 2, 3]),
+        None,
+    )
+    .into_signing_document()
+    .expect("Signing document missing");
+
+    let mut vc = js_vc();
+    let result = vc.js_sign(
+        Algorithm::EdDSA,
+        String::from("assertionMethod"),
+        &doc,
+        ProofType::FiProof,
+    );
+
+    assert!(result.is_err());
+    assert!(vc.js_get_proof().unwrap().is_null());
+}
+
+fn signed_js_vc() -> VC {
+    let mut vc = js_vc();
+    vc.js_sign(
+        Algorithm::EdDSA,
+        String::from("assertionMethod"),
+        &signing_doc(),
+        ProofType::FiProof,
+    )
+    .expect("Signing failed");
+    return vc;
+}
+
+#[wasm_bindgen_test]
+pub fn test_loader_is_seeded_from_a_plain_object() {
+    let docs = JSON::parse(
+        format!(
+            r#"{{"did:example:issuer#key-1": {{"publicKeyHex": "{}", "keyType": "Ed25519"}}}}"#,
+            PUBLIC_KEY_HEX
+        )
+        .as_str(),
+    )
+    .unwrap();
+    Reflect::set(
+        &docs,
+        &JsValue::from_str("did:example:other#key-1"),
+        &JsValue::from(public_doc()),
+    )
+    .unwrap();
+
+    let loader = DocumentLoader::js_new(docs).expect("Loader creation failed");
+    assert_eq!(loader.js_get_cache_size(), 2);
+    assert_eq!(
+        loader.js_get_verification_document("did:example:issuer#key-1"),
+        Some(public_doc())
+    );
+
+    assert_eq!(
+        DocumentLoader::js_new(JsValue::NULL)
+            .expect("Loader creation failed")
+            .js_get_cache_size(),
+        0
+    );
+    assert!(DocumentLoader::js_new(JsValue::from_str("did:example:issuer")).is_err());
+}
+
+#[wasm_bindgen_test]
+pub async fn test_js_resolvers_are_used_by_verify_async() {
+    let vc = signed_js_vc();
+
+    let mut loader = DocumentLoader::js_new(JsValue::UNDEFINED).expect("Loader creation failed");
+    loader.js_add_resolver(Function::new_with_args("url", "return null;"));
+    loader.js_add_resolver(Function::new_with_args(
+        "url",
+        format!(
+            r#"return url.startsWith("did:example:")
+                ? Promise.resolve({{ id: url, publicKeyHex: "{}", keyType: "Ed25519" }})
+                : undefined;"#,
+            PUBLIC_KEY_HEX
+        )
+        .as_str(),
+    ));
+
+    assert!(vc
+        .js_verify_async(&loader)
+        .await
+        .expect("Verification failed"));
+    assert!(loader
+        .js_get_verification_document_async(String::from("did:other:issuer"))
+        .await
+        .is_none());
+}
+
+#[wasm_bindgen_test]
+pub async fn test_failing_js_resolvers_find_nothing() {
+    let mut loader = DocumentLoader::js_new(Object::new().into()).expect("Loader creation failed");
+    loader.js_add_resolver(Function::new_with_args("url", "throw new Error(url);"));
+    loader.js_add_resolver(Function::new_with_args(
+        "url",
+        "return Promise.reject(new Error(url));",
+    ));
+    loader.js_add_resolver(Function::new_with_args("url", "return { id: url };"));
+
+    assert!(loader
+        .js_get_verification_document_async(String::from("did:example:issuer#key-1"))
+        .await
+        .is_none());
+    assert!(signed_js_vc().js_verify_async(&loader).await.is_err());
+}